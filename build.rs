@@ -0,0 +1,60 @@
+//! Compile the runtime to WebAssembly, so that the native binary can embed it as `WASM_BINARY`.
+//!
+//! The crate is compiled a second time by a nested cargo for `wasm32-unknown-unknown`, in its own
+//! target directory. Set `SKIP_WASM_BUILD` to skip this, in which case there is no embedded wasm
+//! runtime.
+
+use std::{env, fs, path::PathBuf, process::Command};
+
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+fn main() {
+	println!("cargo:rerun-if-changed=src");
+	println!("cargo:rerun-if-changed=macros");
+	println!("cargo:rerun-if-changed=Cargo.toml");
+	println!("cargo:rerun-if-env-changed=SKIP_WASM_BUILD");
+
+	let out_dir = PathBuf::from(env::var("OUT_DIR").expect("set by cargo"));
+	let wasm = match build_wasm(&out_dir) {
+		Ok(wasm) => wasm,
+		Err(error) => {
+			println!("cargo:warning=the wasm runtime is not embedded: {error}");
+			Vec::new()
+		},
+	};
+	fs::write(out_dir.join("runtime.wasm"), wasm).expect("OUT_DIR is writable");
+}
+
+/// Build the wasm runtime, returning its code. Returns an empty code when it should not be built.
+fn build_wasm(out_dir: &std::path::Path) -> Result<Vec<u8>, String> {
+	// We are the nested build, or the wasm runtime is not wanted.
+	if env::var("TARGET").is_ok_and(|target| target.starts_with("wasm32")) ||
+		env::var_os("SKIP_WASM_BUILD").is_some()
+	{
+		return Ok(Vec::new());
+	}
+
+	let target_dir = out_dir.join("wasm");
+	let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+	let status = Command::new(cargo)
+		.args(["rustc", "--lib", "--release", "--crate-type", "cdylib", "--target", WASM_TARGET])
+		.arg("--target-dir")
+		.arg(&target_dir)
+		.current_dir(env::var("CARGO_MANIFEST_DIR").expect("set by cargo"))
+		.env("SKIP_WASM_BUILD", "1")
+		// Don't let the settings of the outer build, e.g. clippy, leak into the nested one.
+		.env_remove("RUSTC_WRAPPER")
+		.env_remove("RUSTC_WORKSPACE_WRAPPER")
+		.env_remove("RUSTFLAGS")
+		.env_remove("CARGO_ENCODED_RUSTFLAGS")
+		.env_remove("CLIPPY_ARGS")
+		.status()
+		.map_err(|error| format!("failed to run cargo: {error}"))?;
+	if !status.success() {
+		return Err(format!("cargo failed, is the `{WASM_TARGET}` target installed?"));
+	}
+
+	let name = env::var("CARGO_PKG_NAME").expect("set by cargo").replace('-', "_");
+	let path = target_dir.join(WASM_TARGET).join("release").join(format!("{name}.wasm"));
+	fs::read(&path).map_err(|error| format!("failed to read {}: {error}", path.display()))
+}
@@ -1,5 +1,5 @@
 use super::parse::CallDef;
-use quote::quote;
+use quote::{quote, ToTokens};
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
@@ -8,6 +8,22 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
+	// This is a vector of the first argument passed to each of the functions in `fn_name`. Most
+	// functions expect the `caller`, which is only available if the origin is signed.
+	let fn_caller = methods
+		.iter()
+		.map(|method| {
+			if method.takes_origin {
+				quote!(origin)
+			} else {
+				quote!(crate::support::ensure_signed(origin)?)
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the index used to encode each of the functions in `fn_name`.
+	let fn_index = methods.iter().map(|method| method.index).collect::<Vec<_>>();
+
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
 	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
 	// first two parameters to these calls.
@@ -34,31 +50,127 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			// This variant can never be constructed. It uses `T` for pallets whose calls don't.
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<T>, core::convert::Infallible),
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
 		// appropriate function call with all arguments, including the `caller`.
 		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
+			type Caller = crate::support::Origin<T::AccountId>;
 			type Call = Call<T>;
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
+			fn dispatch(&mut self, origin: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
 							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
+								// Note that we assume the first argument of every call is the
+								// `caller`, or the `origin` itself.
+								#fn_caller,
 								#( #args_name ),*
 							)?;
 						},
 					)*
+					Call::__Ignore(_, never) => match never {},
 				}
 				Ok(())
 			}
 		}
 	};
 
+	// The type of each argument in `args_type` as it is written in the source, for the metadata.
+	let args_type_name = args_type
+		.iter()
+		.map(|types| types.iter().map(|type_| type_name(type_)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// Every argument type of every call, used to bound the `Encode` implementation below.
+	let all_args_type = args_type.iter().flatten().collect::<Vec<_>>();
+
+	// This quote block implements `Encode`, `Decode` and the call metadata for the `Call` enum. Each
+	// call is encoded as its call index followed by its arguments, in order.
+	let encode_impl = quote! {
+		impl<T: Config> crate::support::Encode for Call<T>
+		where
+			#( #all_args_type: crate::support::Encode ),*
+		{
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							dest.push(#fn_index);
+							#( crate::support::Encode::encode_to(#args_name, dest); )*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl<T: Config> crate::support::Decode for Call<T>
+		where
+			#( #all_args_type: crate::support::Decode ),*
+		{
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#fn_index => Ok(Call::#fn_name {
+							#( #args_name: crate::support::Decode::decode(input)? ),*
+						}),
+					)*
+					_ => Err("invalid call index"),
+				}
+			}
+		}
+
+		impl<T: Config> crate::support::GetCallMetadata for Call<T> {
+			fn call_metadata() -> Vec<crate::support::CallMetadata> {
+				vec![
+					#(
+						crate::support::CallMetadata {
+							index: #fn_index,
+							name: stringify!(#fn_name),
+							args: vec![
+								#(
+									crate::support::ArgMetadata {
+										name: stringify!(#args_name),
+										ty: #args_type_name,
+									}
+								),*
+							],
+						}
+					),*
+				]
+			}
+		}
+	};
+
 	// Return the generated code.
-	dispatch_impl.into()
+	quote! {
+		#dispatch_impl
+		#encode_impl
+	}
+}
+
+/// Render a type the way it would usually be written, e.g. `T::AccountId` rather than the
+/// `T :: AccountId` we get from printing its tokens.
+fn type_name(ty: &syn::Type) -> String {
+	let tokens = ty.to_token_stream().to_string();
+	let chars = tokens.chars().collect::<Vec<_>>();
+	let is_word = |c: char| c.is_alphanumeric() || c == '_';
+	let mut name = String::new();
+	for (i, c) in chars.iter().enumerate() {
+		if *c == ' ' {
+			let prev = chars[i - 1];
+			let keep = (is_word(prev) && chars.get(i + 1).is_some_and(|next| is_word(*next))) ||
+				prev == ',';
+			if !keep {
+				continue
+			}
+		}
+		name.push(*c);
+	}
+	name
 }
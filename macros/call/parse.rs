@@ -5,6 +5,7 @@ use syn::spanned::Spanned;
 mod keyword {
 	syn::custom_keyword!(T);
 	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(Origin);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
@@ -23,6 +24,12 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
+	/// The index used to encode this call. Set with `#[call_index(n)]`, otherwise it is the
+	/// position of the function among the functions of the impl block.
+	pub index: u8,
+	/// Whether the function takes the full `origin: Origin<T::AccountId>` rather than just the
+	/// `caller: T::AccountId` of a signed origin.
+	pub takes_origin: bool,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
 }
@@ -43,68 +50,143 @@ impl CallDef {
 			_ => panic!("not supported tokens"),
 		};
 
+		// Only functions are calls, other items of the impl block don't shift implicit indices.
+		let fns = item_impl
+			.items
+			.into_iter()
+			.filter_map(|item| match item {
+				syn::ImplItem::Fn(method) => Some(method),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		// Mixing explicit and implicit indices would let a new function silently take the index
+		// of an explicit one, so once a call has an explicit index, all of them must.
+		let explicit = fns.iter().any(|method| method.attrs.iter().any(is_call_index_attr));
+
 		// Here is where we will store all the callable functions.
-		let mut methods = vec![];
-		for item in item_impl.items {
-			if let syn::ImplItem::Fn(method) = item {
-				// Here is where we will store all the args for each callable functions.
-				let mut args = vec![];
-
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
-					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
-
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
-					Some(syn::FnArg::Typed(arg)) => {
-						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
-					},
-					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
-
-				let fn_name = method.sig.ident.clone();
-
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
-					// All arguments should be typed.
-					let arg = if let syn::FnArg::Typed(arg) = arg {
-						arg
-					} else {
-						unreachable!("All args should be typed.");
-					};
-
-					// Extract the name of the argument.
-					let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
-						pat.ident.clone()
-					} else {
-						let msg = "Invalid pallet::call, argument must be ident";
-						return Err(syn::Error::new(arg.pat.span(), msg))
-					};
-
-					// Store the argument name and the argument type for generating code.
-					args.push((arg_ident, arg.ty.clone()));
-				}
-
-				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+		let mut methods: Vec<CallVariantDef> = vec![];
+		for (position, method) in fns.into_iter().enumerate() {
+			// Here is where we will store all the args for each callable functions.
+			let mut args = vec![];
+
+			// First argument should be some variant of `self`.
+			match method.sig.inputs.first() {
+				Some(syn::FnArg::Receiver(_)) => {},
+				_ => {
+					let msg = "Invalid call, first argument must be a variant of self";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				},
+			}
+
+			// The second argument should be the `caller: T::AccountId` argument, or the
+			// `origin: Origin<T::AccountId>` argument for calls which aren't always signed.
+			let takes_origin = match method.sig.inputs.iter().skip(1).next() {
+				Some(syn::FnArg::Typed(arg)) if is_origin_arg(arg) => {
+					// Here we specifically check that this argument is as we expect for
+					// `origin: Origin<T::AccountId>`.
+					check_origin_arg(arg)?;
+					true
+				},
+				Some(syn::FnArg::Typed(arg)) => {
+					// Here we specifically check that this argument is as we expect for
+					// `caller: T::AccountId`.
+					check_caller_arg(arg)?;
+					false
+				},
+				_ => {
+					let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				},
+			};
+
+			let fn_name = method.sig.ident.clone();
+
+			// An explicit `#[call_index(n)]` keeps the encoding stable when functions are
+			// reordered. Without one, we fall back to the position of the function.
+			let index = match call_index(&method.attrs)? {
+				Some(index) => index,
+				None if explicit => {
+					let msg = "Invalid call, `#[call_index]` must be set on every call once it \
+						is set on one";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				},
+				None => u8::try_from(position).map_err(|_| {
+					let msg = "Invalid call, too many calls to assign an implicit call_index";
+					syn::Error::new(method.sig.span(), msg)
+				})?,
+			};
+			if let Some(other) = methods.iter().find(|m| m.index == index) {
+				let msg = format!(
+					"Invalid call_index, `{}` is already used by `{}`",
+					index, other.name
+				);
+				return Err(syn::Error::new(method.sig.span(), msg))
+			}
+
+			// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+			for arg in method.sig.inputs.iter().skip(2) {
+				// All arguments should be typed.
+				let arg = if let syn::FnArg::Typed(arg) = arg {
+					arg
+				} else {
+					unreachable!("All args should be typed.");
+				};
+
+				// Extract the name of the argument.
+				let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
+					pat.ident.clone()
+				} else {
+					let msg = "Invalid pallet::call, argument must be ident";
+					return Err(syn::Error::new(arg.pat.span(), msg))
+				};
+
+				// Store the argument name and the argument type for generating code.
+				args.push((arg_ident, arg.ty.clone()));
 			}
+
+			// Store all the function name and the arg data for the function.
+			methods.push(CallVariantDef { name: fn_name, index, takes_origin, args });
 		}
 
+		// Calls are always exposed ordered by their index.
+		methods.sort_by_key(|method| method.index);
+
 		// Return all callable functions for this pallet.
 		Ok(Self { pallet_struct, methods })
 	}
 }
 
+/// Extract the index from a `#[call_index(n)]` attribute, if there is one.
+fn call_index(attrs: &[syn::Attribute]) -> syn::Result<Option<u8>> {
+	let mut index = None;
+	for attr in attrs.iter().filter(|attr| is_call_index_attr(attr)) {
+		if index.is_some() {
+			let msg = "Invalid call, `#[call_index]` can only be set once";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		index = Some(attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()?);
+	}
+	Ok(index)
+}
+
+/// Whether `attr` is a `#[call_index(n)]` attribute.
+fn is_call_index_attr(attr: &syn::Attribute) -> bool {
+	attr.path().is_ident("call_index")
+}
+
+/// Remove the `#[call_index(n)]` attributes from the functions of the impl block.
+///
+/// These attributes only exist for this macro, so they must not end up in the final code.
+pub fn remove_call_index_attrs(item: &mut syn::Item) {
+	if let syn::Item::Impl(item_impl) = item {
+		for item in item_impl.items.iter_mut() {
+			if let syn::ImplItem::Fn(method) = item {
+				method.attrs.retain(|attr| !is_call_index_attr(attr));
+			}
+		}
+	}
+}
+
 /// Check caller arg is exactly: `caller: T::AccountId`.
 ///
 /// This is kept strict to keep the code simple.
@@ -139,3 +221,41 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 
 	Ok(())
 }
+
+/// Whether the arg is named `origin` or `_origin`, meaning it should be checked with
+/// `check_origin_arg` rather than `check_caller_arg`.
+fn is_origin_arg(arg: &syn::PatType) -> bool {
+	match &*arg.pat {
+		syn::Pat::Ident(ident) => ident.ident == "origin" || ident.ident == "_origin",
+		_ => false,
+	}
+}
+
+/// Check origin arg is exactly: `origin: Origin<T::AccountId>`.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
+	pub struct CheckDispatchableOriginArg;
+	impl syn::parse::Parse for CheckDispatchableOriginArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<keyword::Origin>()?;
+			input.parse::<syn::Token![<]>()?;
+			input.parse::<keyword::T>()?;
+			input.parse::<syn::Token![::]>()?;
+			input.parse::<keyword::AccountId>()?;
+			input.parse::<syn::Token![>]>()?;
+			Ok(Self)
+		}
+	}
+
+	// This checks the type is `Origin<T::AccountId>` with `CheckDispatchableOriginArg`
+	let ty = &arg.ty;
+	syn::parse2::<CheckDispatchableOriginArg>(ty.to_token_stream()).map_err(|e| {
+		let msg = "Invalid type for second parameter: expected `origin: Origin<T::AccountId>`";
+		let mut err = syn::Error::new(ty.span(), msg);
+		err.combine(e);
+		err
+	})?;
+
+	Ok(())
+}
@@ -1,6 +1,23 @@
 mod call;
 mod runtime;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates:
+/// - `enum Call` - an enum with a variant for each function in the annotated `impl` block, with the
+///   same arguments minus the `caller`.
+/// - implements the trait `support::Dispatch` for the pallet, routing each `Call` variant to its
+///   function.
+/// - implements `support::Encode`, `support::Decode` and `support::GetCallMetadata` for `Call`.
+///   Each call is encoded as its call index followed by its arguments.
+///
+/// The second argument of each function is either `caller: T::AccountId`, in which case the call
+/// must come from a signed origin, or `origin: Origin<T::AccountId>` to handle the origin itself.
+///
+/// The call index of a function can be fixed with `#[call_index(n)]`, so that reordering functions
+/// does not change the encoding. Functions without it use their position among the functions of
+/// the `impl` block. Once a function has an explicit index, all of them must, and using the same
+/// index twice is a compile error.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -15,16 +32,35 @@ pub fn call(
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
-///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   basic actions like incrementing the block number, checking the block to be executed has a
+///   valid block number, dropping the events of the previous block, running the migrations of
+///   every pallet after a runtime upgrade, and running the `on_initialize` hook of every pallet
+///   before the extrinsics and the `on_finalize` hook after them.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet, given
+///   the `support::Origin` of the call. Extrinsics are dispatched with a signed origin, after
+///   charging their fee with the `support::ChargeTransaction` implementation of the `Runtime` and
+///   incrementing the nonce of the user, and inherents with
+///   `Origin::None`. `RuntimeCall` also implements `support::Dispatchable`, for pallets
+///   dispatching calls. Both go through the generated `dispatch_versioned`, which uses the dispatch
+///   logic of the `support::NativeVersions` implementation of the `Runtime` for the version the
+///   block runs.
+/// - implements `support::OnRuntimeUpgrade` and `support::Hooks` for the `Runtime`, running the
+///   hooks of every pallet.
+/// - implements `support::Encode`, `support::Decode` and `support::GetRuntimeMetadata` for
+///   `RuntimeCall`. Each call is encoded as its pallet index followed by the encoded pallet call.
+/// - `enum RuntimeEvent` - an "outer"-enum of the events of the pallets marked with
+///   `#[pallet_event]`, which are the `EventOf<Runtime>` type of the pallet module, or the type
+///   given with `#[pallet_event(Type)]`. It implements `From` the event of each of these pallets,
+///   `support::Encode` and `support::Decode`. Each event is encoded as its pallet index followed
+///   by the encoded pallet event.
+///
+/// The pallet index of a field can be fixed with `#[pallet_index(n)]`, otherwise it is the position
+/// of the field in the struct. Once a field has an explicit index, all of them must, and using the
+/// same index twice is a compile error.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
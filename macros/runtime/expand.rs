@@ -5,19 +5,28 @@ use quote::quote;
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	let RuntimeDef { runtime_struct, pallets } = def;
 
-	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
-	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// This is a vector of all the pallet names, including system.
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
+	// This is a vector of all the pallet types, including system.
+	let pallet_types = pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+	// This is a vector of the index used to encode calls to each of the pallets in `pallet_names`.
+	let pallet_indices = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	// The names, event types and indices of the pallets with events.
+	let event_pallets = pallets
+		.iter()
+		.filter_map(|pallet| Some((pallet, pallet.event.as_ref()?)))
+		.collect::<Vec<_>>();
+	let event_names =
+		event_pallets.iter().map(|(pallet, _)| pallet.name.clone()).collect::<Vec<_>>();
+	let event_types = event_pallets.iter().map(|(_, event)| *event).collect::<Vec<_>>();
+	let event_indices = event_pallets.iter().map(|(pallet, _)| pallet.index).collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
 			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
-			fn new() -> Self {
+			pub fn new() -> Self {
 				Self {
-					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
 					#(
 						#pallet_names: <#pallet_types>::new()
 					),*
@@ -25,21 +34,65 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 			}
 
 			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			pub fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
 				self.system.inc_block_number();
 				if block.header.block_number != self.system.block_number() {
 					return Err(&"block number does not match what is expected")
 				}
+				// The events of the previous block are dropped, so that they don't pile up.
+				self.system.reset_events();
+				// If `system::set_code` switched the runtime version in a previous block, we migrate
+				// the stored data of every pallet before executing anything else.
+				if self.system.note_runtime_upgrade() {
+					crate::support::OnRuntimeUpgrade::on_runtime_upgrade(self);
+				}
+				let block_number = self.system.block_number();
+				crate::support::Hooks::on_initialize(self, block_number);
+				let mut signed = false;
 				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+					let Some(caller) = caller else {
+						// Inherents are checked by every node executing the block: if one fails,
+						// the block is invalid.
+						if signed {
+							return Err("inherents must come before signed extrinsics");
+						}
+						self.dispatch_versioned(crate::support::Origin::None, call)?;
+						continue;
+					};
+					signed = true;
+					// The fee and the nonce are paid even if the call fails, but the other changes
+					// of a failing call are reverted. An extrinsic whose fee can't be paid changes
+					// nothing.
+					let _res = crate::support::ChargeTransaction::charge_transaction(self, &caller)
+						.and_then(|()| {
+							self.system.inc_nonce(&caller);
+							crate::support::storage::with_transaction(|| {
+								self.dispatch_versioned(crate::support::Origin::Signed(caller), call)
+							})
+						})
+						.map_err(|e| {
+							crate::support::log(&format!(
+								"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+								block.header.block_number, i, e
+							))
+						});
+				}
+				crate::support::Hooks::on_finalize(self, block_number)
+			}
+
+			// Dispatch a call with the logic of the runtime version the current block runs, see
+			// `support::NativeVersions`.
+			pub fn dispatch_versioned(
+				&mut self,
+				origin: crate::support::Origin<<#runtime_struct as system::Config>::AccountId>,
+				call: RuntimeCall,
+			) -> crate::support::DispatchResult {
+				let spec_version = self.system.active_spec_version();
+				let versions = <Self as crate::support::NativeVersions>::DISPATCH;
+				match versions.iter().find(|(version, _)| *version == spec_version) {
+					Some((_, dispatch)) => dispatch(self, origin, call),
+					None => crate::support::Dispatch::dispatch(self, origin, call),
 				}
-				Ok(())
 			}
 		}
 	};
@@ -56,16 +109,17 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Caller = crate::support::Origin<<Runtime as system::Config>::AccountId>;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that extrinsics are dispatched with the signed origin of their `caller`, and
+			// the pallet uses that information to determine who we are executing the call on
+			// behalf of.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Caller,
 				runtime_call: Self::Call,
 			) -> crate::support::DispatchResult {
 				// This match statement will allow us to correctly route `RuntimeCall`s
@@ -73,10 +127,170 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							self.#pallet_names.dispatch(origin, call)?;
+						}
+					),*
+				}
+				Ok(())
+			}
+		}
+
+		// Calls dispatch themselves on the runtime, for pallets dispatching calls they are given.
+		// The runtime only holds handles to the storage, so any instance will do. A failing call
+		// leaves no changes behind, even if the pallet dispatching it goes on.
+		impl crate::support::Dispatchable<<#runtime_struct as system::Config>::AccountId>
+			for RuntimeCall
+		{
+			fn dispatch(
+				self,
+				origin: crate::support::Origin<<#runtime_struct as system::Config>::AccountId>,
+			) -> crate::support::DispatchResult {
+				crate::support::storage::with_transaction(|| {
+					#runtime_struct::new().dispatch_versioned(origin, self)
+				})
+			}
+		}
+	};
+
+	// This quote block implements `Encode`, `Decode` and the runtime metadata for the `RuntimeCall`
+	// enum.
+	// Each call is encoded as the pallet index followed by the encoded pallet call.
+	let encode_impl = quote! {
+		impl crate::support::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							dest.push(#pallet_indices);
+							crate::support::Encode::encode_to(call, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::support::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#pallet_indices => Ok(RuntimeCall::#pallet_names(
+							crate::support::Decode::decode(input)?
+						)),
+					)*
+					_ => Err("invalid pallet index"),
+				}
+			}
+		}
+
+		impl crate::support::GetRuntimeMetadata for RuntimeCall {
+			fn runtime_metadata() -> Vec<crate::support::PalletMetadata> {
+				vec![
+					#(
+						crate::support::PalletMetadata {
+							index: #pallet_indices,
+							name: stringify!(#pallet_names),
+							calls: <#pallet_names::Call<#runtime_struct> as
+								crate::support::GetCallMetadata>::call_metadata(),
+						}
+					),*
+				]
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum, with its conversions from the events of
+	// each pallet and its encoding. Each event is encoded as the pallet index followed by the
+	// encoded pallet event.
+	let event_impl = quote! {
+		/// The events of every pallet of the runtime, which the system pallet records.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum RuntimeEvent {
+			#( #event_names(#event_names::#event_types) ),*
+		}
+
+		#(
+			impl From<#event_names::#event_types> for RuntimeEvent {
+				fn from(event: #event_names::#event_types) -> Self {
+					RuntimeEvent::#event_names(event)
+				}
+			}
+		)*
+
+		impl crate::support::Encode for RuntimeEvent {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeEvent::#event_names(event) => {
+							dest.push(#event_indices);
+							crate::support::Encode::encode_to(event, dest);
 						}
 					),*
 				}
+			}
+		}
+
+		impl crate::support::Decode for RuntimeEvent {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#event_indices => Ok(RuntimeEvent::#event_names(
+							crate::support::Decode::decode(input)?
+						)),
+					)*
+					_ => Err("invalid pallet index"),
+				}
+			}
+		}
+	};
+
+	// This quote block runs the runtime upgrade hooks of every pallet, in the order they appear in
+	// the `Runtime` struct.
+	let upgrade_impl = quote! {
+		impl crate::support::OnRuntimeUpgrade for #runtime_struct {
+			fn on_runtime_upgrade(&mut self) {
+				#( crate::support::OnRuntimeUpgrade::on_runtime_upgrade(&mut self.#pallet_names); )*
+			}
+
+			fn on_genesis(&mut self) {
+				#( crate::support::OnRuntimeUpgrade::on_genesis(&mut self.#pallet_names); )*
+			}
+
+			// The data returned by each pallet, encoded as a `Vec<Vec<u8>>`.
+			fn pre_upgrade(&self) -> Result<Vec<u8>, &'static str> {
+				let states: Vec<Vec<u8>> = vec![
+					#( crate::support::OnRuntimeUpgrade::pre_upgrade(&self.#pallet_names)? ),*
+				];
+				Ok(crate::support::Encode::encode(&states))
+			}
+
+			fn post_upgrade(&self, state: Vec<u8>) -> crate::support::DispatchResult {
+				let states = <Vec<Vec<u8>> as crate::support::Decode>::decode_all(&state)?;
+				let mut states = states.into_iter();
+				#(
+					crate::support::OnRuntimeUpgrade::post_upgrade(
+						&self.#pallet_names,
+						states.next().ok_or("missing pre_upgrade state")?,
+					)?;
+				)*
+				Ok(())
+			}
+		}
+	};
+
+	// This quote block runs the block hooks of every pallet, in the order they appear in the
+	// `Runtime` struct.
+	let hooks_impl = quote! {
+		impl crate::support::Hooks<types::BlockNumber> for #runtime_struct {
+			fn on_initialize(&mut self, block_number: types::BlockNumber) {
+				#( crate::support::Hooks::on_initialize(&mut self.#pallet_names, block_number); )*
+			}
+
+			fn on_finalize(
+				&mut self,
+				block_number: types::BlockNumber,
+			) -> crate::support::DispatchResult {
+				#( crate::support::Hooks::on_finalize(&mut self.#pallet_names, block_number)?; )*
 				Ok(())
 			}
 		}
@@ -85,6 +299,10 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
+		#encode_impl
+		#event_impl
+		#upgrade_impl
+		#hooks_impl
 		#runtime_impl
 	}
 	.into()
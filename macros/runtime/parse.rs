@@ -5,9 +5,24 @@ use syn::spanned::Spanned;
 pub struct RuntimeDef {
 	/// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
 	pub runtime_struct: syn::Ident,
-	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
-	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	/// This is the list of pallets included in the `Runtime` struct, including `system`. During
+	/// parsing we check that system exists.
+	pub pallets: Vec<PalletDef>,
+}
+
+/// This is the metadata we keep about each pallet in the `Runtime`.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field in the `Runtime` struct.
+	pub name: syn::Ident,
+	/// The type of the pallet.
+	pub ty: syn::Type,
+	/// The index used to encode calls to this pallet. Set with `#[pallet_index(n)]` on every
+	/// field, otherwise it is the position of the field in the `Runtime` struct.
+	pub index: u8,
+	/// The event type of the pallet, relative to its module, if it has events. Set with
+	/// `#[pallet_event]` for `EventOf<Runtime>`, or `#[pallet_event(Type)]` for another type.
+	pub event: Option<syn::Type>,
 }
 
 impl RuntimeDef {
@@ -25,22 +40,97 @@ impl RuntimeDef {
 		let runtime_struct = item_struct.ident;
 
 		// Here is where we will store a list of all the pallets.
-		let mut pallets = vec![];
-		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+		let mut pallets: Vec<PalletDef> = vec![];
+		// The indices already taken.
+		let mut indices = vec![];
+		// Mixing explicit and implicit indices would let a new field silently take the index of
+		// an explicit one, so once a pallet has an explicit index, all of them must.
+		let explicit = item_struct
+			.fields
+			.iter()
+			.any(|field| field.attrs.iter().any(|attr| attr.path().is_ident("pallet_index")));
+		for (position, field) in item_struct.fields.into_iter().enumerate() {
+			let index = match pallet_index(&field.attrs)? {
+				Some(index) => index,
+				None if explicit => {
+					let msg = "Invalid runtime, `#[pallet_index]` must be set on every pallet once \
+						it is set on one";
+					return Err(syn::Error::new(field.span(), msg))
+				},
+				None => u8::try_from(position).map_err(|_| {
+					let msg = "Invalid runtime, too many pallets to assign an implicit pallet_index";
+					syn::Error::new(field.span(), msg)
+				})?,
+			};
+			if indices.contains(&index) {
+				let msg = format!("Invalid pallet_index, `{}` is already used", index);
+				return Err(syn::Error::new(field.span(), msg))
+			}
+			indices.push(index);
+			let event = pallet_event(&field.attrs, &runtime_struct)?;
+
+			if let Some(name) = field.ident {
+				pallets.push(PalletDef { name, ty: field.ty, index, event })
 			}
 		}
 
+		// Pallets are always exposed ordered by their index.
+		pallets.sort_by_key(|pallet| pallet.index);
+
 		Ok(Self { runtime_struct, pallets })
 	}
 }
 
+/// Extract the index from a `#[pallet_index(n)]` attribute, if there is one.
+fn pallet_index(attrs: &[syn::Attribute]) -> syn::Result<Option<u8>> {
+	let mut index = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("pallet_index")) {
+		if index.is_some() {
+			let msg = "Invalid runtime, `#[pallet_index]` can only be set once";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		index = Some(attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()?);
+	}
+	Ok(index)
+}
+
+/// Extract the event type from a `#[pallet_event]` attribute, if there is one.
+fn pallet_event(
+	attrs: &[syn::Attribute],
+	runtime_struct: &syn::Ident,
+) -> syn::Result<Option<syn::Type>> {
+	let mut event = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("pallet_event")) {
+		if event.is_some() {
+			let msg = "Invalid runtime, `#[pallet_event]` can only be set once";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		event = Some(match &attr.meta {
+			syn::Meta::Path(_) => syn::parse_quote!(EventOf<#runtime_struct>),
+			_ => attr.parse_args::<syn::Type>()?,
+		});
+	}
+	Ok(event)
+}
+
+/// Remove the `#[pallet_index(n)]` and `#[pallet_event]` attributes from the fields of the
+/// `Runtime` struct.
+///
+/// These attributes only exist for this macro, so they must not end up in the final code.
+pub fn remove_pallet_attrs(item: &mut syn::Item) {
+	if let syn::Item::Struct(item_struct) = item {
+		for field in item_struct.fields.iter_mut() {
+			field.attrs.retain(|attr| {
+				!attr.path().is_ident("pallet_index") && !attr.path().is_ident("pallet_event")
+			});
+		}
+	}
+}
+
 /// This function checks that the `system` pallet is the first pallet included in the `Runtime`
 /// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
-/// For example, we assume that the system pallet has no callable functions, and that it contains
-/// specific functions like incrementing the block number and a user's nonce.
+/// For example, we assume that the system pallet contains specific functions like incrementing the
+/// block number and a user's nonce, or noting runtime upgrades.
 ///
 /// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
 fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
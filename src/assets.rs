@@ -0,0 +1,528 @@
+use core::fmt::Debug;
+
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+
+use crate::support::{
+	storage::StorageMap, Decode, DispatchResult, Encode, Hooks, OnRuntimeUpgrade,
+};
+
+pub trait Config: crate::system::Config {
+	/// The type identifying an asset.
+	type AssetId: Ord + Copy + Encode + Decode + Debug;
+	/// The type used to store amounts of an asset.
+	type Balance: CheckedAdd + CheckedSub + Copy + Zero + PartialOrd + Encode + Decode + Debug;
+	/// The maximum length of the name and symbol of an asset, in bytes.
+	const STRING_LIMIT: u32;
+}
+
+/// The key of what an account holds of an asset: `(asset, account)`.
+type AccountKey<T> = (<T as Config>::AssetId, <T as crate::system::Config>::AccountId);
+
+/// The key of an approval: `(asset, owner, delegate)`.
+type ApprovalKey<T> = (
+	<T as Config>::AssetId,
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::AccountId,
+);
+
+/// What is stored about an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which created the asset. It mints, burns and freezes it, and can destroy it.
+	pub issuer: AccountId,
+	/// The amount of the asset held by all accounts.
+	pub supply: Balance,
+}
+
+/// The metadata of an asset, set when it is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetMetadata {
+	pub name: String,
+	pub symbol: String,
+	/// The number of decimals used to display amounts of the asset.
+	pub decimals: u8,
+}
+
+/// What an account holds of an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetAccount<Balance> {
+	pub balance: Balance,
+	/// A frozen account can receive the asset, but not send it.
+	pub is_frozen: bool,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for AssetDetails<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.issuer.encode_to(dest);
+		self.supply.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for AssetDetails<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { issuer: Decode::decode(input)?, supply: Decode::decode(input)? })
+	}
+}
+
+impl Encode for AssetMetadata {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.name.encode_to(dest);
+		self.symbol.encode_to(dest);
+		self.decimals.encode_to(dest);
+	}
+}
+
+impl Decode for AssetMetadata {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			name: Decode::decode(input)?,
+			symbol: Decode::decode(input)?,
+			decimals: Decode::decode(input)?,
+		})
+	}
+}
+
+impl<Balance: Encode> Encode for AssetAccount<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.balance.encode_to(dest);
+		self.is_frozen.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for AssetAccount<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { balance: Decode::decode(input)?, is_frozen: Decode::decode(input)? })
+	}
+}
+
+/// This is the Assets Module.
+/// It lets accounts create their own fungible tokens, next to the native currency of `balances`.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The details of every asset which was created.
+	assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::Balance>>,
+	// The metadata of every asset which was created.
+	metadata: StorageMap<T::AssetId, AssetMetadata>,
+	// What each account holds of each asset. Accounts holding nothing are not stored, unless they
+	// are frozen.
+	accounts: StorageMap<AccountKey<T>, AssetAccount<T::Balance>>,
+	// The amount of an asset an owner allows a delegate to transfer.
+	approvals: StorageMap<ApprovalKey<T>, T::Balance>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the assets module.
+	pub fn new() -> Self {
+		Self {
+			assets: StorageMap::new("Assets", "Asset"),
+			metadata: StorageMap::new("Assets", "Metadata"),
+			accounts: StorageMap::new("Assets", "Account"),
+			approvals: StorageMap::new("Assets", "Approvals"),
+		}
+	}
+
+	/// Get the details of the asset `id`, if it exists.
+	pub fn asset(&self, id: T::AssetId) -> Option<AssetDetails<T::AccountId, T::Balance>> {
+		self.assets.get(&id)
+	}
+
+	/// Get the metadata of the asset `id`, if it exists.
+	pub fn metadata(&self, id: T::AssetId) -> Option<AssetMetadata> {
+		self.metadata.get(&id)
+	}
+
+	/// Get the balance of the asset `id` held by `who`.
+	pub fn balance(&self, id: T::AssetId, who: &T::AccountId) -> T::Balance {
+		self.account(id, who).balance
+	}
+
+	/// Get the amount of the asset `id` which `delegate` may transfer on behalf of `owner`.
+	pub fn allowance(
+		&self,
+		id: T::AssetId,
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+	) -> T::Balance {
+		self.approvals.get(&(id, owner.clone(), delegate.clone())).unwrap_or_else(T::Balance::zero)
+	}
+
+	fn account(&self, id: T::AssetId, who: &T::AccountId) -> AssetAccount<T::Balance> {
+		self.accounts
+			.get(&(id, who.clone()))
+			.unwrap_or(AssetAccount { balance: T::Balance::zero(), is_frozen: false })
+	}
+
+	fn write_account(
+		&mut self,
+		id: T::AssetId,
+		who: &T::AccountId,
+		account: AssetAccount<T::Balance>,
+	) {
+		if account.balance.is_zero() && !account.is_frozen {
+			self.accounts.remove(&(id, who.clone()));
+		} else {
+			self.accounts.insert((id, who.clone()), account);
+		}
+	}
+
+	/// Get the details of the asset `id`, ensuring `who` is its issuer.
+	fn ensure_issuer(
+		&self,
+		id: T::AssetId,
+		who: &T::AccountId,
+	) -> Result<AssetDetails<T::AccountId, T::Balance>, &'static str> {
+		let details = self.asset(id).ok_or("asset does not exist")?;
+		if details.issuer != *who {
+			return Err("caller is not the issuer of the asset");
+		}
+		Ok(details)
+	}
+
+	/// Transfer `amount` of the asset `id` from `from` to `to`, unless `from` is frozen.
+	fn do_transfer(
+		&mut self,
+		id: T::AssetId,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&id) {
+			return Err("asset does not exist");
+		}
+		let mut from_account = self.account(id, from);
+		if from_account.is_frozen {
+			return Err("account is frozen");
+		}
+		from_account.balance =
+			from_account.balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		// Transferring to oneself changes nothing.
+		if from == to {
+			return Ok(());
+		}
+		let mut to_account = self.account(id, to);
+		to_account.balance =
+			to_account.balance.checked_add(&amount).ok_or("Maximum amount of funds reached")?;
+
+		self.write_account(id, from, from_account);
+		self.write_account(id, to, to_account);
+		Ok(())
+	}
+
+	/// Freeze or thaw the account `who`.
+	fn set_frozen(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+		is_frozen: bool,
+	) -> DispatchResult {
+		self.ensure_issuer(id, &caller)?;
+		let mut account = self.account(id, &who);
+		account.is_frozen = is_frozen;
+		self.write_account(id, &who, account);
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create the asset `id`, with `caller` as its issuer and no supply.
+	#[call_index(0)]
+	pub fn create(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		name: String,
+		symbol: String,
+		decimals: u8,
+	) -> DispatchResult {
+		if self.assets.contains_key(&id) {
+			return Err("asset already exists");
+		}
+		if name.len() > T::STRING_LIMIT as usize || symbol.len() > T::STRING_LIMIT as usize {
+			return Err("asset name or symbol is too long");
+		}
+		self.assets.insert(id, AssetDetails { issuer: caller, supply: T::Balance::zero() });
+		self.metadata.insert(id, AssetMetadata { name, symbol, decimals });
+		Ok(())
+	}
+
+	/// Create `amount` of the asset `id` in the account `beneficiary`.
+	/// This function can only be called by the issuer of the asset.
+	#[call_index(1)]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let mut details = self.ensure_issuer(id, &caller)?;
+		let mut account = self.account(id, &beneficiary);
+		details.supply = details.supply.checked_add(&amount).ok_or("Maximum supply reached")?;
+		account.balance =
+			account.balance.checked_add(&amount).ok_or("Maximum amount of funds reached")?;
+		self.assets.insert(id, details);
+		self.write_account(id, &beneficiary, account);
+		Ok(())
+	}
+
+	/// Destroy `amount` of the asset `id` held by `who`, even if `who` is frozen.
+	/// This function can only be called by the issuer of the asset.
+	#[call_index(2)]
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let mut details = self.ensure_issuer(id, &caller)?;
+		let mut account = self.account(id, &who);
+		account.balance = account.balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		details.supply = details.supply.checked_sub(&amount).ok_or("Not enough funds.")?;
+		self.assets.insert(id, details);
+		self.write_account(id, &who, account);
+		Ok(())
+	}
+
+	/// Transfer `amount` of the asset `id` from `caller` to `to`.
+	/// Frozen accounts can't transfer.
+	#[call_index(3)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.do_transfer(id, &caller, &to, amount)
+	}
+
+	/// Allow `delegate` to transfer `amount` more of the asset `id` on behalf of `caller`.
+	#[call_index(4)]
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		delegate: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&id) {
+			return Err("asset does not exist");
+		}
+		let approved = self
+			.allowance(id, &caller, &delegate)
+			.checked_add(&amount)
+			.ok_or("Maximum amount of funds reached")?;
+		self.approvals.insert((id, caller, delegate), approved);
+		Ok(())
+	}
+
+	/// Transfer `amount` of the asset `id` from `owner` to `to`, using the approval `owner` gave
+	/// to `caller`.
+	#[call_index(5)]
+	pub fn transfer_approved(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		owner: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let approved = self
+			.allowance(id, &owner, &caller)
+			.checked_sub(&amount)
+			.ok_or("transfer is not approved")?;
+		self.do_transfer(id, &owner, &to, amount)?;
+		let key = (id, owner, caller);
+		if approved.is_zero() {
+			self.approvals.remove(&key);
+		} else {
+			self.approvals.insert(key, approved);
+		}
+		Ok(())
+	}
+
+	/// Prevent `who` from transferring the asset `id`.
+	/// This function can only be called by the issuer of the asset.
+	#[call_index(6)]
+	pub fn freeze(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.set_frozen(caller, id, who, true)
+	}
+
+	/// Allow a frozen `who` to transfer the asset `id` again.
+	/// This function can only be called by the issuer of the asset.
+	#[call_index(7)]
+	pub fn thaw(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.set_frozen(caller, id, who, false)
+	}
+
+	/// Destroy the asset `id`, along with all the accounts holding it and their approvals.
+	/// This function can only be called by the issuer of the asset.
+	#[call_index(8)]
+	pub fn destroy(&mut self, caller: T::AccountId, id: T::AssetId) -> DispatchResult {
+		self.ensure_issuer(id, &caller)?;
+		self.assets.remove(&id);
+		self.metadata.remove(&id);
+		let accounts = self.accounts.iter().map(|(key, _)| key).filter(|(asset, _)| *asset == id);
+		for key in accounts.collect::<Vec<_>>() {
+			self.accounts.remove(&key);
+		}
+		let approvals = self.approvals.iter().map(|(key, _)| key).filter(|key| key.0 == id);
+		for key in approvals.collect::<Vec<_>>() {
+			self.approvals.remove(&key);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{AssetMetadata, Config, Pallet};
+	use crate::support::RuntimeVersion;
+	use crate::system;
+
+	struct TestConfig;
+	impl Config for TestConfig {
+		type AssetId = u32;
+		type Balance = u128;
+		const STRING_LIMIT: u32 = 8;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	fn accounts() -> (String, String, String) {
+		("alice".to_string(), "bob".to_string(), "charlie".to_string())
+	}
+
+	/// Create the asset 1 issued by alice, who holds 100 of it.
+	fn new_asset() -> Pallet<TestConfig> {
+		let mut assets = Pallet::<TestConfig>::new();
+		let (alice, ..) = accounts();
+		assert_eq!(assets.create(alice.clone(), 1, "Token".into(), "TKN".into(), 12), Ok(()));
+		assert_eq!(assets.mint(alice.clone(), 1, alice, 100), Ok(()));
+		assets
+	}
+
+	#[test]
+	fn create_mint_burn() {
+		let mut assets = new_asset();
+		let (alice, bob, _) = accounts();
+
+		assert_eq!(
+			assets.create(bob.clone(), 1, "Other".into(), "OTH".into(), 0),
+			Err("asset already exists")
+		);
+		assert_eq!(
+			assets.create(bob.clone(), 2, "Long name".into(), "LONG".into(), 0),
+			Err("asset name or symbol is too long")
+		);
+		assert_eq!(
+			assets.metadata(1),
+			Some(AssetMetadata { name: "Token".into(), symbol: "TKN".into(), decimals: 12 })
+		);
+
+		assert_eq!(
+			assets.mint(bob.clone(), 1, bob.clone(), 10),
+			Err("caller is not the issuer of the asset")
+		);
+		assert_eq!(assets.mint(alice.clone(), 2, bob.clone(), 10), Err("asset does not exist"));
+		assert_eq!(assets.mint(alice.clone(), 1, bob.clone(), 10), Ok(()));
+		assert_eq!(assets.asset(1).unwrap().supply, 110);
+
+		assert_eq!(assets.burn(alice.clone(), 1, bob.clone(), 11), Err("Not enough funds."));
+		assert_eq!(assets.burn(alice.clone(), 1, bob.clone(), 10), Ok(()));
+		assert_eq!(assets.balance(1, &bob), 0);
+		assert!(!assets.accounts.contains_key(&(1, bob)));
+		assert_eq!(assets.asset(1).unwrap().supply, 100);
+	}
+
+	#[test]
+	fn transfers() {
+		let mut assets = new_asset();
+		let (alice, bob, charlie) = accounts();
+
+		assert_eq!(assets.transfer(alice.clone(), 1, bob.clone(), 101), Err("Not enough funds."));
+		assert_eq!(assets.transfer(alice.clone(), 2, bob.clone(), 1), Err("asset does not exist"));
+		assert_eq!(assets.transfer(alice.clone(), 1, bob.clone(), 60), Ok(()));
+		assert_eq!((assets.balance(1, &alice), assets.balance(1, &bob)), (40, 60));
+
+		// Charlie may transfer 30 of bob's funds, in several times.
+		assert_eq!(assets.approve_transfer(bob.clone(), 1, charlie.clone(), 20), Ok(()));
+		assert_eq!(assets.approve_transfer(bob.clone(), 1, charlie.clone(), 10), Ok(()));
+		assert_eq!(
+			assets.transfer_approved(charlie.clone(), 1, bob.clone(), charlie.clone(), 31),
+			Err("transfer is not approved")
+		);
+		assert_eq!(
+			assets.transfer_approved(charlie.clone(), 1, bob.clone(), charlie.clone(), 25),
+			Ok(())
+		);
+		assert_eq!(assets.allowance(1, &bob, &charlie), 5);
+		assert_eq!(
+			assets.transfer_approved(charlie.clone(), 1, bob.clone(), alice.clone(), 5),
+			Ok(())
+		);
+		assert!(assets.approvals.is_empty());
+		assert_eq!(assets.balance(1, &alice), 45);
+		assert_eq!(assets.balance(1, &bob), 30);
+		assert_eq!(assets.balance(1, &charlie), 25);
+	}
+
+	#[test]
+	fn freeze_and_destroy() {
+		let mut assets = new_asset();
+		let (alice, bob, charlie) = accounts();
+
+		assert_eq!(
+			assets.freeze(bob.clone(), 1, alice.clone()),
+			Err("caller is not the issuer of the asset")
+		);
+		assert_eq!(assets.freeze(alice.clone(), 1, bob.clone()), Ok(()));
+		// Frozen accounts can receive funds, but not send them.
+		assert_eq!(assets.transfer(alice.clone(), 1, bob.clone(), 50), Ok(()));
+		assert_eq!(assets.transfer(bob.clone(), 1, alice.clone(), 10), Err("account is frozen"));
+		assert_eq!(assets.approve_transfer(bob.clone(), 1, charlie.clone(), 10), Ok(()));
+		assert_eq!(
+			assets.transfer_approved(charlie.clone(), 1, bob.clone(), charlie.clone(), 10),
+			Err("account is frozen")
+		);
+		assert_eq!(assets.thaw(alice.clone(), 1, bob.clone()), Ok(()));
+		assert_eq!(assets.transfer(bob.clone(), 1, alice.clone(), 10), Ok(()));
+
+		assert_eq!(assets.destroy(bob.clone(), 1), Err("caller is not the issuer of the asset"));
+		assert_eq!(assets.destroy(alice.clone(), 1), Ok(()));
+		assert!(assets.assets.is_empty());
+		assert!(assets.metadata.is_empty());
+		assert!(assets.accounts.is_empty());
+		assert!(assets.approvals.is_empty());
+		assert_eq!(assets.transfer(alice, 1, bob, 10), Err("asset does not exist"));
+	}
+}
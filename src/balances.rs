@@ -1,10 +1,151 @@
-use std::collections::BTreeMap;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
+use core::fmt::Debug;
 
-use crate::support::DispatchResult;
+use num::traits::{CheckedAdd, CheckedSub, Saturating, Zero};
+
+use crate::support::{
+	ensure_root,
+	storage::{StorageMap, StorageValue},
+	Currency, Decode, DispatchResult, Encode, Hooks, LockableCurrency, OnRuntimeUpgrade, Origin,
+	ReservableCurrency,
+};
+
+pub use crate::support::{BalanceStatus, LockIdentifier, Reasons};
 
 pub trait Config: crate::system::Config {
-	type Balance: CheckedAdd + CheckedSub + Copy + Zero;
+	type Balance: CheckedAdd
+		+ CheckedSub
+		+ Saturating
+		+ Copy
+		+ Zero
+		+ PartialOrd
+		+ Encode
+		+ Decode
+		+ Debug;
+	/// The minimum balance an account must have to exist, reserved balance included. An account
+	/// left with less is reaped: what is left of its balances is lost, and it is removed from
+	/// storage along with its locks and nonce.
+	const EXISTENTIAL_DEPOSIT: Self::Balance;
+}
+
+/// The version of the layout of the data stored by this pallet.
+/// - 0: every account ever touched is stored, even with a zero balance.
+/// - 1: accounts with a zero balance are not stored.
+/// - 2: the total issuance is stored.
+pub const STORAGE_VERSION: u16 = 2;
+
+/// A named lock on the balance of an account: `amount` of it can't be used for `reasons`. The
+/// funds stay in the balance, and several locks overlap instead of adding up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceLock<Balance> {
+	pub id: LockIdentifier,
+	pub amount: Balance,
+	pub reasons: Reasons,
+}
+
+impl<Balance: Encode> Encode for BalanceLock<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.id.encode_to(dest);
+		self.amount.encode_to(dest);
+		self.reasons.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for BalanceLock<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			id: Decode::decode(input)?,
+			amount: Decode::decode(input)?,
+			reasons: Decode::decode(input)?,
+		})
+	}
+}
+
+/// Funds added to an account without being issued. They are issued when this is dropped, unless
+/// it is offset by a `NegativeImbalance` first.
+#[must_use = "dropping an imbalance changes the total issuance"]
+pub struct PositiveImbalance<T: Config>(T::Balance);
+
+/// Funds removed from an account without being burnt. They are burnt when this is dropped, unless
+/// they are given to an account with `resolve_creating`, or offset by a `PositiveImbalance` first.
+#[must_use = "dropping an imbalance changes the total issuance"]
+pub struct NegativeImbalance<T: Config>(T::Balance);
+
+impl<T: Config> PositiveImbalance<T> {
+	/// The amount of funds.
+	pub fn peek(&self) -> T::Balance {
+		self.0
+	}
+
+	/// Take the funds out, leaving nothing to issue when `self` is dropped.
+	fn take(mut self) -> T::Balance {
+		core::mem::replace(&mut self.0, T::Balance::zero())
+	}
+
+	/// Merge two imbalances.
+	pub fn merge(self, other: Self) -> Self {
+		Self(self.take().saturating_add(other.take()))
+	}
+
+	/// Offset `self` with funds removed from other accounts. What is left over is returned.
+	pub fn offset(self, other: NegativeImbalance<T>) -> Result<Self, NegativeImbalance<T>> {
+		let (positive, negative) = (self.take(), other.take());
+		if positive >= negative {
+			Ok(Self(positive.saturating_sub(negative)))
+		} else {
+			Err(NegativeImbalance(negative.saturating_sub(positive)))
+		}
+	}
+}
+
+impl<T: Config> Drop for PositiveImbalance<T> {
+	fn drop(&mut self) {
+		if !self.0.is_zero() {
+			let pallet = Pallet::<T>::new();
+			pallet.total_issuance.put(pallet.total_issuance().saturating_add(self.0));
+		}
+	}
+}
+
+impl<T: Config> NegativeImbalance<T> {
+	/// The amount of funds.
+	pub fn peek(&self) -> T::Balance {
+		self.0
+	}
+
+	/// Take the funds out, leaving nothing to burn when `self` is dropped.
+	fn take(mut self) -> T::Balance {
+		core::mem::replace(&mut self.0, T::Balance::zero())
+	}
+
+	/// Merge two imbalances.
+	pub fn merge(self, other: Self) -> Self {
+		Self(self.take().saturating_add(other.take()))
+	}
+
+	/// Split off `amount` of the funds, or all of them if there is less. Returns the funds split
+	/// off, and the rest.
+	pub fn split(self, amount: T::Balance) -> (Self, Self) {
+		let funds = self.take();
+		let first = if amount < funds { amount } else { funds };
+		(Self(first), Self(funds.saturating_sub(first)))
+	}
+
+	/// Offset `self` with funds added to other accounts. What is left over is returned.
+	pub fn offset(self, other: PositiveImbalance<T>) -> Result<Self, PositiveImbalance<T>> {
+		match other.offset(self) {
+			Ok(positive) => Err(positive),
+			Err(negative) => Ok(negative),
+		}
+	}
+}
+
+impl<T: Config> Drop for NegativeImbalance<T> {
+	fn drop(&mut self) {
+		if !self.0.is_zero() {
+			let pallet = Pallet::<T>::new();
+			pallet.total_issuance.put(pallet.total_issuance().saturating_sub(self.0));
+		}
+	}
 }
 
 /// This is the Balances Module.
@@ -13,25 +154,497 @@ pub trait Config: crate::system::Config {
 #[derive(Debug)]
 pub struct Pallet<T:Config> {
     // A simple storage mapping from accounts (`AccountID`) to their balances (`Balance`).
-	balances: BTreeMap<T::AccountId, T::Balance>,
+	balances: StorageMap<T::AccountId, T::Balance>,
+	// The funds of each account which are set aside, e.g. as a deposit, and can't be used.
+	reserved: StorageMap<T::AccountId, T::Balance>,
+	// The locks on the balance of each account.
+	locks: StorageMap<T::AccountId, Vec<BalanceLock<T::Balance>>>,
+	// The sum of the balances of all accounts, reserved balances included.
+	total_issuance: StorageValue<T::Balance>,
+	// The version of the layout of the stored data, see `STORAGE_VERSION`.
+	storage_version: StorageValue<u16>,
 }
 
 impl<T:Config> Pallet<T>
 {
     /// Create a new instance of the balances module.
 	pub fn new() -> Self {
-		Self { balances: BTreeMap::new() }
+		Self {
+			balances: StorageMap::new("Balances", "Balances"),
+			reserved: StorageMap::new("Balances", "Reserved"),
+			locks: StorageMap::new("Balances", "Locks"),
+			total_issuance: StorageValue::new("Balances", "TotalIssuance"),
+			storage_version: StorageValue::new("Balances", "StorageVersion"),
+		}
 	}
 
 	/// Set the balance of an account `who` to some `amount`.
+	/// The total issuance changes by the same amount as the balance. A zero balance is not stored
+	/// at all.
 	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-		self.balances.insert(who.clone(), amount);
+		let old = self.balance(who);
+		let total_issuance = self.total_issuance();
+		self.total_issuance.put(if amount >= old {
+			total_issuance.saturating_add(amount.saturating_sub(old))
+		} else {
+			total_issuance.saturating_sub(old.saturating_sub(amount))
+		});
+		if amount.is_zero() {
+			self.balances.remove(who);
+		} else {
+			self.balances.insert(who.clone(), amount);
+		}
 	}
 
 	/// Get the balance of an account `who`.
 	/// If the account has no stored balance, we return zero.
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+		self.balances.get(who).unwrap_or_else(T::Balance::zero)
+	}
+
+	/// The sum of the balances of all accounts.
+	pub fn total_issuance(&self) -> T::Balance {
+		self.total_issuance.get().unwrap_or_else(T::Balance::zero)
+	}
+
+	/// Get the reserved balance of an account `who`: funds it owns, but can't use.
+	pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.reserved.get(who).unwrap_or_else(T::Balance::zero)
+	}
+
+	/// Get the locks on the balance of an account `who`.
+	pub fn locks(&self, who: &T::AccountId) -> Vec<BalanceLock<T::Balance>> {
+		self.locks.get(who).unwrap_or_default()
+	}
+
+	/// The part of the balance of `who` which can be used for `reasons`, i.e. which is not locked.
+	pub fn usable_balance(&self, who: &T::AccountId, reasons: Reasons) -> T::Balance {
+		self.balance(who).saturating_sub(self.frozen_balance(who, reasons))
+	}
+
+	/// The part of the balance of `who` which can't be used for `reasons`. Locks overlap, so this
+	/// is the biggest of the locks which apply.
+	fn frozen_balance(&self, who: &T::AccountId, reasons: Reasons) -> T::Balance {
+		self.locks(who)
+			.into_iter()
+			.filter(|lock| lock.reasons.intersects(reasons))
+			.fold(T::Balance::zero(), |frozen, lock| {
+				if lock.amount > frozen {
+					lock.amount
+				} else {
+					frozen
+				}
+			})
+	}
+
+	/// Ensure the balance of `who` can be lowered to `new_balance` for `reasons`.
+	fn ensure_can_withdraw(
+		&self,
+		who: &T::AccountId,
+		new_balance: T::Balance,
+		reasons: Reasons,
+	) -> DispatchResult {
+		if new_balance < self.frozen_balance(who, reasons) {
+			return Err("Balance is locked.");
+		}
+		Ok(())
+	}
+
+	/// Whether `who` can exist with `free` balance, given its reserved balance.
+	fn can_exist(&self, who: &T::AccountId, free: T::Balance) -> bool {
+		free.saturating_add(self.reserved_balance(who)) >= T::EXISTENTIAL_DEPOSIT
+	}
+
+	/// Store the balance of `who`, without changing the total issuance.
+	fn write_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
+		if amount.is_zero() {
+			self.balances.remove(who);
+		} else {
+			self.balances.insert(who.clone(), amount);
+		}
+	}
+
+	/// Store the reserved balance of `who`, without changing the total issuance.
+	fn write_reserved(&mut self, who: &T::AccountId, amount: T::Balance) {
+		if amount.is_zero() {
+			self.reserved.remove(who);
+		} else {
+			self.reserved.insert(who.clone(), amount);
+		}
+	}
+
+	/// Remove every trace of the account `who`: its balances, which are burnt, its locks and its
+	/// nonce.
+	fn reap_account(&mut self, who: &T::AccountId) {
+		self.set_balance(who, T::Balance::zero());
+		drop(NegativeImbalance::<T>(self.reserved.remove(who).unwrap_or_else(T::Balance::zero)));
+		self.locks.remove(who);
+		crate::system::Pallet::<T>::new().reap_account(who);
+	}
+
+	/// Add `value` to the balance of `who`, without issuing it. Nothing is added if it would
+	/// overflow, or if it would create an account with less than the existential deposit.
+	pub fn deposit_creating(
+		&mut self,
+		who: &T::AccountId,
+		value: T::Balance,
+	) -> PositiveImbalance<T> {
+		match self.balance(who).checked_add(&value) {
+			Some(new_balance) if self.can_exist(who, new_balance) => {
+				self.write_balance(who, new_balance);
+				PositiveImbalance(value)
+			},
+			_ => PositiveImbalance(T::Balance::zero()),
+		}
+	}
+
+	/// Give the funds of `imbalance` to `who`. They are burnt if that would overflow, or create an
+	/// account with less than the existential deposit.
+	pub fn resolve_creating(&mut self, who: &T::AccountId, imbalance: NegativeImbalance<T>) {
+		// The deposit offsets the imbalance, unless it failed and the funds are burnt.
+		let deposited = self.deposit_creating(who, imbalance.peek());
+		drop(imbalance.offset(deposited));
+	}
+
+	/// Remove `value` from the balance of `who` for `reasons`, without burning it. If `who` is left
+	/// with less than the existential deposit, this fails when `keep_alive` is set, otherwise `who`
+	/// is reaped and what is left of its balances is burnt.
+	pub fn withdraw(
+		&mut self,
+		who: &T::AccountId,
+		value: T::Balance,
+		reasons: Reasons,
+		keep_alive: bool,
+	) -> Result<NegativeImbalance<T>, &'static str> {
+		let new_balance = self.balance(who).checked_sub(&value).ok_or("Not enough funds.")?;
+		self.ensure_can_withdraw(who, new_balance, reasons)?;
+		let reap = !self.can_exist(who, new_balance);
+		if reap && keep_alive {
+			return Err("Transfer would kill the account.");
+		}
+
+		self.write_balance(who, new_balance);
+		if reap {
+			self.reap_account(who);
+		}
+		Ok(NegativeImbalance(value))
+	}
+
+	/// Remove as much as possible of `value` from the balance of `who`, without burning it. Locks
+	/// are ignored. Returns the funds removed, and what could not be removed. `who` is reaped if it
+	/// is left with less than the existential deposit.
+	pub fn slash(
+		&mut self,
+		who: &T::AccountId,
+		value: T::Balance,
+	) -> (NegativeImbalance<T>, T::Balance) {
+		let balance = self.balance(who);
+		let slashed = if value < balance { value } else { balance };
+		self.write_balance(who, balance.saturating_sub(slashed));
+		if !self.can_exist(who, balance.saturating_sub(slashed)) {
+			self.reap_account(who);
+		}
+		(NegativeImbalance(slashed), value.saturating_sub(slashed))
+	}
+
+	/// Move `value` from the balance of `who` to its reserved balance.
+	pub fn reserve(&mut self, who: &T::AccountId, value: T::Balance) -> DispatchResult {
+		let new_balance = self.balance(who).checked_sub(&value).ok_or("Not enough funds.")?;
+		self.ensure_can_withdraw(who, new_balance, Reasons::Misc)?;
+		let new_reserved = self
+			.reserved_balance(who)
+			.checked_add(&value)
+			.ok_or("Maximum amount of funds reached")?;
+		self.write_balance(who, new_balance);
+		self.write_reserved(who, new_reserved);
+		Ok(())
+	}
+
+	/// Move as much as possible of `value` from the reserved balance of `who` back to its balance.
+	/// Returns what could not be unreserved.
+	pub fn unreserve(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let reserved = self.reserved_balance(who);
+		let unreserved = if value < reserved { value } else { reserved };
+		match self.balance(who).checked_add(&unreserved) {
+			Some(new_balance) => {
+				self.write_reserved(who, reserved.saturating_sub(unreserved));
+				self.write_balance(who, new_balance);
+				value.saturating_sub(unreserved)
+			},
+			None => value,
+		}
+	}
+
+	/// Remove as much as possible of `value` from the reserved balance of `who`, without burning
+	/// it. Returns the funds removed, and what could not be removed.
+	pub fn slash_reserved(
+		&mut self,
+		who: &T::AccountId,
+		value: T::Balance,
+	) -> (NegativeImbalance<T>, T::Balance) {
+		let reserved = self.reserved_balance(who);
+		let slashed = if value < reserved { value } else { reserved };
+		self.write_reserved(who, reserved.saturating_sub(slashed));
+		if !self.can_exist(who, self.balance(who)) {
+			self.reap_account(who);
+		}
+		(NegativeImbalance(slashed), value.saturating_sub(slashed))
+	}
+
+	/// Move as much as possible of `value` from the reserved balance of `slashed` to the balance
+	/// of `beneficiary`, or to its reserved balance depending on `status`. Returns what could not
+	/// be moved.
+	pub fn repatriate_reserved(
+		&mut self,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: T::Balance,
+		status: BalanceStatus,
+	) -> Result<T::Balance, &'static str> {
+		if slashed == beneficiary {
+			return Ok(match status {
+				BalanceStatus::Free => self.unreserve(slashed, value),
+				BalanceStatus::Reserved => {
+					let reserved = self.reserved_balance(slashed);
+					value.saturating_sub(if value < reserved { value } else { reserved })
+				},
+			});
+		}
+
+		let reserved = self.reserved_balance(slashed);
+		let moved = if value < reserved { value } else { reserved };
+		let (balance, beneficiary_reserved) =
+			(self.balance(beneficiary), self.reserved_balance(beneficiary));
+		let new_total = match status {
+			BalanceStatus::Free => balance.checked_add(&moved),
+			BalanceStatus::Reserved => beneficiary_reserved.checked_add(&moved),
+		}
+		.ok_or("Maximum amount of funds reached")?;
+		if balance.saturating_add(beneficiary_reserved).saturating_add(moved) <
+			T::EXISTENTIAL_DEPOSIT
+		{
+			return Err("Balance too low to create the account.");
+		}
+
+		self.write_reserved(slashed, reserved.saturating_sub(moved));
+		match status {
+			BalanceStatus::Free => self.write_balance(beneficiary, new_total),
+			BalanceStatus::Reserved => self.write_reserved(beneficiary, new_total),
+		}
+		if !self.can_exist(slashed, self.balance(slashed)) {
+			self.reap_account(slashed);
+		}
+		Ok(value.saturating_sub(moved))
+	}
+
+	/// Lock `amount` of the balance of `who` for `reasons`, under the name `id`. This replaces any
+	/// lock with the same name.
+	pub fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: Reasons,
+	) {
+		let mut locks = self.locks(who);
+		locks.retain(|lock| lock.id != id);
+		if !amount.is_zero() {
+			locks.push(BalanceLock { id, amount, reasons });
+		}
+		self.write_locks(who, locks);
+	}
+
+	/// Like `set_lock`, but the lock named `id` only grows: it keeps the biggest amount, and the
+	/// reasons of both locks.
+	pub fn extend_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: Reasons,
+	) {
+		let (amount, reasons) = match self.locks(who).into_iter().find(|lock| lock.id == id) {
+			Some(lock) => (
+				if lock.amount > amount { lock.amount } else { amount },
+				lock.reasons.union(reasons),
+			),
+			None => (amount, reasons),
+		};
+		self.set_lock(id, who, amount, reasons);
+	}
+
+	/// Remove the lock named `id` from the balance of `who`.
+	pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		let mut locks = self.locks(who);
+		locks.retain(|lock| lock.id != id);
+		self.write_locks(who, locks);
+	}
+
+	fn write_locks(&mut self, who: &T::AccountId, locks: Vec<BalanceLock<T::Balance>>) {
+		if locks.is_empty() {
+			self.locks.remove(who);
+		} else {
+			self.locks.insert(who.clone(), locks);
+		}
+	}
+
+	/// Transfer `amount` from `from` to `to`. If `from` is left with less than the existential
+	/// deposit, the transfer fails when `keep_alive` is set, otherwise `from` is reaped.
+	fn do_transfer(
+		&mut self,
+		from: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+		keep_alive: bool,
+	) -> DispatchResult {
+		let new_from_b = self.balance(&from).checked_sub(&amount).ok_or("Not enough funds.")?;
+		self.ensure_can_withdraw(&from, new_from_b, Reasons::Misc)?;
+		// Transferring to oneself changes nothing.
+		if from == to {
+			return Ok(());
+		}
+		let new_to_b =
+			self.balance(&to).checked_add(&amount).ok_or("Maximum amount of funds reached")?;
+		if !self.can_exist(&to, new_to_b) {
+			return Err("Balance too low to create the account.");
+		}
+		let reap = !self.can_exist(&from, new_from_b);
+		if reap && keep_alive {
+			return Err("Transfer would kill the account.");
+		}
+
+		if reap {
+			self.reap_account(&from);
+		} else {
+			self.set_balance(&from, new_from_b);
+		}
+		self.set_balance(&to, new_to_b);
+		Ok(())
+	}
+
+	/// The sum of the balances of all accounts, reserved balances included, or `None` if it
+	/// overflows.
+	fn sum_of_balances(&self) -> Option<T::Balance> {
+		self.balances
+			.iter()
+			.chain(self.reserved.iter())
+			.try_fold(T::Balance::zero(), |total, (_, balance)| total.checked_add(&balance))
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+// The inherent methods of the pallet come first, so `self.method(..)` calls them below.
+impl<T: Config> Currency<T::AccountId> for Pallet<T> {
+	type Balance = T::Balance;
+
+	fn total_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.balance(who).saturating_add(self.reserved_balance(who))
+	}
+
+	fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.balance(who)
+	}
+
+	fn total_issuance(&self) -> T::Balance {
+		self.total_issuance()
+	}
+
+	fn minimum_balance(&self) -> T::Balance {
+		T::EXISTENTIAL_DEPOSIT
+	}
+
+	fn transfer(
+		&mut self,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		value: T::Balance,
+		keep_alive: bool,
+	) -> DispatchResult {
+		self.do_transfer(from.clone(), to.clone(), value, keep_alive)
+	}
+
+	fn deposit_creating(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		self.deposit_creating(who, value).peek()
+	}
+
+	fn withdraw(
+		&mut self,
+		who: &T::AccountId,
+		value: T::Balance,
+		reasons: Reasons,
+		keep_alive: bool,
+	) -> DispatchResult {
+		drop(self.withdraw(who, value, reasons, keep_alive)?);
+		Ok(())
+	}
+
+	fn slash(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		self.slash(who, value).1
+	}
+}
+
+impl<T: Config> ReservableCurrency<T::AccountId> for Pallet<T> {
+	fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.reserved_balance(who)
+	}
+
+	fn can_reserve(&self, who: &T::AccountId, value: T::Balance) -> bool {
+		self.balance(who).checked_sub(&value).is_some_and(|new_balance| {
+			self.ensure_can_withdraw(who, new_balance, Reasons::Misc).is_ok()
+		})
+	}
+
+	fn reserve(&mut self, who: &T::AccountId, value: T::Balance) -> DispatchResult {
+		self.reserve(who, value)
+	}
+
+	fn unreserve(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		self.unreserve(who, value)
+	}
+
+	fn slash_reserved(&mut self, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		self.slash_reserved(who, value).1
+	}
+
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: T::Balance,
+		status: BalanceStatus,
+	) -> Result<T::Balance, &'static str> {
+		self.repatriate_reserved(slashed, beneficiary, value, status)
+	}
+}
+
+impl<T: Config> LockableCurrency<T::AccountId> for Pallet<T> {
+	fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: Reasons,
+	) {
+		self.set_lock(id, who, amount, reasons)
+	}
+
+	fn extend_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: Reasons,
+	) {
+		self.extend_lock(id, who, amount, reasons)
+	}
+
+	fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		self.remove_lock(id, who)
 	}
 }
 
@@ -41,40 +654,160 @@ impl<T:Config> Pallet<T>
     /// Transfer `amount` from one account to another.
 	/// This function verifies that `from` has at least `amount` balance to transfer,
 	/// and that no mathematical overflows occur.
+	/// Locked funds can't be transferred.
+	/// If `from` is left with less than the existential deposit, it is reaped.
+	#[call_index(0)]
 	pub fn transfer(
 		&mut self,
 		caller: T::AccountId,
 		to: T::AccountId,
 		amount: T::Balance,
 	) -> DispatchResult {
-        let new_from_b = self.
-            balance(&caller).
-            checked_sub(&amount).
-            ok_or("Not enough funds.")?;
-        let new_to_b = self.
-            balance(&to).
-            checked_add(&amount).
-            ok_or("Maximum amount of funds reached")?;
-        
-        self.set_balance(&caller, new_from_b);
-        self.set_balance(&to, new_to_b);
+		self.do_transfer(caller, to, amount, false)
+	}
+
+	/// Transfer `amount` from one account to another, failing if `from` would be left with less
+	/// than the existential deposit.
+	#[call_index(1)]
+	pub fn transfer_keep_alive(
+		&mut self,
+		caller: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.do_transfer(caller, to, amount, true)
+	}
+
+	/// Transfer all the usable balance of `caller` to `to`. `caller` is reaped, unless it has
+	/// locked or reserved funds.
+	#[call_index(2)]
+	pub fn transfer_all(&mut self, caller: T::AccountId, to: T::AccountId) -> DispatchResult {
+		let amount = self.usable_balance(&caller, Reasons::Misc);
+		self.do_transfer(caller, to, amount, false)
+	}
+
+	/// Create `amount` of new funds in the account `who`, increasing the total issuance.
+	/// This function can only be called by `Root`.
+	#[call_index(3)]
+	pub fn mint(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		let issued = self.deposit_creating(&who, amount);
+		if issued.peek() != amount {
+			return Err("Balance too low to create the account.");
+		}
+		// `issued` is dropped here, increasing the total issuance.
+		Ok(())
+	}
+
+	/// Destroy `amount` of the funds of the account `who`, decreasing the total issuance.
+	/// If `who` is left with less than the existential deposit, it is reaped.
+	/// This function can only be called by `Root`.
+	#[call_index(4)]
+	pub fn burn(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		drop(self.withdraw(&who, amount, Reasons::All, false)?);
+		Ok(())
+	}
+
+	/// Set the free balance of the account `who` to `amount`, see `set_balance`. `who` is reaped
+	/// if it is left with less than the existential deposit.
+	/// This function can only be called by `Root`.
+	#[call_index(5)]
+	pub fn force_set_balance(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		self.set_balance(&who, amount);
+		if !self.can_exist(&who, amount) {
+			self.reap_account(&who);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {
+	fn on_runtime_upgrade(&mut self) {
+		if self.storage_version.get().unwrap_or(0) < 1 {
+			// Drop the accounts left with a zero balance.
+			self.balances.translate(|_, balance: T::Balance| {
+				if balance.is_zero() {
+					None
+				} else {
+					Some(balance)
+				}
+			});
+		}
+		if self.storage_version.get().unwrap_or(0) < 2 {
+			// Start tracking the total issuance.
+			let total = self.sum_of_balances().unwrap_or_else(T::Balance::zero);
+			self.total_issuance.put(total);
+		}
+		self.storage_version.put(STORAGE_VERSION);
+	}
+
+	fn on_genesis(&mut self) {
+		self.storage_version.put(STORAGE_VERSION);
+	}
+
+	fn pre_upgrade(&self) -> Result<Vec<u8>, &'static str> {
+		Ok(self.sum_of_balances().ok_or("total balance overflows")?.encode())
+	}
+
+	fn post_upgrade(&self, state: Vec<u8>) -> DispatchResult {
+		let total_before = T::Balance::decode_all(&state)?;
+		if self.sum_of_balances() != Some(total_before) {
+			return Err("migration changed the total balance");
+		}
+		if self.balances.iter().any(|(_, balance)| balance.is_zero()) {
+			return Err("migration left accounts with a zero balance");
+		}
+		if self.total_issuance.get() != Some(total_before) {
+			return Err("migration did not set the total issuance");
+		}
+		if self.storage_version.get() != Some(STORAGE_VERSION) {
+			return Err("migration did not update the storage version");
+		}
 		Ok(())
 	}
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::balances::{Pallet, Config};
+    use crate::balances::{BalanceStatus, Call, Pallet, Config, Reasons};
+	use crate::support::{
+		storage::{self, StorageMap},
+		ArgMetadata, CallMetadata, Encode, GetCallMetadata, OnRuntimeUpgrade, Origin,
+		RuntimeVersion,
+	};
 	use crate::system;
 
 	struct TestConfig;
 	impl Config for TestConfig {
         type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 10;
     }
     impl system::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
 		type Nonce = u32;
+		type RuntimeEvent = ();
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
     }
 
 	#[test]
@@ -106,4 +839,286 @@ mod tests {
 			Err("Not enough funds.")
 		);
 	}
+
+	#[test]
+	fn encode_transfer() {
+		let call = Call::<TestConfig>::transfer { to: "bob".to_string(), amount: 7 };
+		let mut expected = vec![0];
+		expected.extend_from_slice(&3u32.to_le_bytes());
+		expected.extend_from_slice(b"bob");
+		expected.extend_from_slice(&7u128.to_le_bytes());
+		assert_eq!(call.encode(), expected);
+	}
+
+	#[test]
+	fn call_metadata() {
+		assert_eq!(
+			Call::<TestConfig>::call_metadata(),
+			vec![
+				CallMetadata {
+					index: 0,
+					name: "transfer",
+					args: vec![
+						ArgMetadata { name: "to", ty: "T::AccountId" },
+						ArgMetadata { name: "amount", ty: "T::Balance" },
+					],
+				},
+				CallMetadata {
+					index: 1,
+					name: "transfer_keep_alive",
+					args: vec![
+						ArgMetadata { name: "to", ty: "T::AccountId" },
+						ArgMetadata { name: "amount", ty: "T::Balance" },
+					],
+				},
+				CallMetadata {
+					index: 2,
+					name: "transfer_all",
+					args: vec![ArgMetadata { name: "to", ty: "T::AccountId" }],
+				},
+				CallMetadata {
+					index: 3,
+					name: "mint",
+					args: vec![
+						ArgMetadata { name: "who", ty: "T::AccountId" },
+						ArgMetadata { name: "amount", ty: "T::Balance" },
+					],
+				},
+				CallMetadata {
+					index: 4,
+					name: "burn",
+					args: vec![
+						ArgMetadata { name: "who", ty: "T::AccountId" },
+						ArgMetadata { name: "amount", ty: "T::Balance" },
+					],
+				},
+				CallMetadata {
+					index: 5,
+					name: "force_set_balance",
+					args: vec![
+						ArgMetadata { name: "who", ty: "T::AccountId" },
+						ArgMetadata { name: "amount", ty: "T::Balance" },
+					],
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn existential_deposit() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let mut system = system::Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+		system.inc_nonce(alice);
+
+		assert_eq!(
+			balances.transfer(alice.clone(), bob.clone(), 9),
+			Err("Balance too low to create the account.")
+		);
+		assert_eq!(
+			balances.transfer_keep_alive(alice.clone(), bob.clone(), 95),
+			Err("Transfer would kill the account.")
+		);
+		assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 90), Ok(()));
+		assert_eq!(balances.balance(alice), 10);
+
+		// Alice is reaped, and the 5 left are lost.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 5), Ok(()));
+		assert_eq!(balances.balance(bob), 95);
+		assert!(!balances.balances.contains_key(alice));
+		assert_eq!(system.nonce(alice), 0);
+
+		// Root can't leave an account below the existential deposit either.
+		system.inc_nonce(bob);
+		assert_eq!(balances.force_set_balance(Origin::Root, bob.clone(), 5), Ok(()));
+		assert_eq!(balances.balance(bob), 0);
+		assert_eq!(system.nonce(bob), 0);
+		assert_eq!(balances.total_issuance(), 0);
+	}
+
+	#[test]
+	fn transfer_all() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+
+		assert_eq!(balances.transfer_all(alice.clone(), bob.clone()), Ok(()));
+		assert_eq!(balances.balance(bob), 100);
+		assert!(!balances.balances.contains_key(alice));
+		assert_eq!(
+			balances.transfer_all(alice.clone(), "charlie".to_string()),
+			Err("Balance too low to create the account.")
+		);
+	}
+
+	/// Check that the sum of all balances is the total issuance.
+	fn assert_issuance(balances: &Pallet<TestConfig>, expected: u128) {
+		assert_eq!(balances.sum_of_balances(), Some(expected));
+		assert_eq!(balances.total_issuance(), expected);
+	}
+
+	#[test]
+	fn total_issuance() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+		assert_issuance(&balances, 100);
+
+		// Reaping alice burns the 5 left.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 95), Ok(()));
+		assert_issuance(&balances, 95);
+		assert_eq!(balances.transfer(bob.clone(), bob.clone(), 95), Ok(()));
+		assert_issuance(&balances, 95);
+
+		assert_eq!(
+			balances.mint(Origin::Signed(bob.clone()), bob.clone(), 10),
+			Err("bad origin: expected root")
+		);
+		assert_eq!(
+			balances.mint(Origin::Root, alice.clone(), 5),
+			Err("Balance too low to create the account.")
+		);
+		assert_eq!(balances.mint(Origin::Root, alice.clone(), 50), Ok(()));
+		assert_issuance(&balances, 145);
+		assert_eq!(balances.burn(Origin::Root, bob.clone(), 100), Err("Not enough funds."));
+		assert_eq!(balances.burn(Origin::Root, bob.clone(), 90), Ok(()));
+		assert_eq!(balances.balance(bob), 0);
+		assert_issuance(&balances, 50);
+	}
+
+	#[test]
+	fn imbalances() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+
+		// A fee of 30 from alice: 20 go to bob, and the rest is burnt.
+		let fee = balances.withdraw(alice, 30, Reasons::Fee, true).unwrap();
+		assert_eq!(balances.total_issuance(), 100);
+		let (reward, burnt) = fee.split(20);
+		balances.resolve_creating(bob, reward);
+		assert_eq!(burnt.peek(), 10);
+		drop(burnt);
+		assert_eq!(balances.balance(bob), 20);
+		assert_issuance(&balances, 90);
+
+		// A reward of 5 for bob, paid by slashing alice.
+		let (slashed, missing) = balances.slash(alice, 5);
+		assert_eq!(missing, 0);
+		let reward = balances.deposit_creating(bob, 5);
+		assert_eq!(reward.offset(slashed).ok().map(|rest| rest.peek()), Some(0));
+		assert_eq!(balances.balance(alice), 65);
+		assert_issuance(&balances, 90);
+
+		// Slashing more than the balance reaps the account.
+		let (slashed, missing) = balances.slash(alice, 100);
+		assert_eq!((slashed.peek(), missing), (65, 35));
+		drop(slashed);
+		assert_issuance(&balances, 25);
+
+		// Funds given to an account which can't exist are burnt.
+		let funds = balances.withdraw(bob, 5, Reasons::Fee, true).unwrap();
+		balances.resolve_creating(&"charlie".to_string(), funds);
+		assert_issuance(&balances, 20);
+	}
+
+	#[test]
+	fn reserves() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+
+		assert_eq!(balances.reserve(alice, 101), Err("Not enough funds."));
+		assert_eq!(balances.reserve(alice, 95), Ok(()));
+		assert_eq!((balances.balance(alice), balances.reserved_balance(alice)), (5, 95));
+		assert_issuance(&balances, 100);
+
+		// Reserved funds can't be transferred, but keep alice alive.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Err("Not enough funds."));
+		assert_eq!(balances.unreserve(alice, 100), 5);
+		assert_eq!((balances.balance(alice), balances.reserved_balance(alice)), (100, 0));
+
+		// Repatriating to bob creates his account, if he gets enough.
+		assert_eq!(balances.reserve(alice, 50), Ok(()));
+		assert_eq!(
+			balances.repatriate_reserved(alice, bob, 5, BalanceStatus::Free),
+			Err("Balance too low to create the account.")
+		);
+		assert_eq!(balances.repatriate_reserved(alice, bob, 20, BalanceStatus::Free), Ok(0));
+		assert_eq!(balances.repatriate_reserved(alice, bob, 40, BalanceStatus::Reserved), Ok(10));
+		assert_eq!((balances.balance(bob), balances.reserved_balance(bob)), (20, 30));
+		assert_eq!(balances.reserved_balance(alice), 0);
+		assert_issuance(&balances, 100);
+
+		// Bob only exists thanks to his reserved balance, so slashing it reaps him.
+		assert_eq!(balances.transfer(bob.clone(), alice.clone(), 15), Ok(()));
+		let (slashed, missing) = balances.slash_reserved(bob, 30);
+		assert_eq!((slashed.peek(), missing), (30, 0));
+		drop(slashed);
+		assert!(!balances.balances.contains_key(bob));
+		assert_issuance(&balances, 65);
+	}
+
+	#[test]
+	fn locks() {
+		let mut balances = Pallet::<TestConfig>::new();
+		let (alice, bob) = (&"alice".to_string(), &"bob".to_string());
+		balances.set_balance(alice, 100);
+
+		balances.set_lock(*b"staking ", alice, 60, Reasons::All);
+		balances.set_lock(*b"vesting ", alice, 30, Reasons::Misc);
+		assert_eq!(balances.usable_balance(alice, Reasons::Misc), 40);
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 41), Err("Balance is locked."));
+		assert_eq!(balances.reserve(alice, 41), Err("Balance is locked."));
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 40), Ok(()));
+
+		// Locks for other reasons don't apply.
+		balances.set_lock(*b"staking ", alice, 10, Reasons::Fee);
+		assert_eq!(balances.usable_balance(alice, Reasons::Fee), 50);
+		assert_eq!(balances.usable_balance(alice, Reasons::Misc), 30);
+		let fee = balances.withdraw(alice, 51, Reasons::Fee, true);
+		assert_eq!(fee.err(), Some("Balance is locked."));
+
+		// Extending a lock keeps the biggest amount, and all the reasons.
+		balances.extend_lock(*b"staking ", alice, 5, Reasons::Misc);
+		assert_eq!(balances.locks(alice)[1].amount, 10);
+		assert_eq!(balances.locks(alice)[1].reasons, Reasons::All);
+
+		balances.remove_lock(*b"vesting ", alice);
+		balances.remove_lock(*b"staking ", alice);
+		assert!(balances.locks(alice).is_empty());
+		assert_eq!(balances.transfer_all(alice.clone(), bob.clone()), Ok(()));
+		assert_eq!(balances.balance(bob), 100);
+	}
+
+	#[test]
+	fn migrate_zero_balances() {
+		// Balances as stored before version 1, including zero balances.
+		let old_balances = StorageMap::<String, u128>::new("Balances", "Balances");
+		old_balances.insert("alice".to_string(), 100);
+		old_balances.insert("bob".to_string(), 0);
+
+		let mut balances = Pallet::<TestConfig>::new();
+		assert_eq!(balances.try_on_runtime_upgrade(), Ok(()));
+		assert_eq!(balances.balance(&"alice".to_string()), 100);
+		assert!(!balances.balances.contains_key(&"bob".to_string()));
+		assert_eq!(balances.total_issuance(), 100);
+
+		// The migration only runs once.
+		old_balances.insert("bob".to_string(), 0);
+		balances.on_runtime_upgrade();
+		assert!(balances.balances.contains_key(&"bob".to_string()));
+	}
+
+	#[test]
+	fn migrations_skip_new_chains() {
+		let mut balances = Pallet::<TestConfig>::new();
+		balances.on_genesis();
+		balances.set_balance(&"alice".to_string(), 100);
+
+		let state = storage::snapshot();
+		assert_eq!(balances.try_on_runtime_upgrade(), Ok(()));
+		assert_eq!(storage::snapshot(), state);
+	}
 }
@@ -0,0 +1,414 @@
+//! A chain of blocks executed by the runtime, along with the state after each of them.
+//!
+//! In archive mode, the state after every block is kept and can be queried. Otherwise, only the
+//! state after the last blocks, and after the finalized block, is kept, see `Pruning`.
+//!
+//! Chains are moved between machines with export files, which contain blocks to re-execute and/or
+//! a snapshot of the state, along with the state root the importer must end up with. The format
+//! of an export file is:
+//! - the bytes of `MAGIC`, followed by the `FORMAT_VERSION` as a `u16`,
+//! - an optional snapshot: the block number it was taken at, followed by the encoded `State`,
+//! - the encoded blocks to execute on top of the state of the importer, or of the snapshot,
+//! - the root of the state after the last block, or of the snapshot if there are no blocks.
+
+use std::collections::BTreeMap;
+
+use crate::runtime::{types, ExecutionStrategy, Runtime};
+use crate::support::{
+    hashing,
+    storage::{self, State},
+    Decode, Encode,
+};
+
+/// The bytes every export file starts with.
+pub const MAGIC: [u8; 4] = *b"RSMC";
+
+/// The version of the export file format, increased with every change to it.
+/// - 1: the initial format.
+/// - 2: extrinsics without a caller are inherents.
+pub const FORMAT_VERSION: u16 = 2;
+
+/// The hash of an encoded block.
+pub type BlockHash = [u8; 32];
+
+/// Which states a `Chain` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pruning {
+    /// Keep the state after every block.
+    Archive,
+    /// Only keep the state after the last `n` blocks, and after the finalized block.
+    KeepLast(types::BlockNumber),
+}
+
+/// The content of an export file.
+struct ExportFile {
+    snapshot: Option<(types::BlockNumber, State)>,
+    blocks: Vec<Vec<u8>>,
+    state_root: [u8; 32],
+}
+
+impl Encode for ExportFile {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        MAGIC.encode_to(dest);
+        FORMAT_VERSION.encode_to(dest);
+        self.snapshot.encode_to(dest);
+        self.blocks.encode_to(dest);
+        self.state_root.encode_to(dest);
+    }
+}
+
+impl Decode for ExportFile {
+    fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+        if <[u8; 4]>::decode(input) != Ok(MAGIC) {
+            return Err("not an export file");
+        }
+        if u16::decode(input)? != FORMAT_VERSION {
+            return Err("unsupported export file version");
+        }
+        Ok(Self {
+            snapshot: Decode::decode(input)?,
+            blocks: Decode::decode(input)?,
+            state_root: Decode::decode(input)?,
+        })
+    }
+}
+
+/// Read `state` through the runtime. Changes made by `f` are discarded.
+fn read<R>(state: &State, f: impl FnOnce(&Runtime) -> R) -> R {
+    storage::execute_with(&mut state.clone(), || f(&Runtime::new()))
+}
+
+/// The blocks executed by the runtime, and the state after each of them.
+#[derive(Clone)]
+pub struct Chain {
+    /// How blocks are executed.
+    strategy: ExecutionStrategy,
+    /// Which states are kept.
+    pruning: Pruning,
+    /// The number of the block the chain started from, e.g. 0 for the genesis state.
+    start: types::BlockNumber,
+    /// The number of the last finalized block. Its state is never pruned.
+    finalized: types::BlockNumber,
+    /// The encoded blocks, by block number.
+    blocks: BTreeMap<types::BlockNumber, Vec<u8>>,
+    /// The number of each block, by block hash.
+    numbers: BTreeMap<BlockHash, types::BlockNumber>,
+    /// The state after each block which was not pruned, by block number. This includes the state
+    /// the chain started from.
+    states: BTreeMap<types::BlockNumber, State>,
+}
+
+impl Chain {
+    /// Create a chain starting from the `genesis` state, executing blocks following `strategy`.
+    /// The chain is in archive mode, see `with_pruning`.
+    pub fn new(genesis: State, strategy: ExecutionStrategy) -> Self {
+        let number = read(&genesis, |runtime| runtime.system.block_number());
+        Self {
+            strategy,
+            pruning: Pruning::Archive,
+            start: number,
+            finalized: number,
+            blocks: BTreeMap::new(),
+            numbers: BTreeMap::new(),
+            states: BTreeMap::from([(number, genesis)]),
+        }
+    }
+
+    /// Only keep the states selected by `pruning`.
+    pub fn with_pruning(mut self, pruning: Pruning) -> Self {
+        self.pruning = pruning;
+        self.prune();
+        self
+    }
+
+    /// The number of the last block executed.
+    pub fn best_number(&self) -> types::BlockNumber {
+        *self.states.keys().next_back().expect("there is always a state")
+    }
+
+    /// The state after the last block executed.
+    pub fn state(&self) -> &State {
+        self.states.values().next_back().expect("there is always a state")
+    }
+
+    /// The root of the state after the last block executed.
+    pub fn state_root(&self) -> [u8; 32] {
+        storage::root(self.state())
+    }
+
+    /// The number of the last finalized block.
+    pub fn finalized_number(&self) -> types::BlockNumber {
+        self.finalized
+    }
+
+    /// The hash of the block `number`, if this chain executed it.
+    pub fn block_hash(&self, number: types::BlockNumber) -> Option<BlockHash> {
+        self.blocks.get(&number).map(|block| hashing::blake2_256(block))
+    }
+
+    /// The state after the block `number`, unless it was pruned.
+    fn state_at(&self, number: types::BlockNumber) -> Result<&State, &'static str> {
+        if !(self.start..=self.best_number()).contains(&number) {
+            return Err("block is not known");
+        }
+        self.states.get(&number).ok_or("state is pruned")
+    }
+
+    /// Read the state after the last block executed through the runtime.
+    pub fn inspect<R>(&self, f: impl FnOnce(&Runtime) -> R) -> R {
+        read(self.state(), f)
+    }
+
+    /// Read the state after the block with hash `at` through the runtime, unless it was pruned.
+    pub fn inspect_at<R>(
+        &self,
+        at: BlockHash,
+        f: impl FnOnce(&Runtime) -> R,
+    ) -> Result<R, &'static str> {
+        let number = self.numbers.get(&at).ok_or("block is not known")?;
+        Ok(read(self.state_at(*number)?, f))
+    }
+
+    /// The balance of `who` after the block with hash `at`, unless its state was pruned.
+    pub fn balance(
+        &self,
+        who: &types::AccountId,
+        at: BlockHash,
+    ) -> Result<types::Balance, &'static str> {
+        self.inspect_at(at, |runtime| runtime.balances.balance(who))
+    }
+
+    /// Mark the block `number` as finalized: its state is kept whatever the pruning.
+    pub fn finalize(&mut self, number: types::BlockNumber) -> Result<(), &'static str> {
+        if number < self.finalized {
+            return Err("a later block is already finalized");
+        }
+        self.state_at(number)?;
+        self.finalized = number;
+        self.prune();
+        Ok(())
+    }
+
+    /// Drop the states which are not kept with the pruning of the chain.
+    fn prune(&mut self) {
+        let Pruning::KeepLast(keep) = self.pruning else { return };
+        let (best, finalized) = (self.best_number(), self.finalized);
+        self.states
+            .retain(|&number, _| best - number < keep || number == best || number == finalized);
+    }
+
+    /// Execute `block` on top of the last block executed.
+    pub fn execute_block(&mut self, block: types::Block) -> Result<(), String> {
+        let number = block.header.block_number;
+        let encoded = block.encode();
+        let mut state = self.state().clone();
+        storage::execute_with(&mut state, || {
+            Runtime::new().execute_block_with(self.strategy, block)
+        })?;
+        self.numbers.insert(hashing::blake2_256(&encoded), number);
+        self.blocks.insert(number, encoded);
+        self.states.insert(number, state);
+        self.prune();
+        Ok(())
+    }
+
+    /// Export the blocks `from` to `to`, included, to be executed by a chain which is at block
+    /// `from - 1`.
+    pub fn export_blocks(
+        &self,
+        from: types::BlockNumber,
+        to: types::BlockNumber,
+    ) -> Result<Vec<u8>, &'static str> {
+        if from > to {
+            return Err("first block is after the last one");
+        }
+        let blocks = (from..=to)
+            .map(|number| self.blocks.get(&number).cloned().ok_or("block is not known"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let state = self.state_at(to)?;
+        Ok(ExportFile { snapshot: None, blocks, state_root: storage::root(state) }.encode())
+    }
+
+    /// Export a snapshot of the state after the block `at`.
+    pub fn export_state(&self, at: types::BlockNumber) -> Result<Vec<u8>, &'static str> {
+        let state = self.state_at(at)?;
+        let state_root = storage::root(state);
+        Ok(ExportFile { snapshot: Some((at, state.clone())), blocks: Vec::new(), state_root }
+            .encode())
+    }
+
+    /// Import an export file: load its snapshot if it has one, in place of the whole chain, then
+    /// execute its blocks. Nothing is imported unless the resulting state root is the expected
+    /// one.
+    pub fn import(&mut self, file: &[u8]) -> Result<(), String> {
+        let file = ExportFile::decode_all(file)?;
+        let mut chain = self.clone();
+        if let Some((number, state)) = file.snapshot {
+            if read(&state, |runtime| runtime.system.block_number()) != number {
+                return Err("snapshot is not at its block number".to_string());
+            }
+            chain.start = number;
+            chain.finalized = number;
+            chain.blocks.clear();
+            chain.numbers.clear();
+            chain.states = BTreeMap::from([(number, state)]);
+        }
+        for block in file.blocks {
+            chain.execute_block(types::Block::decode_all(&block)?)?;
+        }
+        if chain.state_root() != file.state_root {
+            return Err("state root does not match the export file".to_string());
+        }
+        *self = chain;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chain, Pruning, FORMAT_VERSION, MAGIC};
+    use crate::runtime::{types, ExecutionStrategy, Runtime, RuntimeCall};
+    use crate::support::{self, storage, Encode};
+    use crate::{balances, timestamp};
+
+    fn genesis(alice: u128) -> storage::State {
+        let mut state = storage::State::new();
+        storage::execute_with(&mut state, || {
+            let mut runtime = Runtime::new();
+            support::OnRuntimeUpgrade::on_genesis(&mut runtime);
+            runtime.balances.set_balance(&"alice".to_string(), alice)
+        });
+        state
+    }
+
+    fn transfer(block_number: u32, amount: u128) -> types::Block {
+        types::Block {
+            header: support::Header { block_number },
+            extrinsics: vec![
+                types::Extrinsic {
+                    caller: None,
+                    call: RuntimeCall::timestamp(timestamp::Call::set {
+                        now: 6_000 * block_number as u64,
+                    }),
+                },
+                types::Extrinsic {
+                    caller: Some("alice".to_string()),
+                    call: RuntimeCall::balances(balances::Call::transfer {
+                        to: "bob".to_string(),
+                        amount,
+                    }),
+                },
+            ],
+        }
+    }
+
+    fn chain() -> Chain {
+        let mut chain = Chain::new(genesis(200), ExecutionStrategy::NativeElseWasm);
+        assert_eq!(chain.execute_block(transfer(1, 10)), Ok(()));
+        assert_eq!(chain.execute_block(transfer(2, 20)), Ok(()));
+        chain
+    }
+
+    #[test]
+    fn export_import_blocks() {
+        let chain = chain();
+        assert_eq!(chain.best_number(), 2);
+        assert_ne!(chain.block_hash(1), chain.block_hash(2));
+        let file = chain.export_blocks(1, 2).unwrap();
+
+        let mut imported = Chain::new(genesis(200), ExecutionStrategy::NativeElseWasm);
+        assert_eq!(imported.import(&file), Ok(()));
+        assert_eq!(imported.state_root(), chain.state_root());
+        assert_eq!(imported.block_hash(2), chain.block_hash(2));
+
+        // Executing the same blocks from another state doesn't give the same state root.
+        let mut other = Chain::new(genesis(15), ExecutionStrategy::NativeElseWasm);
+        assert_eq!(
+            other.import(&file),
+            Err("state root does not match the export file".to_string())
+        );
+        assert_eq!(other.best_number(), 0);
+
+        assert_eq!(chain.export_blocks(2, 3), Err("block is not known"));
+        assert_eq!(chain.export_blocks(2, 1), Err("first block is after the last one"));
+    }
+
+    #[test]
+    fn export_import_state() {
+        let mut chain = chain();
+        let file = chain.export_state(1).unwrap();
+
+        let mut imported = Chain::new(storage::State::new(), ExecutionStrategy::NativeElseWasm);
+        assert_eq!(imported.import(&file), Ok(()));
+        assert_eq!(imported.best_number(), 1);
+        assert_eq!(imported.inspect(|runtime| runtime.balances.balance(&"bob".to_string())), 10);
+
+        // The imported chain can execute the next blocks.
+        assert_eq!(imported.import(&chain.export_blocks(2, 2).unwrap()), Ok(()));
+        assert_eq!(imported.state_root(), chain.state_root());
+        assert_eq!(chain.execute_block(transfer(3, 30)), Ok(()));
+        assert_eq!(imported.execute_block(transfer(3, 30)), Ok(()));
+        assert_eq!(imported.state_root(), chain.state_root());
+    }
+
+    #[test]
+    fn invalid_files() {
+        let mut chain = chain();
+        let file = chain.export_state(2).unwrap();
+        assert!(file.starts_with(&MAGIC));
+
+        let mut other_version = file.clone();
+        other_version[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(FORMAT_VERSION + 1).encode());
+        assert_eq!(
+            chain.import(&other_version),
+            Err("unsupported export file version".to_string())
+        );
+        assert_eq!(chain.import(b"not an export"), Err("not an export file".to_string()));
+
+        // The state root is the last thing in the file.
+        let mut tampered = file.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            chain.import(&tampered),
+            Err("state root does not match the export file".to_string())
+        );
+    }
+
+    #[test]
+    fn archive() {
+        let chain = chain();
+        let alice = "alice".to_string();
+        assert_eq!(chain.balance(&alice, chain.block_hash(1).unwrap()), Ok(180));
+        assert_eq!(chain.balance(&alice, chain.block_hash(2).unwrap()), Ok(150));
+        assert_eq!(chain.balance(&alice, [0; 32]), Err("block is not known"));
+        assert_eq!(chain.export_state(0).map(|_| ()), Ok(()));
+    }
+
+    #[test]
+    fn pruning() {
+        let mut chain = chain().with_pruning(Pruning::KeepLast(1));
+        let alice = "alice".to_string();
+        let hash_1 = chain.block_hash(1).unwrap();
+        assert_eq!(chain.balance(&alice, hash_1), Err("state is pruned"));
+        assert_eq!(chain.balance(&alice, chain.block_hash(2).unwrap()), Ok(150));
+        // The finalized genesis state is kept.
+        assert_eq!(chain.export_state(0).map(|_| ()), Ok(()));
+        assert_eq!(chain.finalize(1), Err("state is pruned"));
+
+        let mut chain = chain.with_pruning(Pruning::KeepLast(2));
+        assert_eq!(chain.execute_block(transfer(3, 30)), Ok(()));
+        assert_eq!(chain.finalize(2), Ok(()));
+        assert_eq!(chain.finalize(1), Err("a later block is already finalized"));
+        assert_eq!(chain.export_state(0).map(|_| ()), Err("state is pruned"));
+
+        assert_eq!(chain.execute_block(transfer(4, 1)), Ok(()));
+        assert_eq!(chain.execute_block(transfer(5, 1)), Ok(()));
+        // The last 2 blocks, and the finalized one.
+        let hash = |number| chain.block_hash(number).unwrap();
+        assert_eq!(chain.balance(&alice, hash(2)), Ok(150));
+        assert_eq!(chain.balance(&alice, hash(3)), Err("state is pruned"));
+        assert_eq!(chain.balance(&alice, hash(4)), Ok(99));
+        assert_eq!(chain.balance(&alice, hash(5)), Ok(88));
+        assert_eq!(chain.finalize(6), Err("block is not known"));
+    }
+}
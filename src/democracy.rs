@@ -0,0 +1,848 @@
+use num::traits::{Bounded, CheckedDiv, CheckedMul, Saturating, Zero};
+
+use crate::support::{
+	ensure_root,
+	storage::{StorageMap, StorageValue},
+	Currency, Decode, DispatchResult, Dispatchable, Encode, Hooks, LockIdentifier,
+	LockableCurrency, OnRuntimeUpgrade, Origin, Reasons, ReservableCurrency,
+};
+
+/// The name of the lock this pallet sets on the funds of voters.
+pub const DEMOCRACY_ID: LockIdentifier = *b"democrac";
+
+pub trait Config: crate::system::Config {
+	/// The call type of the runtime, whose calls can be proposed.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The pallet holding the funds of accounts, usually `balances`. Balances are multiplied by
+	/// the conviction of votes.
+	type Currency: ReservableCurrency<
+			Self::AccountId,
+			Balance: From<u32> + CheckedMul + CheckedDiv + Bounded,
+		> + LockableCurrency<Self::AccountId>;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<EventOf<Self>> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The minimum deposit of a proposal.
+	const MINIMUM_DEPOSIT: BalanceOf<Self>;
+	/// The number of blocks between two launches of the most backed proposal as a referendum.
+	const LAUNCH_PERIOD: Self::BlockNumber;
+	/// The number of blocks a referendum is open for votes.
+	const VOTING_PERIOD: Self::BlockNumber;
+	/// The number of blocks between the approval of a proposal and its dispatch.
+	const ENACTMENT_PERIOD: Self::BlockNumber;
+	/// The number of blocks the funds of a winning vote stay locked after the referendum, for
+	/// each lock period of its conviction.
+	const VOTE_LOCKING_PERIOD: Self::BlockNumber;
+	/// The maximum number of proposals waiting for a referendum.
+	const MAX_PROPOSALS: u32;
+}
+
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// The index of a proposal.
+pub type PropIndex = u32;
+
+/// The index of a referendum.
+pub type ReferendumIndex = u32;
+
+/// How long a voter is ready to lock their funds after a referendum, which multiplies their votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conviction {
+	/// A tenth of a vote per unit of funds, which are not locked after the referendum.
+	None,
+	/// One vote per unit, locked for one period.
+	Locked1x,
+	/// Two votes per unit, locked for two periods.
+	Locked2x,
+	/// Three votes per unit, locked for four periods.
+	Locked3x,
+	/// Four votes per unit, locked for eight periods.
+	Locked4x,
+	/// Five votes per unit, locked for sixteen periods.
+	Locked5x,
+	/// Six votes per unit, locked for thirty two periods.
+	Locked6x,
+}
+
+impl Conviction {
+	/// The number of `VOTE_LOCKING_PERIOD`s the funds of a winning vote stay locked for.
+	pub fn lock_periods(self) -> u32 {
+		match self {
+			Conviction::None => 0,
+			conviction => 1 << (conviction as u32 - 1),
+		}
+	}
+
+	/// The votes `balance` is worth with this conviction.
+	pub fn votes<Balance>(self, balance: Balance) -> Balance
+	where
+		Balance: From<u32> + CheckedMul + CheckedDiv + Bounded + Zero,
+	{
+		match self {
+			Conviction::None => {
+				balance.checked_div(&Balance::from(10)).unwrap_or_else(Balance::zero)
+			},
+			conviction => balance
+				.checked_mul(&Balance::from(conviction as u32))
+				.unwrap_or_else(Balance::max_value),
+		}
+	}
+}
+
+/// Each conviction is encoded as the index of its variant.
+impl Encode for Conviction {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+impl Decode for Conviction {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => Conviction::None,
+			1 => Conviction::Locked1x,
+			2 => Conviction::Locked2x,
+			3 => Conviction::Locked3x,
+			4 => Conviction::Locked4x,
+			5 => Conviction::Locked5x,
+			6 => Conviction::Locked6x,
+			_ => return Err("invalid conviction"),
+		})
+	}
+}
+
+/// A vote in a referendum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vote<Balance> {
+	/// Whether the vote is for the proposal.
+	pub aye: bool,
+	/// The conviction of the vote.
+	pub conviction: Conviction,
+	/// The funds of the voter backing the vote.
+	pub balance: Balance,
+}
+
+impl<Balance: Encode> Encode for Vote<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.aye.encode_to(dest);
+		self.conviction.encode_to(dest);
+		self.balance.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for Vote<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			aye: Decode::decode(input)?,
+			conviction: Decode::decode(input)?,
+			balance: Decode::decode(input)?,
+		})
+	}
+}
+
+/// The votes of a referendum so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tally<Balance> {
+	/// The votes for the proposal, weighted by conviction.
+	pub ayes: Balance,
+	/// The votes against the proposal, weighted by conviction.
+	pub nays: Balance,
+	/// The funds which voted, without conviction.
+	pub turnout: Balance,
+}
+
+impl<Balance> Tally<Balance>
+where
+	Balance: From<u32> + CheckedMul + CheckedDiv + Bounded + Saturating + Zero + Copy,
+{
+	fn add(&mut self, vote: Vote<Balance>) {
+		let votes = vote.conviction.votes(vote.balance);
+		if vote.aye {
+			self.ayes = self.ayes.saturating_add(votes);
+		} else {
+			self.nays = self.nays.saturating_add(votes);
+		}
+		self.turnout = self.turnout.saturating_add(vote.balance);
+	}
+
+	fn remove(&mut self, vote: Vote<Balance>) {
+		let votes = vote.conviction.votes(vote.balance);
+		if vote.aye {
+			self.ayes = self.ayes.saturating_sub(votes);
+		} else {
+			self.nays = self.nays.saturating_sub(votes);
+		}
+		self.turnout = self.turnout.saturating_sub(vote.balance);
+	}
+}
+
+impl<Balance: Encode> Encode for Tally<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(&self.ayes, &self.nays, &self.turnout).encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for Tally<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let (ayes, nays, turnout) = Decode::decode(input)?;
+		Ok(Self { ayes, nays, turnout })
+	}
+}
+
+/// A referendum on a proposal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferendumInfo<BlockNumber, Balance> {
+	/// The referendum is open for votes until the block `end`.
+	Ongoing { end: BlockNumber, proposal: Vec<u8>, tally: Tally<Balance> },
+	/// The referendum ended at the block `end`, and the proposal was `approved` or not.
+	Finished { approved: bool, end: BlockNumber },
+}
+
+/// Each referendum is encoded as the index of its variant, followed by its fields.
+impl<BlockNumber: Encode, Balance: Encode> Encode for ReferendumInfo<BlockNumber, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			ReferendumInfo::Ongoing { end, proposal, tally } => {
+				(0u8, (end, proposal, tally)).encode_to(dest)
+			},
+			ReferendumInfo::Finished { approved, end } => (1u8, (approved, end)).encode_to(dest),
+		}
+	}
+}
+
+impl<BlockNumber: Decode, Balance: Decode> Decode for ReferendumInfo<BlockNumber, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => {
+				let (end, proposal, tally) = Decode::decode(input)?;
+				ReferendumInfo::Ongoing { end, proposal, tally }
+			},
+			1 => {
+				let (approved, end) = Decode::decode(input)?;
+				ReferendumInfo::Finished { approved, end }
+			},
+			_ => return Err("invalid referendum"),
+		})
+	}
+}
+
+/// A referendum, for a runtime `T`.
+pub type ReferendumInfoOf<T> =
+	ReferendumInfo<<T as crate::system::Config>::BlockNumber, BalanceOf<T>>;
+
+/// The votes of an account, which lock its funds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Voting<Balance, BlockNumber> {
+	/// The votes of the account, in referendums which are ongoing or whose vote was not removed.
+	pub votes: Vec<(ReferendumIndex, Vote<Balance>)>,
+	/// The funds locked by removed votes, and the block they are locked until.
+	pub prior: Option<(BlockNumber, Balance)>,
+}
+
+impl<Balance, BlockNumber> Default for Voting<Balance, BlockNumber> {
+	fn default() -> Self {
+		Self { votes: Vec::new(), prior: None }
+	}
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for Voting<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.votes.encode_to(dest);
+		self.prior.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for Voting<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { votes: Decode::decode(input)?, prior: Decode::decode(input)? })
+	}
+}
+
+/// The votes of an account, for a runtime `T`.
+pub type VotingOf<T> = Voting<BalanceOf<T>, <T as crate::system::Config>::BlockNumber>;
+
+/// The proposals waiting for a referendum: their index, encoded call and proposer.
+type PublicProps<AccountId> = StorageValue<Vec<(PropIndex, Vec<u8>, AccountId)>>;
+
+/// The calls of approved proposals, by the block they are dispatched at.
+type Enactments<BlockNumber> = StorageMap<BlockNumber, Vec<(ReferendumIndex, Vec<u8>)>>;
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId, BalanceOf<T>>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId, Balance> {
+	/// A proposal was made, with the given deposit.
+	Proposed { proposal_index: PropIndex, deposit: Balance },
+	/// `seconder` backed a proposal with a deposit.
+	Seconded { seconder: AccountId, prop_index: PropIndex },
+	/// A proposal was launched as a referendum.
+	Started { ref_index: ReferendumIndex },
+	/// `voter` voted in a referendum.
+	Voted { voter: AccountId, ref_index: ReferendumIndex, vote: Vote<Balance> },
+	/// A referendum approved its proposal, which will be dispatched after the enactment period.
+	Passed { ref_index: ReferendumIndex },
+	/// A referendum rejected its proposal.
+	NotPassed { ref_index: ReferendumIndex },
+	/// A referendum was cancelled.
+	Cancelled { ref_index: ReferendumIndex },
+	/// The proposal approved by a referendum was dispatched, with the given result.
+	Executed { ref_index: ReferendumIndex, result: Result<(), String> },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode, Balance: Encode> Encode for Event<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::Proposed { proposal_index, deposit } => {
+				(0u8, (proposal_index, deposit)).encode_to(dest)
+			},
+			Event::Seconded { seconder, prop_index } => {
+				(1u8, (seconder, prop_index)).encode_to(dest)
+			},
+			Event::Started { ref_index } => (2u8, ref_index).encode_to(dest),
+			Event::Voted { voter, ref_index, vote } => {
+				(3u8, (voter, ref_index, vote)).encode_to(dest)
+			},
+			Event::Passed { ref_index } => (4u8, ref_index).encode_to(dest),
+			Event::NotPassed { ref_index } => (5u8, ref_index).encode_to(dest),
+			Event::Cancelled { ref_index } => (6u8, ref_index).encode_to(dest),
+			Event::Executed { ref_index, result } => (7u8, (ref_index, result)).encode_to(dest),
+		}
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Event<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => {
+				let (proposal_index, deposit) = Decode::decode(input)?;
+				Event::Proposed { proposal_index, deposit }
+			},
+			1 => {
+				let (seconder, prop_index) = Decode::decode(input)?;
+				Event::Seconded { seconder, prop_index }
+			},
+			2 => Event::Started { ref_index: Decode::decode(input)? },
+			3 => {
+				let (voter, ref_index, vote) = Decode::decode(input)?;
+				Event::Voted { voter, ref_index, vote }
+			},
+			4 => Event::Passed { ref_index: Decode::decode(input)? },
+			5 => Event::NotPassed { ref_index: Decode::decode(input)? },
+			6 => Event::Cancelled { ref_index: Decode::decode(input)? },
+			7 => {
+				let (ref_index, result) = Decode::decode(input)?;
+				Event::Executed { ref_index, result }
+			},
+			_ => return Err("invalid democracy event"),
+		})
+	}
+}
+
+/// This is the Democracy Module.
+/// It lets token holders decide on proposals, which are dispatched as `Root` once approved.
+///
+/// Accounts propose calls, with a deposit, which others can second. Every launch period, the
+/// proposal with the most backing becomes a referendum, open for votes during the voting period.
+/// Votes are weighted by the conviction of the voter, who keeps their funds locked longer after
+/// the referendum in exchange. A referendum passes with a simple majority of the weighted votes.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The index of the next proposal.
+	public_prop_count: StorageValue<PropIndex>,
+	// The proposals waiting for a referendum.
+	public_props: PublicProps<T::AccountId>,
+	// The accounts which backed each proposal, and the deposit each of them reserved.
+	deposit_of: StorageMap<PropIndex, (Vec<T::AccountId>, BalanceOf<T>)>,
+	// The index of the next referendum.
+	referendum_count: StorageValue<ReferendumIndex>,
+	// The referendums.
+	referendum_info_of: StorageMap<ReferendumIndex, ReferendumInfo<T::BlockNumber, BalanceOf<T>>>,
+	// The ongoing referendums, by the block they end at.
+	referendums_ending: StorageMap<T::BlockNumber, Vec<ReferendumIndex>>,
+	// The votes of each account.
+	voting_of: StorageMap<T::AccountId, Voting<BalanceOf<T>, T::BlockNumber>>,
+	// The approved proposals, by the block they are dispatched at.
+	enactments: Enactments<T::BlockNumber>,
+	// The block the next referendum is launched at.
+	next_launch: StorageValue<T::BlockNumber>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the democracy module.
+	pub fn new() -> Self {
+		Self {
+			public_prop_count: StorageValue::new("Democracy", "PublicPropCount"),
+			public_props: StorageValue::new("Democracy", "PublicProps"),
+			deposit_of: StorageMap::new("Democracy", "DepositOf"),
+			referendum_count: StorageValue::new("Democracy", "ReferendumCount"),
+			referendum_info_of: StorageMap::new("Democracy", "ReferendumInfoOf"),
+			referendums_ending: StorageMap::new("Democracy", "ReferendumsEnding"),
+			voting_of: StorageMap::new("Democracy", "VotingOf"),
+			enactments: StorageMap::new("Democracy", "Enactments"),
+			next_launch: StorageValue::new("Democracy", "NextLaunch"),
+		}
+	}
+
+	/// The indices of the proposals waiting for a referendum, and their proposers.
+	pub fn public_props(&self) -> Vec<(PropIndex, T::AccountId)> {
+		let props = self.public_props.get().unwrap_or_default();
+		props.into_iter().map(|(index, _, proposer)| (index, proposer)).collect()
+	}
+
+	/// The referendum `ref_index`, if it exists.
+	pub fn referendum_info(&self, ref_index: ReferendumIndex) -> Option<ReferendumInfoOf<T>> {
+		self.referendum_info_of.get(&ref_index)
+	}
+
+	/// The votes of `who`.
+	pub fn voting_of(&self, who: &T::AccountId) -> VotingOf<T> {
+		self.voting_of.get(who).unwrap_or_default()
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// Lock the funds `who` voted with, in ongoing referendums or until their prior lock expires.
+	fn update_lock(&mut self, who: &T::AccountId) {
+		let now = crate::system::Pallet::<T>::new().block_number();
+		let mut voting = self.voting_of(who);
+		voting.prior = voting.prior.filter(|(until, _)| *until > now);
+		let prior = voting.prior.map(|(_, balance)| balance);
+		let locked =
+			voting.votes.iter().map(|(_, vote)| vote.balance).chain(prior).fold(
+				BalanceOf::<T>::zero(),
+				|max, balance| if balance > max { balance } else { max },
+			);
+
+		let mut currency = T::Currency::default();
+		if locked.is_zero() {
+			currency.remove_lock(DEMOCRACY_ID, who);
+			self.voting_of.remove(who);
+		} else {
+			currency.set_lock(DEMOCRACY_ID, who, locked, Reasons::Misc);
+			self.voting_of.insert(who.clone(), voting);
+		}
+	}
+
+	/// Launch the proposal with the most backing as a referendum, if there is one.
+	fn launch_public(&mut self, now: T::BlockNumber) {
+		let mut props = self.public_props.get().unwrap_or_default();
+		let backing = |index: &PropIndex| {
+			self.deposit_of.get(index).map_or(BalanceOf::<T>::zero(), |(backers, deposit)| {
+				BalanceOf::<T>::from(backers.len() as u32)
+					.checked_mul(&deposit)
+					.unwrap_or_else(BalanceOf::<T>::max_value)
+			})
+		};
+		let Some(best) = (0..props.len()).reduce(|best, i| {
+			if backing(&props[i].0) > backing(&props[best].0) {
+				i
+			} else {
+				best
+			}
+		}) else {
+			return;
+		};
+		let (prop_index, proposal, _) = props.remove(best);
+		self.public_props.put(props);
+
+		if let Some((backers, deposit)) = self.deposit_of.remove(&prop_index) {
+			let mut currency = T::Currency::default();
+			for backer in backers {
+				currency.unreserve(&backer, deposit);
+			}
+		}
+		let ref_index = self.referendum_count.get().unwrap_or(0);
+		self.referendum_count.put(ref_index + 1);
+		let mut end = now;
+		end += T::VOTING_PERIOD;
+		let zero = BalanceOf::<T>::zero();
+		let tally = Tally { ayes: zero, nays: zero, turnout: zero };
+		self.referendum_info_of.insert(ref_index, ReferendumInfo::Ongoing { end, proposal, tally });
+		let mut ending = self.referendums_ending.get(&end).unwrap_or_default();
+		ending.push(ref_index);
+		self.referendums_ending.insert(end, ending);
+		self.deposit_event(Event::Started { ref_index });
+	}
+
+	/// Close the referendums ending at `now`, queueing the approved proposals for enactment.
+	fn bake_referendums(&mut self, now: T::BlockNumber) {
+		for ref_index in self.referendums_ending.remove(&now).unwrap_or_default() {
+			let Some(ReferendumInfo::Ongoing { end, proposal, tally }) =
+				self.referendum_info_of.get(&ref_index)
+			else {
+				continue;
+			};
+			let approved = tally.ayes > tally.nays;
+			self.referendum_info_of.insert(ref_index, ReferendumInfo::Finished { approved, end });
+			if approved {
+				let mut when = now;
+				when += T::ENACTMENT_PERIOD;
+				let mut enactments = self.enactments.get(&when).unwrap_or_default();
+				enactments.push((ref_index, proposal));
+				self.enactments.insert(when, enactments);
+				self.deposit_event(Event::Passed { ref_index });
+			} else {
+				self.deposit_event(Event::NotPassed { ref_index });
+			}
+		}
+	}
+
+	/// Dispatch the approved proposals due at `now`, as `Root`.
+	fn enact(&mut self, now: T::BlockNumber) {
+		for (ref_index, proposal) in self.enactments.remove(&now).unwrap_or_default() {
+			let result = T::RuntimeCall::decode_all(&proposal)
+				.map_err(String::from)
+				.and_then(|call| call.dispatch(Origin::Root).map_err(String::from));
+			self.deposit_event(Event::Executed { ref_index, result });
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose `proposal`, reserving `value` from the `caller` until it is launched as a
+	/// referendum.
+	// The calls are boxed, since they are part of the call type of the runtime.
+	#[allow(clippy::boxed_local)]
+	#[call_index(0)]
+	pub fn propose(
+		&mut self,
+		caller: T::AccountId,
+		proposal: Box<T::RuntimeCall>,
+		value: BalanceOf<T>,
+	) -> DispatchResult {
+		if value < T::MINIMUM_DEPOSIT {
+			return Err("deposit is below the minimum");
+		}
+		let mut props = self.public_props.get().unwrap_or_default();
+		if props.len() >= T::MAX_PROPOSALS as usize {
+			return Err("too many proposals");
+		}
+		T::Currency::default().reserve(&caller, value)?;
+		let index = self.public_prop_count.get().unwrap_or(0);
+		self.public_prop_count.put(index + 1);
+		props.push((index, proposal.encode(), caller.clone()));
+		self.public_props.put(props);
+		self.deposit_of.insert(index, (vec![caller], value));
+		self.deposit_event(Event::Proposed { proposal_index: index, deposit: value });
+		Ok(())
+	}
+
+	/// Back the proposal `proposal`, reserving the same deposit as its proposer until it is
+	/// launched as a referendum.
+	#[call_index(1)]
+	pub fn second(&mut self, caller: T::AccountId, proposal: PropIndex) -> DispatchResult {
+		let (mut backers, deposit) =
+			self.deposit_of.get(&proposal).ok_or("proposal does not exist")?;
+		T::Currency::default().reserve(&caller, deposit)?;
+		backers.push(caller.clone());
+		self.deposit_of.insert(proposal, (backers, deposit));
+		self.deposit_event(Event::Seconded { seconder: caller, prop_index: proposal });
+		Ok(())
+	}
+
+	/// Vote in the ongoing referendum `ref_index`, replacing any previous vote of the `caller`
+	/// in it. The funds of the vote are locked until the vote is removed.
+	#[call_index(2)]
+	pub fn vote(
+		&mut self,
+		caller: T::AccountId,
+		ref_index: ReferendumIndex,
+		vote: Vote<BalanceOf<T>>,
+	) -> DispatchResult {
+		let Some(ReferendumInfo::Ongoing { end, proposal, mut tally }) =
+			self.referendum_info_of.get(&ref_index)
+		else {
+			return Err("referendum is not ongoing");
+		};
+		if vote.balance > T::Currency::default().free_balance(&caller) {
+			return Err("insufficient funds to vote");
+		}
+		let mut voting = self.voting_of(&caller);
+		match voting.votes.iter_mut().find(|(index, _)| *index == ref_index) {
+			Some((_, previous)) => {
+				tally.remove(*previous);
+				*previous = vote;
+			},
+			None => voting.votes.push((ref_index, vote)),
+		}
+		tally.add(vote);
+		self.referendum_info_of.insert(ref_index, ReferendumInfo::Ongoing { end, proposal, tally });
+		self.voting_of.insert(caller.clone(), voting);
+		self.update_lock(&caller);
+		self.deposit_event(Event::Voted { voter: caller, ref_index, vote });
+		Ok(())
+	}
+
+	/// Remove the vote of the `caller` in the referendum `ref_index`. If the referendum is
+	/// ongoing, the vote no longer counts. If it ended and the vote won, its funds stay locked
+	/// for the lock periods of its conviction.
+	#[call_index(3)]
+	pub fn remove_vote(
+		&mut self,
+		caller: T::AccountId,
+		ref_index: ReferendumIndex,
+	) -> DispatchResult {
+		let mut voting = self.voting_of(&caller);
+		let position = voting
+			.votes
+			.iter()
+			.position(|(index, _)| *index == ref_index)
+			.ok_or("caller did not vote in the referendum")?;
+		let (_, vote) = voting.votes.remove(position);
+		match self.referendum_info_of.get(&ref_index) {
+			Some(ReferendumInfo::Ongoing { end, proposal, mut tally }) => {
+				tally.remove(vote);
+				let info = ReferendumInfo::Ongoing { end, proposal, tally };
+				self.referendum_info_of.insert(ref_index, info);
+			},
+			Some(ReferendumInfo::Finished { approved, end }) if approved == vote.aye => {
+				let mut until = end;
+				for _ in 0..vote.conviction.lock_periods() {
+					until += T::VOTE_LOCKING_PERIOD;
+				}
+				let (prior_until, prior_balance) = voting.prior.unwrap_or((until, vote.balance));
+				let until = if prior_until > until { prior_until } else { until };
+				let balance =
+					if prior_balance > vote.balance { prior_balance } else { vote.balance };
+				voting.prior = Some((until, balance));
+			},
+			// Losing votes, and votes in cancelled referendums, are not locked.
+			_ => {},
+		}
+		self.voting_of.insert(caller.clone(), voting);
+		self.update_lock(&caller);
+		Ok(())
+	}
+
+	/// Unlock the funds of `target` which are no longer locked by its votes.
+	#[call_index(4)]
+	pub fn unlock(&mut self, _caller: T::AccountId, target: T::AccountId) -> DispatchResult {
+		self.update_lock(&target);
+		Ok(())
+	}
+
+	/// Cancel the referendum `ref_index`, which must be ongoing.
+	#[call_index(5)]
+	pub fn cancel_referendum(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		ref_index: ReferendumIndex,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		let Some(ReferendumInfo::Ongoing { end, .. }) = self.referendum_info_of.get(&ref_index)
+		else {
+			return Err("referendum is not ongoing");
+		};
+		self.referendum_info_of.remove(&ref_index);
+		let mut ending = self.referendums_ending.get(&end).unwrap_or_default();
+		ending.retain(|index| *index != ref_index);
+		if ending.is_empty() {
+			self.referendums_ending.remove(&end);
+		} else {
+			self.referendums_ending.insert(end, ending);
+		}
+		self.deposit_event(Event::Cancelled { ref_index });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// Close the referendums ending at `now`, dispatch the proposals due, and launch a new
+	/// referendum every launch period.
+	fn on_initialize(&mut self, now: T::BlockNumber) {
+		self.bake_referendums(now);
+		self.enact(now);
+		if self.next_launch.get().is_none_or(|next_launch| now >= next_launch) {
+			let mut next_launch = now;
+			next_launch += T::LAUNCH_PERIOD;
+			self.next_launch.put(next_launch);
+			self.launch_public(now);
+		}
+	}
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Conviction, Event, EventOf, Pallet, ReferendumInfo, Vote};
+	use crate::support::{
+		testing::{self, RootOrigin},
+		Hooks, Origin, RuntimeVersion,
+	};
+	use crate::{balances, system};
+
+	// Proposals only pass through referendums, which dispatch them as `Root`.
+	type TestCall = testing::TestCall<RootOrigin>;
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type Currency = balances::Pallet<Self>;
+		type RuntimeEvent = EventOf<Self>;
+		const MINIMUM_DEPOSIT: u128 = 10;
+		const LAUNCH_PERIOD: u32 = 2;
+		const VOTING_PERIOD: u32 = 2;
+		const ENACTMENT_PERIOD: u32 = 1;
+		const VOTE_LOCKING_PERIOD: u32 = 4;
+		const MAX_PROPOSALS: u32 = 2;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+	const CHARLIE: u64 = 3;
+
+	fn setup() -> (Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance(&ALICE, 100);
+		balances.set_balance(&BOB, 100);
+		balances.set_balance(&CHARLIE, 15);
+		(Pallet::new(), balances)
+	}
+
+	fn run_to_block(democracy: &mut Pallet<TestConfig>, n: u32) {
+		let mut system = system::Pallet::<TestConfig>::new();
+		while system.block_number() < n {
+			system.inc_block_number();
+			democracy.on_initialize(system.block_number());
+		}
+	}
+
+	fn aye(balance: u128) -> Vote<u128> {
+		Vote { aye: true, conviction: Conviction::Locked1x, balance }
+	}
+
+	#[test]
+	fn proposals() {
+		let (mut democracy, balances) = setup();
+		let call = |value| Box::new(TestCall::new(value));
+
+		assert_eq!(democracy.propose(ALICE, call(1), 9), Err("deposit is below the minimum"));
+		assert_eq!(democracy.propose(CHARLIE, call(1), 20), Err("Not enough funds."));
+		assert_eq!(democracy.propose(ALICE, call(1), 10), Ok(()));
+		assert_eq!(democracy.propose(BOB, call(2), 10), Ok(()));
+		assert_eq!(democracy.propose(BOB, call(3), 10), Err("too many proposals"));
+
+		assert_eq!(democracy.second(BOB, 2), Err("proposal does not exist"));
+		assert_eq!(democracy.second(CHARLIE, 0), Ok(()));
+		assert_eq!(democracy.second(CHARLIE, 1), Err("Not enough funds."));
+		assert_eq!(balances.reserved_balance(&CHARLIE), 10);
+
+		// The most backed proposal is launched first, which returns its deposits.
+		run_to_block(&mut democracy, 1);
+		assert_eq!(democracy.public_props(), vec![(1, BOB)]);
+		assert_eq!(balances.reserved_balance(&CHARLIE), 0);
+		assert_eq!(democracy.second(CHARLIE, 0), Err("proposal does not exist"));
+	}
+
+	#[test]
+	fn votes() {
+		let (mut democracy, balances) = setup();
+		assert_eq!(democracy.vote(ALICE, 0, aye(10)), Err("referendum is not ongoing"));
+		assert_eq!(democracy.propose(ALICE, Box::new(TestCall::new(1)), 10), Ok(()));
+		run_to_block(&mut democracy, 1);
+
+		assert_eq!(democracy.vote(ALICE, 0, aye(101)), Err("insufficient funds to vote"));
+		assert_eq!(democracy.remove_vote(ALICE, 0), Err("caller did not vote in the referendum"));
+		assert_eq!(democracy.vote(ALICE, 0, aye(50)), Ok(()));
+		assert_eq!(democracy.vote(BOB, 0, Vote { aye: false, ..aye(40) }), Ok(()));
+		assert_eq!(democracy.vote(BOB, 0, Vote { aye: false, ..aye(60) }), Ok(()));
+		assert_eq!(democracy.remove_vote(BOB, 0), Ok(()));
+		assert!(balances.locks(&BOB).is_empty());
+
+		// The winning vote stays locked after the referendum, until alice unlocks it.
+		run_to_block(&mut democracy, 3);
+		assert_eq!(
+			democracy.referendum_info(0),
+			Some(ReferendumInfo::Finished { approved: true, end: 3 })
+		);
+		assert_eq!(democracy.vote(ALICE, 0, aye(50)), Err("referendum is not ongoing"));
+		assert_eq!(democracy.remove_vote(ALICE, 0), Ok(()));
+		assert_eq!(democracy.voting_of(&ALICE).prior, Some((7, 50)));
+		assert_eq!(democracy.unlock(BOB, ALICE), Ok(()));
+		assert_eq!(democracy.voting_of(&ALICE).prior, Some((7, 50)));
+		run_to_block(&mut democracy, 7);
+		assert_eq!(democracy.unlock(BOB, ALICE), Ok(()));
+		assert_eq!(democracy.voting_of(&ALICE).prior, None);
+	}
+
+	#[test]
+	fn referendums() {
+		let (mut democracy, _) = setup();
+		assert_eq!(democracy.propose(ALICE, Box::new(TestCall::new(1)), 10), Ok(()));
+		assert_eq!(democracy.propose(BOB, Box::new(TestCall::new(2)), 20), Ok(()));
+
+		// Bob's proposal is launched at block 1 and passes at block 3, when alice's proposal is
+		// launched. Each referendum is only closed at its end.
+		run_to_block(&mut democracy, 1);
+		assert_eq!(democracy.vote(ALICE, 0, aye(10)), Ok(()));
+		run_to_block(&mut democracy, 4);
+		assert_eq!(testing::dispatched(), vec![(2, Origin::Root)]);
+		assert!(matches!(democracy.referendum_info(1), Some(ReferendumInfo::Ongoing { .. })));
+		run_to_block(&mut democracy, 5);
+		assert_eq!(
+			democracy.referendum_info(1),
+			Some(ReferendumInfo::Finished { approved: false, end: 5 })
+		);
+
+		let events = system::Pallet::<TestConfig>::new().events();
+		assert_eq!(
+			events[2..],
+			[
+				Event::Started { ref_index: 0 },
+				Event::Voted { voter: ALICE, ref_index: 0, vote: aye(10) },
+				Event::Passed { ref_index: 0 },
+				Event::Started { ref_index: 1 },
+				Event::Executed { ref_index: 0, result: Ok(()) },
+				Event::NotPassed { ref_index: 1 },
+			]
+		);
+		testing::assert_round_trip(events);
+	}
+
+	#[test]
+	fn cancel_referendum() {
+		let (mut democracy, _) = setup();
+		assert_eq!(democracy.cancel_referendum(Origin::Root, 0), Err("referendum is not ongoing"));
+		assert_eq!(democracy.propose(ALICE, Box::new(TestCall::new(1)), 10), Ok(()));
+		run_to_block(&mut democracy, 1);
+
+		assert_eq!(
+			democracy.cancel_referendum(Origin::Signed(ALICE), 0),
+			Err("bad origin: expected root")
+		);
+		assert_eq!(democracy.cancel_referendum(Origin::Root, 0), Ok(()));
+		assert_eq!(democracy.cancel_referendum(Origin::Root, 0), Err("referendum is not ongoing"));
+		assert_eq!(democracy.referendum_info(0), None);
+		assert_eq!(democracy.referendums_ending.get(&3), None);
+		run_to_block(&mut democracy, 3);
+		let events = system::Pallet::<TestConfig>::new().events();
+		assert_eq!(events.last(), Some(&Event::Cancelled { ref_index: 0 }));
+	}
+}
@@ -0,0 +1,18 @@
+pub mod assets;
+pub mod balances;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod chain;
+pub mod democracy;
+pub mod multisig;
+pub mod nfts;
+pub mod proof_of_existence;
+pub mod proxy;
+pub mod runtime;
+pub mod scheduler;
+pub mod sudo;
+pub mod support;
+pub mod system;
+pub mod timestamp;
+pub mod treasury;
+pub mod utility;
+pub mod vesting;
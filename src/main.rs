@@ -1,69 +1,49 @@
-mod balances;
-mod support;
-mod system;
-mod proof_of_existence;
-
-use crate::support::Dispatch;
-
-// These are the concrete types we will use in our simple state machine.
-// Modules are configured for these types directly, and they satisfy all of our
-// trait requirements.
-mod types {
-    pub type AccountId = String;
-    pub type Balance = u128;
-    pub type BlockNumber = u32;
-    pub type Nonce = u32;
-    pub type Content = &'static str;
-    pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
-    pub type Header = crate::support::Header<BlockNumber>;
-    pub type Block = crate::support::Block<Header, Extrinsic>;
-}
-
-// This is our main Runtime.
-// It accumulates all of the different pallets we want to use.
-#[derive(Debug)]
-#[macros::runtime]
-pub struct Runtime {
-    system: system::Pallet<Self>,
-    balances: balances::Pallet<Self>,
-    proof_of_existence: proof_of_existence::Pallet<Self>,
-}
-
-impl system::Config for Runtime {
-    type AccountId = types::AccountId;
-    type BlockNumber = types::BlockNumber;
-    type Nonce = types::Nonce;
-}
-
-impl balances::Config for Runtime {
-    type Balance = types::Balance;
-}
-
-impl proof_of_existence::Config for Runtime {
-    type Content = types::Content;
-}
+use rust_state_machine::chain::Chain;
+use rust_state_machine::runtime::{types, ExecutionStrategy, Runtime, RuntimeCall};
+use rust_state_machine::support::{hashing, storage};
+use rust_state_machine::{balances, proof_of_existence, support, timestamp};
 
 fn main() {
-    // initialize runtime
+    // initialize the genesis state
     let alice = "alice".to_string();
     let bob = "bob".to_string();
     let charlie = "charlie".to_string();
-    let mut r = Runtime::new();
-    r.balances.set_balance(&alice, 100);
+    let mut genesis = storage::State::new();
+    // alice keeps enough to pay her fees and reserve the claim deposit, and administers the chain
+    storage::execute_with(&mut genesis, || {
+        let mut runtime = Runtime::new();
+        support::OnRuntimeUpgrade::on_genesis(&mut runtime);
+        runtime.balances.set_balance(&alice, 160);
+        runtime.sudo.initialize_key(alice.clone());
+    });
+    let mut chain = Chain::new(genesis.clone(), ExecutionStrategy::NativeElseWasm);
+
+    // the block author starts each block with the time, as an inherent
+    let start = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("the clock is after the Unix epoch")
+        .as_millis() as u64;
+    let set_time = |block_number: u32| support::Extrinsic {
+        caller: None,
+        call: RuntimeCall::timestamp(timestamp::Call::set {
+            now: start + 6_000 * block_number as u64,
+        }),
+    };
 
     // execute two transactions in one block
     let block_1 = types::Block {
         header: support::Header { block_number: 1 },
         extrinsics: vec![
+            set_time(1),
             support::Extrinsic {
-                caller: alice.clone(),
+                caller: Some(alice.clone()),
                 call: RuntimeCall::balances(balances::Call::transfer {
                     to: bob.clone(),
                     amount: 69,
                 }),
             },
             support::Extrinsic {
-                caller: alice.clone(),
+                caller: Some(alice.clone()),
                 call: RuntimeCall::balances(balances::Call::transfer {
                     to: charlie,
                     amount: 31,
@@ -75,10 +55,11 @@ fn main() {
     let block_2 = types::Block {
         header: support::Header { block_number: 2 },
         extrinsics: vec![
+            set_time(2),
             support::Extrinsic {
-                caller: alice.clone(),
+                caller: Some(alice.clone()),
                 call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
-                    claim: "hi, bob"
+                    claim: hashing::blake2_256(b"hi, bob"),
                 })
             },
         ]
@@ -87,11 +68,14 @@ fn main() {
     let block_3 = types::Block {
         header: support::Header { block_number: 3 },
         extrinsics: vec![
+            set_time(3),
             support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "hi, bob"
-                })
+                caller: Some(alice.clone()),
+                call: RuntimeCall::proof_of_existence(
+                    proof_of_existence::Call::create_claim_from_bytes {
+                        content: b"hi, bob".to_vec(),
+                    },
+                ),
             },
         ]
     };
@@ -99,10 +83,11 @@ fn main() {
     let block_4 = types::Block {
         header: support::Header { block_number: 4 },
         extrinsics: vec![
+            set_time(4),
             support::Extrinsic {
-                caller: bob.clone(),
+                caller: Some(bob.clone()),
                 call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
-                    claim: "hi, bob"
+                    claim: hashing::blake2_256(b"hi, bob"),
                 })
             },
         ]
@@ -111,20 +96,35 @@ fn main() {
     let block_5 = types::Block {
         header: support::Header { block_number: 5 },
         extrinsics: vec![
+            set_time(5),
             support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "hi, bob"
-                })
+                caller: Some(alice.clone()),
+                call: RuntimeCall::proof_of_existence(
+                    proof_of_existence::Call::create_claim_from_bytes {
+                        content: b"hi, bob".to_vec(),
+                    },
+                ),
             },
         ]
     };
 
-    r.execute_block(block_1).expect("invalid block");
-    r.execute_block(block_2).expect("invalid block");
-    r.execute_block(block_3).expect("invalid block");
-    r.execute_block(block_4).expect("invalid block");
-    r.execute_block(block_5).expect("invalid block");
+    chain.execute_block(block_1).expect("invalid block");
+    chain.execute_block(block_2).expect("invalid block");
+    chain.execute_block(block_3).expect("invalid block");
+    chain.execute_block(block_4).expect("invalid block");
+    chain.execute_block(block_5).expect("invalid block");
+
+    chain.inspect(|r| println!("{:#?}", r));
+    let block_1 = chain.block_hash(1).expect("block 1 was executed");
+    println!("alice had {:?} after block 1", chain.balance(&alice, block_1));
 
-    println!("{:#?}", r);
+    // move the chain to another node through an export file
+    let path = std::env::temp_dir().join("rust-state-machine.export");
+    std::fs::write(&path, chain.export_blocks(1, 5).expect("blocks are known"))
+        .expect("failed to write the export file");
+    let mut imported = Chain::new(genesis, ExecutionStrategy::NativeElseWasm);
+    imported
+        .import(&std::fs::read(&path).expect("failed to read the export file"))
+        .expect("invalid export file");
+    assert_eq!(imported.state_root(), chain.state_root());
 }
@@ -0,0 +1,452 @@
+use crate::support::{
+	hashing::blake2_256, storage::StorageMap, Currency, Decode, DeriveAccount, DispatchResult,
+	Dispatchable, Encode, Hooks, OnRuntimeUpgrade, Origin, ReservableCurrency,
+};
+
+pub trait Config: crate::system::Config<AccountId: DeriveAccount> {
+	/// The call type of the runtime, which multisig accounts dispatch.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The pallet holding the funds of accounts, usually `balances`.
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<EventOf<Self>> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The funds reserved from the signatory starting a multisig operation, until it is executed
+	/// or cancelled.
+	const DEPOSIT: BalanceOf<Self>;
+	/// The maximum number of signatories of a multisig account.
+	const MAX_SIGNATORIES: u32;
+}
+
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// The hash of an encoded call, which signatories approve.
+pub type CallHash = [u8; 32];
+
+/// A multisig operation waiting for approvals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multisig<AccountId, BlockNumber, Balance> {
+	/// The block the operation was started at.
+	pub when: BlockNumber,
+	/// The funds reserved from the `depositor`.
+	pub deposit: Balance,
+	/// The signatory which started the operation.
+	pub depositor: AccountId,
+	/// The signatories which approved the operation so far, sorted.
+	pub approvals: Vec<AccountId>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode, Balance: Encode> Encode
+	for Multisig<AccountId, BlockNumber, Balance>
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.when.encode_to(dest);
+		self.deposit.encode_to(dest);
+		self.depositor.encode_to(dest);
+		self.approvals.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, BlockNumber: Decode, Balance: Decode> Decode
+	for Multisig<AccountId, BlockNumber, Balance>
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			when: Decode::decode(input)?,
+			deposit: Decode::decode(input)?,
+			depositor: Decode::decode(input)?,
+			approvals: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A multisig operation, for a runtime `T`.
+pub type MultisigOf<T> = Multisig<
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::BlockNumber,
+	BalanceOf<T>,
+>;
+
+/// The pending multisig operations, by account and call hash.
+type Multisigs<AccountId, BlockNumber, Balance> =
+	StorageMap<(AccountId, CallHash), Multisig<AccountId, BlockNumber, Balance>>;
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId> {
+	/// `approving` started a multisig operation of `multisig`, for the call `call_hash`.
+	NewMultisig { approving: AccountId, multisig: AccountId, call_hash: CallHash },
+	/// `approving` approved a pending multisig operation.
+	MultisigApproval { approving: AccountId, multisig: AccountId, call_hash: CallHash },
+	/// The approval of `approving` completed a multisig operation, whose call was dispatched.
+	MultisigExecuted {
+		approving: AccountId,
+		multisig: AccountId,
+		call_hash: CallHash,
+		result: Result<(), String>,
+	},
+	/// `cancelling` cancelled a pending multisig operation.
+	MultisigCancelled { cancelling: AccountId, multisig: AccountId, call_hash: CallHash },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode> Encode for Event<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::NewMultisig { approving, multisig, call_hash } => {
+				(0u8, (approving, multisig, call_hash)).encode_to(dest)
+			},
+			Event::MultisigApproval { approving, multisig, call_hash } => {
+				(1u8, (approving, multisig, call_hash)).encode_to(dest)
+			},
+			Event::MultisigExecuted { approving, multisig, call_hash, result } => {
+				(2u8, (approving, multisig, call_hash)).encode_to(dest);
+				result.encode_to(dest);
+			},
+			Event::MultisigCancelled { cancelling, multisig, call_hash } => {
+				(3u8, (cancelling, multisig, call_hash)).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<AccountId: Decode> Decode for Event<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let index = u8::decode(input)?;
+		if index > 3 {
+			return Err("invalid multisig event");
+		}
+		let (account, multisig, call_hash) = Decode::decode(input)?;
+		Ok(match index {
+			0 => Event::NewMultisig { approving: account, multisig, call_hash },
+			1 => Event::MultisigApproval { approving: account, multisig, call_hash },
+			2 => Event::MultisigExecuted {
+				approving: account,
+				multisig,
+				call_hash,
+				result: Decode::decode(input)?,
+			},
+			_ => Event::MultisigCancelled { cancelling: account, multisig, call_hash },
+		})
+	}
+}
+
+/// This is the Multisig Module.
+/// It lets a set of signatories control an account together: a call of the account is only
+/// dispatched once `threshold` of them approved it.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The pending operations of each multisig account, by the hash of their call.
+	multisigs: Multisigs<T::AccountId, T::BlockNumber, BalanceOf<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the multisig module.
+	pub fn new() -> Self {
+		Self { multisigs: StorageMap::new("Multisig", "Multisigs") }
+	}
+
+	/// The account controlled by the `signatories`, `threshold` of which must approve its calls.
+	/// The order of the signatories doesn't matter.
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+		let mut signatories = signatories.to_vec();
+		signatories.sort();
+		T::AccountId::derive_account(&(b"multisig".to_vec(), signatories, threshold).encode())
+	}
+
+	/// The pending operation of `multisig` for the call `call_hash`, if any.
+	pub fn multisig(&self, multisig: &T::AccountId, call_hash: &CallHash) -> Option<MultisigOf<T>> {
+		self.multisigs.get(&(multisig.clone(), *call_hash))
+	}
+
+	/// The hash of `call`, which signatories approve.
+	pub fn call_hash(call: &T::RuntimeCall) -> CallHash {
+		blake2_256(&call.encode())
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// The multisig account of the `caller` and the `other_signatories`, after checking them.
+	fn ensure_multisig(
+		caller: &T::AccountId,
+		threshold: u16,
+		other_signatories: &[T::AccountId],
+	) -> Result<T::AccountId, &'static str> {
+		if threshold < 2 {
+			return Err("threshold must be at least two");
+		}
+		let mut signatories = other_signatories.to_vec();
+		signatories.push(caller.clone());
+		signatories.sort();
+		signatories.dedup();
+		if signatories.len() != other_signatories.len() + 1 {
+			return Err("signatories must be unique");
+		}
+		if signatories.len() > T::MAX_SIGNATORIES as usize {
+			return Err("too many signatories");
+		}
+		if signatories.len() < threshold as usize {
+			return Err("threshold is greater than the number of signatories");
+		}
+		Ok(Self::multi_account_id(&signatories, threshold))
+	}
+
+	/// Record the approval of `caller` for the call `call_hash` of `multisig`, reserving the
+	/// deposit if it starts the operation.
+	fn approve(
+		&mut self,
+		caller: T::AccountId,
+		multisig: T::AccountId,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		let key = (multisig.clone(), call_hash);
+		let mut operation = match self.multisigs.get(&key) {
+			Some(operation) => operation,
+			None => {
+				T::Currency::default().reserve(&caller, T::DEPOSIT)?;
+				let when = crate::system::Pallet::<T>::new().block_number();
+				let depositor = caller.clone();
+				Multisig { when, deposit: T::DEPOSIT, depositor, approvals: Vec::new() }
+			},
+		};
+		match operation.approvals.binary_search(&caller) {
+			Ok(_) => return Err("caller already approved the operation"),
+			Err(position) => operation.approvals.insert(position, caller.clone()),
+		}
+		let event = if operation.approvals.len() == 1 {
+			Event::NewMultisig { approving: caller, multisig, call_hash }
+		} else {
+			Event::MultisigApproval { approving: caller, multisig, call_hash }
+		};
+		self.multisigs.insert(key, operation);
+		self.deposit_event(event);
+		Ok(())
+	}
+
+	/// Remove the operation stored at `key`, returning the deposit of its depositor.
+	fn remove_operation(&mut self, key: &(T::AccountId, CallHash)) {
+		if let Some(operation) = self.multisigs.remove(key) {
+			T::Currency::default().unreserve(&operation.depositor, operation.deposit);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Approve `call` as one of the signatories of the multisig account of the `caller` and the
+	/// `other_signatories`, with the given `threshold`. If this is the last approval needed, the
+	/// call is dispatched from the multisig account, and its result recorded in an event.
+	/// Otherwise, the approval is recorded, and if it is the first one, the `caller` reserves a
+	/// deposit until the operation is executed or cancelled.
+	// The call is boxed, since it is part of the call type of the runtime.
+	#[allow(clippy::boxed_local)]
+	#[call_index(0)]
+	pub fn as_multi(
+		&mut self,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		let call_hash = Self::call_hash(&call);
+		let key = (multisig.clone(), call_hash);
+		// The approvals of the operation, counting the one of the caller.
+		let approvals = self.multisigs.get(&key).map_or(1, |operation| {
+			operation.approvals.len() + usize::from(!operation.approvals.contains(&caller))
+		});
+		if approvals < threshold as usize {
+			return self.approve(caller, multisig, call_hash);
+		}
+
+		// The operation is removed first, so that the call can't execute it again.
+		self.remove_operation(&key);
+		let result = call.dispatch(Origin::Signed(multisig.clone())).map_err(String::from);
+		self.deposit_event(Event::MultisigExecuted {
+			approving: caller,
+			multisig,
+			call_hash,
+			result,
+		});
+		Ok(())
+	}
+
+	/// Like `as_multi`, but with the hash of the call rather than the call, so it never dispatches
+	/// the call: one of the signatories does with `as_multi` once enough of them approved it.
+	#[call_index(1)]
+	pub fn approve_as_multi(
+		&mut self,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		self.approve(caller, multisig, call_hash)
+	}
+
+	/// Cancel the pending operation for the call `call_hash`, which the `caller` started. This
+	/// returns its deposit.
+	#[call_index(2)]
+	pub fn cancel_as_multi(
+		&mut self,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+		let key = (multisig.clone(), call_hash);
+		let operation = self.multisigs.get(&key).ok_or("multisig operation does not exist")?;
+		if operation.depositor != caller {
+			return Err("only the signatory which started the operation can cancel it");
+		}
+		self.remove_operation(&key);
+		self.deposit_event(Event::MultisigCancelled { cancelling: caller, multisig, call_hash });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, EventOf, Pallet};
+	use crate::support::{
+		testing::{self, TestCall},
+		RuntimeVersion,
+	};
+	use crate::{balances, system};
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type Currency = balances::Pallet<Self>;
+		type RuntimeEvent = EventOf<Self>;
+		const DEPOSIT: u128 = 10;
+		const MAX_SIGNATORIES: u32 = 3;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+	const CHARLIE: u64 = 3;
+	const DAVE: u64 = 4;
+
+	fn setup() -> (Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance(&ALICE, 100);
+		balances.set_balance(&BOB, 5);
+		(Pallet::new(), balances)
+	}
+
+	#[test]
+	fn invalid_signatories() {
+		let (mut multisig, _) = setup();
+		let hash = Pallet::<TestConfig>::call_hash(&TestCall::new(1));
+
+		assert_eq!(
+			multisig.approve_as_multi(ALICE, 1, vec![BOB], hash),
+			Err("threshold must be at least two")
+		);
+		assert_eq!(
+			multisig.approve_as_multi(ALICE, 2, vec![BOB, ALICE], hash),
+			Err("signatories must be unique")
+		);
+		assert_eq!(
+			multisig.approve_as_multi(ALICE, 2, vec![BOB, CHARLIE, DAVE], hash),
+			Err("too many signatories")
+		);
+		assert_eq!(
+			multisig.as_multi(ALICE, 3, vec![BOB], Box::new(TestCall::new(1))),
+			Err("threshold is greater than the number of signatories")
+		);
+	}
+
+	#[test]
+	fn approvals() {
+		let (mut multisig, balances) = setup();
+		let account = Pallet::<TestConfig>::multi_account_id(&[BOB, ALICE], 2);
+		let hash = Pallet::<TestConfig>::call_hash(&TestCall::new(0));
+
+		// Bob can't afford the deposit to start the operation.
+		assert_eq!(multisig.approve_as_multi(BOB, 2, vec![ALICE], hash), Err("Not enough funds."));
+		assert_eq!(multisig.approve_as_multi(ALICE, 2, vec![BOB], hash), Ok(()));
+		assert_eq!(balances.reserved_balance(&ALICE), 10);
+		assert_eq!(
+			multisig.approve_as_multi(ALICE, 2, vec![BOB], hash),
+			Err("caller already approved the operation")
+		);
+		assert_eq!(
+			multisig.cancel_as_multi(BOB, 2, vec![ALICE], hash),
+			Err("only the signatory which started the operation can cancel it")
+		);
+
+		// The failing call still completes the operation, and returns the deposit.
+		assert_eq!(multisig.as_multi(BOB, 2, vec![ALICE], Box::new(TestCall::new(0))), Ok(()));
+		assert_eq!(multisig.multisig(&account, &hash), None);
+		assert_eq!(balances.reserved_balance(&ALICE), 0);
+		assert_eq!(
+			multisig.cancel_as_multi(ALICE, 2, vec![BOB], hash),
+			Err("multisig operation does not exist")
+		);
+		let events = system::Pallet::<TestConfig>::new().events();
+		assert_eq!(
+			events,
+			vec![
+				Event::NewMultisig { approving: ALICE, multisig: account, call_hash: hash },
+				Event::MultisigExecuted {
+					approving: BOB,
+					multisig: account,
+					call_hash: hash,
+					result: Err("zero".to_string()),
+				},
+			]
+		);
+		testing::assert_round_trip(events);
+	}
+
+	#[test]
+	fn cancel() {
+		let (mut multisig, balances) = setup();
+		let account = Pallet::<TestConfig>::multi_account_id(&[ALICE, BOB, CHARLIE], 3);
+		let hash = Pallet::<TestConfig>::call_hash(&TestCall::new(1));
+
+		assert_eq!(multisig.approve_as_multi(ALICE, 3, vec![BOB, CHARLIE], hash), Ok(()));
+		assert_eq!(multisig.approve_as_multi(CHARLIE, 3, vec![ALICE, BOB], hash), Ok(()));
+		let operation = multisig.multisig(&account, &hash).unwrap();
+		assert_eq!(operation.approvals, vec![ALICE, CHARLIE]);
+		assert_eq!(multisig.cancel_as_multi(ALICE, 3, vec![CHARLIE, BOB], hash), Ok(()));
+		assert_eq!(multisig.multisig(&account, &hash), None);
+		assert_eq!(balances.reserved_balance(&ALICE), 0);
+
+		// Nothing was dispatched.
+		assert_eq!(testing::dispatched(), vec![]);
+	}
+}
@@ -0,0 +1,735 @@
+use core::fmt::Debug;
+
+use crate::support::{
+	storage::StorageMap, Decode, DispatchResult, Encode, Hooks, OnRuntimeUpgrade,
+};
+
+pub trait Config: crate::system::Config {
+	/// The type identifying a collection.
+	type CollectionId: Ord + Copy + Encode + Decode + Debug;
+	/// The type identifying an item within its collection.
+	type ItemId: Ord + Copy + Encode + Decode + Debug;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<Event<Self::AccountId, Self::CollectionId, Self::ItemId>>
+		+ Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The maximum length of the key of an attribute, in bytes.
+	const KEY_LIMIT: u32;
+	/// The maximum length of the value of an attribute, in bytes.
+	const VALUE_LIMIT: u32;
+}
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<
+	<T as crate::system::Config>::AccountId,
+	<T as Config>::CollectionId,
+	<T as Config>::ItemId,
+>;
+
+/// The key of an item: `(collection, item)`.
+type ItemKey<T> = (<T as Config>::CollectionId, <T as Config>::ItemId);
+
+/// The key of an attribute: `(collection, item, key)`.
+type AttributeKey<T> = (<T as Config>::CollectionId, <T as Config>::ItemId, Vec<u8>);
+
+/// What is stored about a collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionDetails<AccountId> {
+	/// The account which created the collection. It mints the items, and manages the collection.
+	pub owner: AccountId,
+	/// The number of items in the collection.
+	pub items: u32,
+	/// The maximum number of items in the collection, if any.
+	pub max_supply: Option<u32>,
+	/// Whether the items of the collection are locked with their current owners.
+	pub transfers_locked: bool,
+}
+
+/// What is stored about an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemDetails<AccountId> {
+	pub owner: AccountId,
+	/// The account allowed to transfer the item on behalf of its owner, if any.
+	pub approved: Option<AccountId>,
+}
+
+impl<AccountId: Encode> Encode for CollectionDetails<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.items.encode_to(dest);
+		self.max_supply.encode_to(dest);
+		self.transfers_locked.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode> Decode for CollectionDetails<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			owner: Decode::decode(input)?,
+			items: Decode::decode(input)?,
+			max_supply: Decode::decode(input)?,
+			transfers_locked: Decode::decode(input)?,
+		})
+	}
+}
+
+impl<AccountId: Encode> Encode for ItemDetails<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.approved.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode> Decode for ItemDetails<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { owner: Decode::decode(input)?, approved: Decode::decode(input)? })
+	}
+}
+
+/// The errors of this pallet. Calls still return a `DispatchResult`, which holds the message of
+/// the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	UnknownCollection,
+	UnknownItem,
+	CollectionIdInUse,
+	ItemIdInUse,
+	NoPermission,
+	MaxSupplyReached,
+	MaxSupplyTooLow,
+	TransfersLocked,
+	NoApproval,
+	KeyTooLong,
+	ValueTooLong,
+	CollectionNotEmpty,
+}
+
+impl From<Error> for &'static str {
+	fn from(error: Error) -> Self {
+		match error {
+			Error::UnknownCollection => "collection does not exist",
+			Error::UnknownItem => "item does not exist",
+			Error::CollectionIdInUse => "collection id is already in use",
+			Error::ItemIdInUse => "item id is already in use",
+			Error::NoPermission => "caller has no permission",
+			Error::MaxSupplyReached => "collection reached its max supply",
+			Error::MaxSupplyTooLow => "max supply is lower than the number of items",
+			Error::TransfersLocked => "transfers of the collection are locked",
+			Error::NoApproval => "item has no approved delegate",
+			Error::KeyTooLong => "attribute key is too long",
+			Error::ValueTooLong => "attribute value is too long",
+			Error::CollectionNotEmpty => "collection still has items",
+		}
+	}
+}
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId, CollectionId, ItemId> {
+	Created {
+		collection: CollectionId,
+		owner: AccountId,
+	},
+	Destroyed {
+		collection: CollectionId,
+	},
+	MaxSupplySet {
+		collection: CollectionId,
+		max_supply: Option<u32>,
+	},
+	TransferLockSet {
+		collection: CollectionId,
+		locked: bool,
+	},
+	Issued {
+		collection: CollectionId,
+		item: ItemId,
+		owner: AccountId,
+	},
+	AttributeSet {
+		collection: CollectionId,
+		item: ItemId,
+		key: Vec<u8>,
+		value: Vec<u8>,
+	},
+	AttributeCleared {
+		collection: CollectionId,
+		item: ItemId,
+		key: Vec<u8>,
+	},
+	Transferred {
+		collection: CollectionId,
+		item: ItemId,
+		from: AccountId,
+		to: AccountId,
+	},
+	TransferApproved {
+		collection: CollectionId,
+		item: ItemId,
+		owner: AccountId,
+		delegate: AccountId,
+	},
+	ApprovalCancelled {
+		collection: CollectionId,
+		item: ItemId,
+		owner: AccountId,
+		delegate: AccountId,
+	},
+	Burned {
+		collection: CollectionId,
+		item: ItemId,
+		owner: AccountId,
+	},
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode, CollectionId: Encode, ItemId: Encode> Encode
+	for Event<AccountId, CollectionId, ItemId>
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::Created { collection, owner } => (0u8, (collection, owner)).encode_to(dest),
+			Event::Destroyed { collection } => (1u8, collection).encode_to(dest),
+			Event::MaxSupplySet { collection, max_supply } => {
+				(2u8, (collection, max_supply)).encode_to(dest)
+			},
+			Event::TransferLockSet { collection, locked } => {
+				(3u8, (collection, locked)).encode_to(dest)
+			},
+			Event::Issued { collection, item, owner } => {
+				(4u8, (collection, item, owner)).encode_to(dest)
+			},
+			Event::AttributeSet { collection, item, key, value } => {
+				(5u8, (collection, item, (key, value))).encode_to(dest)
+			},
+			Event::AttributeCleared { collection, item, key } => {
+				(6u8, (collection, item, key)).encode_to(dest)
+			},
+			Event::Transferred { collection, item, from, to } => {
+				(7u8, (collection, item, (from, to))).encode_to(dest)
+			},
+			Event::TransferApproved { collection, item, owner, delegate } => {
+				(8u8, (collection, item, (owner, delegate))).encode_to(dest)
+			},
+			Event::ApprovalCancelled { collection, item, owner, delegate } => {
+				(9u8, (collection, item, (owner, delegate))).encode_to(dest)
+			},
+			Event::Burned { collection, item, owner } => {
+				(10u8, (collection, item, owner)).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<AccountId: Decode, CollectionId: Decode, ItemId: Decode> Decode
+	for Event<AccountId, CollectionId, ItemId>
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => {
+				let (collection, owner) = Decode::decode(input)?;
+				Event::Created { collection, owner }
+			},
+			1 => Event::Destroyed { collection: Decode::decode(input)? },
+			2 => {
+				let (collection, max_supply) = Decode::decode(input)?;
+				Event::MaxSupplySet { collection, max_supply }
+			},
+			3 => {
+				let (collection, locked) = Decode::decode(input)?;
+				Event::TransferLockSet { collection, locked }
+			},
+			4 => {
+				let (collection, item, owner) = Decode::decode(input)?;
+				Event::Issued { collection, item, owner }
+			},
+			5 => {
+				let (collection, item, (key, value)) = Decode::decode(input)?;
+				Event::AttributeSet { collection, item, key, value }
+			},
+			6 => {
+				let (collection, item, key) = Decode::decode(input)?;
+				Event::AttributeCleared { collection, item, key }
+			},
+			7 => {
+				let (collection, item, (from, to)) = Decode::decode(input)?;
+				Event::Transferred { collection, item, from, to }
+			},
+			8 => {
+				let (collection, item, (owner, delegate)) = Decode::decode(input)?;
+				Event::TransferApproved { collection, item, owner, delegate }
+			},
+			9 => {
+				let (collection, item, (owner, delegate)) = Decode::decode(input)?;
+				Event::ApprovalCancelled { collection, item, owner, delegate }
+			},
+			10 => {
+				let (collection, item, owner) = Decode::decode(input)?;
+				Event::Burned { collection, item, owner }
+			},
+			_ => return Err("invalid nfts event"),
+		})
+	}
+}
+
+/// This is the NFTs Module.
+/// It lets accounts create collections of unique items, and trade them.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The details of every collection which was created.
+	collections: StorageMap<T::CollectionId, CollectionDetails<T::AccountId>>,
+	// The details of every item which was minted, and not burnt.
+	items: StorageMap<ItemKey<T>, ItemDetails<T::AccountId>>,
+	// The attributes of every item, as arbitrary bytes.
+	attributes: StorageMap<AttributeKey<T>, Vec<u8>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the NFTs module.
+	pub fn new() -> Self {
+		Self {
+			collections: StorageMap::new("Nfts", "Collection"),
+			items: StorageMap::new("Nfts", "Item"),
+			attributes: StorageMap::new("Nfts", "Attribute"),
+		}
+	}
+
+	/// Get the details of the collection `collection`, if it exists.
+	pub fn collection(
+		&self,
+		collection: T::CollectionId,
+	) -> Option<CollectionDetails<T::AccountId>> {
+		self.collections.get(&collection)
+	}
+
+	/// Get the details of the item `item` of `collection`, if it exists.
+	pub fn item(
+		&self,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> Option<ItemDetails<T::AccountId>> {
+		self.items.get(&(collection, item))
+	}
+
+	/// Get the owner of the item `item` of `collection`, if it exists.
+	pub fn owner(&self, collection: T::CollectionId, item: T::ItemId) -> Option<T::AccountId> {
+		self.item(collection, item).map(|details| details.owner)
+	}
+
+	/// Get the value of the attribute `key` of the item `item` of `collection`, if it is set.
+	pub fn attribute(
+		&self,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: &[u8],
+	) -> Option<Vec<u8>> {
+		self.attributes.get(&(collection, item, key.to_vec()))
+	}
+
+	/// Record an event of this pallet in the system pallet.
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// Get the details of `collection`, ensuring `who` owns it.
+	fn ensure_collection_owner(
+		&self,
+		collection: T::CollectionId,
+		who: &T::AccountId,
+	) -> Result<CollectionDetails<T::AccountId>, Error> {
+		let details = self.collection(collection).ok_or(Error::UnknownCollection)?;
+		if details.owner != *who {
+			return Err(Error::NoPermission);
+		}
+		Ok(details)
+	}
+
+	/// Get the details of the item `item` of `collection`, ensuring `who` owns it.
+	fn ensure_item_owner(
+		&self,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		who: &T::AccountId,
+	) -> Result<ItemDetails<T::AccountId>, Error> {
+		let details = self.item(collection, item).ok_or(Error::UnknownItem)?;
+		if details.owner != *who {
+			return Err(Error::NoPermission);
+		}
+		Ok(details)
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create the collection `collection`, owned by `caller`, with an optional maximum number of
+	/// items.
+	#[call_index(0)]
+	pub fn create(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		max_supply: Option<u32>,
+	) -> DispatchResult {
+		if self.collections.contains_key(&collection) {
+			return Err(Error::CollectionIdInUse.into());
+		}
+		let details = CollectionDetails {
+			owner: caller.clone(),
+			items: 0,
+			max_supply,
+			transfers_locked: false,
+		};
+		self.collections.insert(collection, details);
+		self.deposit_event(Event::Created { collection, owner: caller });
+		Ok(())
+	}
+
+	/// Destroy the collection `collection`, which must have no items left.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(1)]
+	pub fn destroy(&mut self, caller: T::AccountId, collection: T::CollectionId) -> DispatchResult {
+		let details = self.ensure_collection_owner(collection, &caller)?;
+		if details.items > 0 {
+			return Err(Error::CollectionNotEmpty.into());
+		}
+		self.collections.remove(&collection);
+		self.deposit_event(Event::Destroyed { collection });
+		Ok(())
+	}
+
+	/// Change the maximum number of items in `collection`. It can't be lower than the number of
+	/// items the collection already has.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(2)]
+	pub fn set_max_supply(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		max_supply: Option<u32>,
+	) -> DispatchResult {
+		let mut details = self.ensure_collection_owner(collection, &caller)?;
+		if max_supply.is_some_and(|max_supply| max_supply < details.items) {
+			return Err(Error::MaxSupplyTooLow.into());
+		}
+		details.max_supply = max_supply;
+		self.collections.insert(collection, details);
+		self.deposit_event(Event::MaxSupplySet { collection, max_supply });
+		Ok(())
+	}
+
+	/// Lock or unlock the transfers of all the items of `collection`.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(3)]
+	pub fn set_transfer_lock(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		locked: bool,
+	) -> DispatchResult {
+		let mut details = self.ensure_collection_owner(collection, &caller)?;
+		details.transfers_locked = locked;
+		self.collections.insert(collection, details);
+		self.deposit_event(Event::TransferLockSet { collection, locked });
+		Ok(())
+	}
+
+	/// Mint the item `item` of `collection`, owned by `owner`.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(4)]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		let mut details = self.ensure_collection_owner(collection, &caller)?;
+		if self.items.contains_key(&(collection, item)) {
+			return Err(Error::ItemIdInUse.into());
+		}
+		if details.max_supply.is_some_and(|max_supply| details.items >= max_supply) {
+			return Err(Error::MaxSupplyReached.into());
+		}
+		details.items += 1;
+		self.collections.insert(collection, details);
+		self.items.insert((collection, item), ItemDetails { owner: owner.clone(), approved: None });
+		self.deposit_event(Event::Issued { collection, item, owner });
+		Ok(())
+	}
+
+	/// Set the attribute `key` of the item `item` of `collection` to `value`.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(5)]
+	pub fn set_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: Vec<u8>,
+		value: Vec<u8>,
+	) -> DispatchResult {
+		self.ensure_collection_owner(collection, &caller)?;
+		if !self.items.contains_key(&(collection, item)) {
+			return Err(Error::UnknownItem.into());
+		}
+		if key.len() > T::KEY_LIMIT as usize {
+			return Err(Error::KeyTooLong.into());
+		}
+		if value.len() > T::VALUE_LIMIT as usize {
+			return Err(Error::ValueTooLong.into());
+		}
+		self.attributes.insert((collection, item, key.clone()), value.clone());
+		self.deposit_event(Event::AttributeSet { collection, item, key, value });
+		Ok(())
+	}
+
+	/// Remove the attribute `key` of the item `item` of `collection`.
+	/// This function can only be called by the owner of the collection.
+	#[call_index(6)]
+	pub fn clear_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: Vec<u8>,
+	) -> DispatchResult {
+		self.ensure_collection_owner(collection, &caller)?;
+		if self.attributes.remove(&(collection, item, key.clone())).is_some() {
+			self.deposit_event(Event::AttributeCleared { collection, item, key });
+		}
+		Ok(())
+	}
+
+	/// Transfer the item `item` of `collection` to `to`.
+	/// This function can be called by the owner of the item, or the delegate it approved. The
+	/// approval is cancelled by the transfer.
+	#[call_index(7)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		to: T::AccountId,
+	) -> DispatchResult {
+		let collection_details = self.collection(collection).ok_or(Error::UnknownCollection)?;
+		if collection_details.transfers_locked {
+			return Err(Error::TransfersLocked.into());
+		}
+		let details = self.item(collection, item).ok_or(Error::UnknownItem)?;
+		if details.owner != caller && details.approved.as_ref() != Some(&caller) {
+			return Err(Error::NoPermission.into());
+		}
+		self.items.insert((collection, item), ItemDetails { owner: to.clone(), approved: None });
+		self.deposit_event(Event::Transferred { collection, item, from: details.owner, to });
+		Ok(())
+	}
+
+	/// Allow `delegate` to transfer the item `item` of `collection`, replacing any previous
+	/// delegate.
+	/// This function can only be called by the owner of the item.
+	#[call_index(8)]
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		let mut details = self.ensure_item_owner(collection, item, &caller)?;
+		details.approved = Some(delegate.clone());
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::TransferApproved { collection, item, owner: caller, delegate });
+		Ok(())
+	}
+
+	/// Cancel the approval of the delegate of the item `item` of `collection`.
+	/// This function can only be called by the owner of the item.
+	#[call_index(9)]
+	pub fn cancel_approval(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		let mut details = self.ensure_item_owner(collection, item, &caller)?;
+		let delegate = details.approved.take().ok_or(Error::NoApproval)?;
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::ApprovalCancelled { collection, item, owner: caller, delegate });
+		Ok(())
+	}
+
+	/// Burn the item `item` of `collection`, along with its attributes.
+	/// This function can only be called by the owner of the item.
+	#[call_index(10)]
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		self.ensure_item_owner(collection, item, &caller)?;
+		let mut collection_details = self.collection(collection).ok_or(Error::UnknownCollection)?;
+		collection_details.items -= 1;
+		self.collections.insert(collection, collection_details);
+		self.items.remove(&(collection, item));
+		let attributes = self
+			.attributes
+			.iter()
+			.map(|(key, _)| key)
+			.filter(|(c, i, _)| (*c, *i) == (collection, item));
+		for key in attributes.collect::<Vec<_>>() {
+			self.attributes.remove(&key);
+		}
+		self.deposit_event(Event::Burned { collection, item, owner: caller });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, Error, Event, EventOf, Pallet};
+	use crate::support::{Decode, Encode, RuntimeVersion};
+	use crate::system;
+
+	struct TestConfig;
+	impl Config for TestConfig {
+		type CollectionId = u32;
+		type ItemId = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const KEY_LIMIT: u32 = 4;
+		const VALUE_LIMIT: u32 = 8;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+	const CHARLIE: u64 = 3;
+
+	fn events() -> Vec<EventOf<TestConfig>> {
+		system::Pallet::<TestConfig>::new().events()
+	}
+
+	#[test]
+	fn collections() {
+		let mut nfts = Pallet::<TestConfig>::new();
+
+		assert_eq!(nfts.create(ALICE, 0, Some(1)), Ok(()));
+		assert_eq!(nfts.create(BOB, 0, None), Err(Error::CollectionIdInUse.into()));
+		assert_eq!(nfts.mint(BOB, 0, 0, BOB), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.mint(ALICE, 1, 0, BOB), Err(Error::UnknownCollection.into()));
+		assert_eq!(nfts.mint(ALICE, 0, 0, BOB), Ok(()));
+		assert_eq!(nfts.mint(ALICE, 0, 0, BOB), Err(Error::ItemIdInUse.into()));
+		assert_eq!(nfts.mint(ALICE, 0, 1, BOB), Err(Error::MaxSupplyReached.into()));
+
+		assert_eq!(nfts.set_max_supply(ALICE, 0, Some(0)), Err(Error::MaxSupplyTooLow.into()));
+		assert_eq!(nfts.set_max_supply(ALICE, 0, None), Ok(()));
+		assert_eq!(nfts.mint(ALICE, 0, 1, CHARLIE), Ok(()));
+		assert_eq!(nfts.collection(0).unwrap().items, 2);
+
+		assert_eq!(nfts.destroy(ALICE, 0), Err(Error::CollectionNotEmpty.into()));
+		assert_eq!(nfts.burn(ALICE, 0, 0), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.burn(BOB, 0, 0), Ok(()));
+		assert_eq!(nfts.burn(CHARLIE, 0, 1), Ok(()));
+		assert_eq!(nfts.destroy(BOB, 0), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.destroy(ALICE, 0), Ok(()));
+		assert!(nfts.collections.is_empty());
+		assert!(nfts.items.is_empty());
+
+		assert_eq!(events().len(), 7);
+		assert_eq!(events()[1], Event::Issued { collection: 0, item: 0, owner: BOB });
+		assert_eq!(events()[6], Event::Destroyed { collection: 0 });
+	}
+
+	#[test]
+	fn attributes() {
+		let mut nfts = Pallet::<TestConfig>::new();
+		assert_eq!(nfts.create(ALICE, 0, None), Ok(()));
+		assert_eq!(nfts.mint(ALICE, 0, 0, BOB), Ok(()));
+
+		let set = |nfts: &mut Pallet<TestConfig>, caller, key: &[u8], value: &[u8]| {
+			nfts.set_attribute(caller, 0, 0, key.to_vec(), value.to_vec())
+		};
+		assert_eq!(set(&mut nfts, BOB, b"name", b"bob"), Err(Error::NoPermission.into()));
+		assert_eq!(set(&mut nfts, ALICE, b"colour", b"red"), Err(Error::KeyTooLong.into()));
+		assert_eq!(set(&mut nfts, ALICE, b"name", b"a long name"), Err(Error::ValueTooLong.into()));
+		assert_eq!(
+			nfts.set_attribute(ALICE, 0, 1, b"name".to_vec(), b"bob".to_vec()),
+			Err(Error::UnknownItem.into())
+		);
+		assert_eq!(set(&mut nfts, ALICE, b"name", b"bob"), Ok(()));
+		assert_eq!(set(&mut nfts, ALICE, b"size", b"big"), Ok(()));
+		assert_eq!(nfts.attribute(0, 0, b"name"), Some(b"bob".to_vec()));
+
+		assert_eq!(nfts.clear_attribute(ALICE, 0, 0, b"name".to_vec()), Ok(()));
+		assert_eq!(nfts.attribute(0, 0, b"name"), None);
+		assert_eq!(
+			events().last(),
+			Some(&Event::AttributeCleared { collection: 0, item: 0, key: b"name".to_vec() })
+		);
+
+		// Burning an item removes its attributes.
+		assert_eq!(nfts.burn(BOB, 0, 0), Ok(()));
+		assert!(nfts.attributes.is_empty());
+	}
+
+	#[test]
+	fn transfers() {
+		let mut nfts = Pallet::<TestConfig>::new();
+		assert_eq!(nfts.create(ALICE, 0, None), Ok(()));
+		assert_eq!(nfts.mint(ALICE, 0, 0, BOB), Ok(()));
+
+		assert_eq!(nfts.transfer(ALICE, 0, 0, ALICE), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.transfer(BOB, 0, 1, ALICE), Err(Error::UnknownItem.into()));
+		assert_eq!(nfts.transfer(BOB, 0, 0, CHARLIE), Ok(()));
+		assert_eq!(nfts.owner(0, 0), Some(CHARLIE));
+
+		// Bob may transfer the item back once, on behalf of charlie.
+		assert_eq!(nfts.approve_transfer(BOB, 0, 0, BOB), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.approve_transfer(CHARLIE, 0, 0, BOB), Ok(()));
+		assert_eq!(nfts.transfer(BOB, 0, 0, BOB), Ok(()));
+		assert_eq!(nfts.owner(0, 0), Some(BOB));
+		assert_eq!(nfts.transfer(CHARLIE, 0, 0, CHARLIE), Err(Error::NoPermission.into()));
+
+		assert_eq!(nfts.cancel_approval(BOB, 0, 0), Err(Error::NoApproval.into()));
+		assert_eq!(nfts.approve_transfer(BOB, 0, 0, CHARLIE), Ok(()));
+		assert_eq!(nfts.cancel_approval(BOB, 0, 0), Ok(()));
+		assert_eq!(nfts.transfer(CHARLIE, 0, 0, CHARLIE), Err(Error::NoPermission.into()));
+
+		// Locked items can't be transferred, even by their owner.
+		assert_eq!(nfts.set_transfer_lock(BOB, 0, true), Err(Error::NoPermission.into()));
+		assert_eq!(nfts.set_transfer_lock(ALICE, 0, true), Ok(()));
+		assert_eq!(nfts.transfer(BOB, 0, 0, CHARLIE), Err(Error::TransfersLocked.into()));
+		assert_eq!(nfts.set_transfer_lock(ALICE, 0, false), Ok(()));
+		assert_eq!(nfts.transfer(BOB, 0, 0, CHARLIE), Ok(()));
+
+		assert_eq!(
+			events().last(),
+			Some(&Event::Transferred { collection: 0, item: 0, from: BOB, to: CHARLIE })
+		);
+	}
+
+	#[test]
+	fn encode_events() {
+		let event: EventOf<TestConfig> =
+			Event::TransferApproved { collection: 1, item: 2, owner: ALICE, delegate: BOB };
+		assert_eq!(EventOf::<TestConfig>::decode_all(&event.encode()), Ok(event));
+		assert_eq!(EventOf::<TestConfig>::decode_all(&[11]), Err("invalid nfts event"));
+	}
+}
@@ -1,99 +1,894 @@
-use crate::support::DispatchResult;
+use crate::support::{
+    hashing::Hasher,
+    merkle::{self, MerkleProof},
+    storage::{self, StorageMap, StorageValue},
+    Currency, Decode, DispatchResult, Encode, Hooks, OnRuntimeUpgrade, ReservableCurrency,
+    UnixTime,
+};
 use core::fmt::Debug;
+use num::traits::Zero;
 use std::collections::BTreeMap;
 
 pub trait Config: crate::system::Config {
-    /// The type which represents the content that can be claimed using this pallet.
-    /// Could be the content directly as bytes, or better yet the hash of that content.
-    /// We leave that decision to the runtime developer.
-    type Content: Debug + Ord;
+    /// The type which represents the content that can be claimed using this pallet: the hash of
+    /// that content, so that claims don't store the content itself.
+    type Content: Debug + Clone + Ord + AsRef<[u8]> + Encode + Decode;
+    /// The hash function turning content into a claim.
+    type Hashing: Hasher<Output = Self::Content>;
+    /// The pallet holding the funds of accounts, usually `balances`.
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// The source of the time claims are made at, `()` if there is none.
+    type Time: UnixTime;
+    /// The funds reserved from the owner of a claim, until it is revoked.
+    const CLAIM_DEPOSIT: BalanceOf<Self>;
+    /// The maximum length of the description of a claim, in bytes.
+    const DESCRIPTION_LIMIT: u32;
 }
 
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// The version of the layout of the data stored by this pallet.
+/// - 0: claims are keyed by their raw content, and only store their owner.
+/// - 1: claims are keyed by the hash of their content, and store the block they were made at.
+/// - 2: claims store their time and description, and the history of their owners is kept.
+/// - 3: the claims of each account are indexed.
+pub const STORAGE_VERSION: u16 = 3;
+
+/// A claim on some content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claim<AccountId, BlockNumber> {
+    /// The account which owns the claim.
+    pub owner: AccountId,
+    /// The block the claim was made at.
+    pub block_number: BlockNumber,
+    /// The time the claim was made at, in milliseconds since the Unix epoch, if it was known.
+    pub timestamp: Option<u64>,
+    /// A description of the content, or where to find it, set by the owner.
+    pub description: Option<String>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for Claim<AccountId, BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.owner.encode_to(dest);
+        self.block_number.encode_to(dest);
+        self.timestamp.encode_to(dest);
+        self.description.encode_to(dest);
+    }
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Decode for Claim<AccountId, BlockNumber> {
+    fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            owner: Decode::decode(input)?,
+            block_number: Decode::decode(input)?,
+            timestamp: Decode::decode(input)?,
+            description: Decode::decode(input)?,
+        })
+    }
+}
+
+/// A change of the owner of some content: from `block_number` on, it is owned by `owner`, or by
+/// no one if the claim was revoked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnerChange<AccountId, BlockNumber> {
+    pub block_number: BlockNumber,
+    pub owner: Option<AccountId>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for OwnerChange<AccountId, BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block_number.encode_to(dest);
+        self.owner.encode_to(dest);
+    }
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Decode for OwnerChange<AccountId, BlockNumber> {
+    fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self { block_number: Decode::decode(input)?, owner: Decode::decode(input)? })
+    }
+}
+
+/// Every change of the owner of some content, oldest first.
+pub type History<AccountId, BlockNumber> = Vec<OwnerChange<AccountId, BlockNumber>>;
+
+/// A change of owner, for a runtime `T`.
+pub type OwnerChangeOf<T> =
+    OwnerChange<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// A claim, for a runtime `T`.
+pub type ClaimOf<T> =
+    Claim<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-    /// A simple storage map from content to the owner of that content.
+    /// A simple storage map from content to its claim.
     /// Accounts can make multiple different claims, but each claim can only have one owner.
-    claims: BTreeMap<T::Content, T::AccountId>,
+    claims: StorageMap<T::Content, Claim<T::AccountId, T::BlockNumber>>,
+    /// Every change of the owner of some content, oldest first. It is kept after the claim is
+    /// revoked, so that it is possible to know who owned the content at any block.
+    history: StorageMap<T::Content, History<T::AccountId, T::BlockNumber>>,
+    /// The claims owned by each account, oldest first.
+    claims_of: StorageMap<T::AccountId, Vec<T::Content>>,
+    /// The account allowed to transfer a claim on behalf of its owner, if any.
+    approvals: StorageMap<T::Content, T::AccountId>,
+    /// The block at the end of which each expiring claim is removed.
+    expiries: StorageMap<T::Content, T::BlockNumber>,
+    /// The claims expiring at the end of each block.
+    expiring: StorageMap<T::BlockNumber, Vec<T::Content>>,
+    /// The number of documents of each claim on the Merkle root of a batch, see `anchor_batch`.
+    batches: StorageMap<T::Content, u32>,
+    /// The deposit reserved for each claim. Claims made before deposits existed have none.
+    deposits: StorageMap<T::Content, BalanceOf<T>>,
+    /// The pallet holding the deposits.
+    currency: T::Currency,
+    // The version of the layout of the stored data, see `STORAGE_VERSION`.
+    storage_version: StorageValue<u16>,
 }
 
 impl<T: Config> Pallet<T> {
     /// Create a new instance of the Proof of Existence Module.
     pub fn new() -> Self {
         Self {
-            claims: BTreeMap::new(),
+            claims: StorageMap::new("ProofOfExistence", "Claims"),
+            history: StorageMap::new("ProofOfExistence", "History"),
+            claims_of: StorageMap::new("ProofOfExistence", "ClaimsOf"),
+            approvals: StorageMap::new("ProofOfExistence", "Approvals"),
+            expiries: StorageMap::new("ProofOfExistence", "Expiries"),
+            expiring: StorageMap::new("ProofOfExistence", "Expiring"),
+            batches: StorageMap::new("ProofOfExistence", "Batches"),
+            deposits: StorageMap::new("ProofOfExistence", "Deposits"),
+            currency: T::Currency::default(),
+            storage_version: StorageValue::new("ProofOfExistence", "StorageVersion"),
         }
     }
 
     /// Get the owner (if any) of a claim.
-    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+    pub fn get_claim(&self, claim: &T::Content) -> Option<T::AccountId> {
+        self.claims.get(claim).map(|claim| claim.owner)
+    }
+
+    /// Get the claim (if any) on some content.
+    pub fn claim(&self, claim: &T::Content) -> Option<ClaimOf<T>> {
         self.claims.get(claim)
     }
+
+    /// Every change of the owner of some content, oldest first.
+    pub fn history(&self, claim: &T::Content) -> Vec<OwnerChangeOf<T>> {
+        self.history.get(claim).unwrap_or_default()
+    }
+
+    /// The owner (if any) of some content at the end of the block `block_number`.
+    pub fn owner_at(
+        &self,
+        claim: &T::Content,
+        block_number: T::BlockNumber,
+    ) -> Option<T::AccountId> {
+        let history = self.history(claim);
+        let change = history.into_iter().rev().find(|change| change.block_number <= block_number);
+        change.and_then(|change| change.owner)
+    }
+
+    /// The claims owned by `who`, oldest first.
+    pub fn claims_of(&self, who: &T::AccountId) -> Vec<T::Content> {
+        self.claims_of.get(who).unwrap_or_default()
+    }
+
+    /// The account allowed to transfer a claim on behalf of its owner, if any.
+    pub fn approved(&self, claim: &T::Content) -> Option<T::AccountId> {
+        self.approvals.get(claim)
+    }
+
+    /// The block at the end of which a claim expires, if it does.
+    pub fn expiry(&self, claim: &T::Content) -> Option<T::BlockNumber> {
+        self.expiries.get(claim)
+    }
+
+    /// Check whether `content` was claimed, returning the owner of the claim and the block it was
+    /// made at.
+    pub fn verify(&self, content: &[u8]) -> Option<(T::AccountId, T::BlockNumber)> {
+        self.claims.get(&T::Hashing::hash(content)).map(|claim| (claim.owner, claim.block_number))
+    }
+
+    /// The number of documents of a batch, if `claim` is the Merkle root of one.
+    pub fn batch_size(&self, claim: &T::Content) -> Option<u32> {
+        self.batches.get(claim)
+    }
+
+    /// Check that the document with the hash `proof.leaf` is part of a claimed batch, returning
+    /// the owner of the claim and the block it was made at. The path of the proof can't be longer
+    /// than the depth of a tree with the documents of the batch. See `support::merkle` for how
+    /// clients build the proof of each document of a batch.
+    pub fn verify_proof(
+        &self,
+        proof: &MerkleProof<T::Content>,
+    ) -> Option<(T::AccountId, T::BlockNumber)> {
+        let documents = self.batches.get(&proof.root)?;
+        if proof.siblings.len() > merkle::max_depth(documents) as usize
+            || !proof.verify::<T::Hashing>()
+        {
+            return None;
+        }
+        self.claims.get(&proof.root).map(|claim| (claim.owner, claim.block_number))
+    }
+
+    /// Record that `claim` is owned by `owner` from the current block on.
+    fn note_owner(&mut self, claim: &T::Content, owner: Option<T::AccountId>) {
+        let block_number = crate::system::Pallet::<T>::new().block_number();
+        let mut history = self.history(claim);
+        history.push(OwnerChange { block_number, owner });
+        self.history.insert(claim.clone(), history);
+    }
+
+    /// Add `claim` to the claims of `owner`.
+    fn index_claim(&mut self, owner: &T::AccountId, claim: &T::Content) {
+        let mut claims = self.claims_of(owner);
+        claims.push(claim.clone());
+        self.claims_of.insert(owner.clone(), claims);
+    }
+
+    /// Remove `claim` from the claims of `owner`.
+    fn unindex_claim(&mut self, owner: &T::AccountId, claim: &T::Content) {
+        let mut claims = self.claims_of(owner);
+        claims.retain(|owned| owned != claim);
+        if claims.is_empty() {
+            self.claims_of.remove(owner);
+        } else {
+            self.claims_of.insert(owner.clone(), claims);
+        }
+    }
+
+    /// Remove `claim` from the agenda of the block it expires at, if it expires.
+    fn clear_expiry(&mut self, claim: &T::Content) {
+        let Some(expiry) = self.expiries.remove(claim) else { return };
+        let mut claims = self.expiring.get(&expiry).unwrap_or_default();
+        claims.retain(|expiring| expiring != claim);
+        if claims.is_empty() {
+            self.expiring.remove(&expiry);
+        } else {
+            self.expiring.insert(expiry, claims);
+        }
+    }
+
+    /// Remove `claim` of `owner`, giving its deposit back.
+    fn remove_claim(&mut self, claim: &T::Content, owner: &T::AccountId) {
+        self.claims.remove(claim);
+        self.note_owner(claim, None);
+        self.unindex_claim(owner, claim);
+        self.approvals.remove(claim);
+        self.clear_expiry(claim);
+        self.batches.remove(claim);
+        if let Some(deposit) = self.deposits.remove(claim) {
+            self.currency.unreserve(owner, deposit);
+        }
+    }
+
+    /// Give `claim` to `to`, who reserves its deposit in place of the previous owner.
+    fn do_transfer(
+        &mut self,
+        claim: T::Content,
+        mut details: ClaimOf<T>,
+        to: T::AccountId,
+    ) -> DispatchResult {
+        if let Some(deposit) = self.deposits.get(&claim) {
+            self.currency.reserve(&to, deposit)?;
+            self.currency.unreserve(&details.owner, deposit);
+        }
+        self.unindex_claim(&details.owner, &claim);
+        self.index_claim(&to, &claim);
+        self.note_owner(&claim, Some(to.clone()));
+        self.approvals.remove(&claim);
+        details.owner = to;
+        self.claims.insert(claim, details);
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[macros::call]
 impl<T:Config> Pallet<T> {
         /// Create a new claim on behalf of the `caller`.
     /// This function will return an error if someone already has claimed that content.
+    /// The `caller` must be able to reserve the claim deposit.
+    #[call_index(0)]
     pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
         if self.get_claim(&claim).is_some() {
             return Err("this content has already been claimed");
         }
-        self.claims.insert(claim, caller);
+        self.currency.reserve(&caller, T::CLAIM_DEPOSIT)?;
+        if !T::CLAIM_DEPOSIT.is_zero() {
+            self.deposits.insert(claim.clone(), T::CLAIM_DEPOSIT);
+        }
+        self.note_owner(&claim, Some(caller.clone()));
+        self.index_claim(&caller, &claim);
+        let block_number = crate::system::Pallet::<T>::new().block_number();
+        let timestamp = T::Time::now();
+        let details = Claim { owner: caller, block_number, timestamp, description: None };
+        self.claims.insert(claim, details);
         Ok(())
     }
 
     /// Revoke an existing claim on some content.
     /// This function should only succeed if the caller is the owner of an existing claim.
     /// It will return an error if the claim does not exist, or if the caller is not the owner.
+    /// The deposit of the claim is given back to the caller.
+    #[call_index(1)]
     pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
         let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
-        if *owner != caller {
+        if owner != caller {
+            return Err("claim does not belong to caller");
+        }
+        self.remove_claim(&claim, &caller);
+        Ok(())
+    }
+
+    /// Create a new claim on `content` on behalf of the `caller`, like `create_claim` does with
+    /// the hash of `content`. Only the hash is stored.
+    #[call_index(2)]
+    pub fn create_claim_from_bytes(
+        &mut self,
+        caller: T::AccountId,
+        content: Vec<u8>,
+    ) -> DispatchResult {
+        self.create_claim(caller, T::Hashing::hash(&content))
+    }
+
+    /// Set or clear the description of a claim of the `caller`.
+    #[call_index(3)]
+    pub fn set_description(
+        &mut self,
+        caller: T::AccountId,
+        claim: T::Content,
+        description: Option<String>,
+    ) -> DispatchResult {
+        let mut details = self.claims.get(&claim).ok_or("claim does not exist")?;
+        if details.owner != caller {
+            return Err("claim does not belong to caller");
+        }
+        if description.as_ref().is_some_and(|d| d.len() > T::DESCRIPTION_LIMIT as usize) {
+            return Err("description is too long");
+        }
+        details.description = description;
+        self.claims.insert(claim, details);
+        Ok(())
+    }
+
+    /// Transfer a claim of the `caller` to `to`.
+    /// `to` reserves the deposit of the claim, which is given back to the `caller`.
+    #[call_index(4)]
+    pub fn transfer_claim(
+        &mut self,
+        caller: T::AccountId,
+        claim: T::Content,
+        to: T::AccountId,
+    ) -> DispatchResult {
+        let details = self.claims.get(&claim).ok_or("claim does not exist")?;
+        if details.owner != caller {
+            return Err("claim does not belong to caller");
+        }
+        self.do_transfer(claim, details, to)
+    }
+
+    /// Allow `delegate` to transfer a claim of the `caller`, see `transfer_approved_claim`.
+    /// This replaces any previous approval, and lasts until the claim changes owner.
+    #[call_index(5)]
+    pub fn approve_transfer(
+        &mut self,
+        caller: T::AccountId,
+        claim: T::Content,
+        delegate: T::AccountId,
+    ) -> DispatchResult {
+        let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
+        if owner != caller {
+            return Err("claim does not belong to caller");
+        }
+        self.approvals.insert(claim, delegate);
+        Ok(())
+    }
+
+    /// Cancel the approval given for a claim of the `caller`.
+    #[call_index(6)]
+    pub fn cancel_approval(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+        let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
+        if owner != caller {
+            return Err("claim does not belong to caller");
+        }
+        self.approvals.remove(&claim).ok_or("claim has no approval")?;
+        Ok(())
+    }
+
+    /// Transfer a claim to `to` on behalf of its owner, who approved the `caller` to do so.
+    #[call_index(7)]
+    pub fn transfer_approved_claim(
+        &mut self,
+        caller: T::AccountId,
+        claim: T::Content,
+        to: T::AccountId,
+    ) -> DispatchResult {
+        let details = self.claims.get(&claim).ok_or("claim does not exist")?;
+        if self.approved(&claim) != Some(caller) {
+            return Err("caller is not approved to transfer the claim");
+        }
+        self.do_transfer(claim, details, to)
+    }
+
+    /// Make a claim of the `caller` expire at the end of the block `expiry`, or never if it is
+    /// `None`. The claim is then removed, and its deposit given back to its owner.
+    #[call_index(8)]
+    pub fn set_expiry(
+        &mut self,
+        caller: T::AccountId,
+        claim: T::Content,
+        expiry: Option<T::BlockNumber>,
+    ) -> DispatchResult {
+        let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
+        if owner != caller {
             return Err("claim does not belong to caller");
         }
-        self.claims.remove(&claim);
+        let now = crate::system::Pallet::<T>::new().block_number();
+        if expiry.is_some_and(|expiry| expiry < now) {
+            return Err("expiry is in the past");
+        }
+        self.clear_expiry(&claim);
+        if let Some(expiry) = expiry {
+            let mut claims = self.expiring.get(&expiry).unwrap_or_default();
+            claims.push(claim.clone());
+            self.expiring.insert(expiry, claims);
+            self.expiries.insert(claim, expiry);
+        }
+        Ok(())
+    }
+
+    /// Claim a batch of `documents` at once, with the Merkle root of their hashes.
+    /// Only the root is stored, for the price of a single claim. The existence of each document is
+    /// then proven with its path to the root, see `verify_proof`.
+    #[call_index(9)]
+    pub fn anchor_batch(
+        &mut self,
+        caller: T::AccountId,
+        root: T::Content,
+        documents: u32,
+    ) -> DispatchResult {
+        if documents == 0 {
+            return Err("batch is empty");
+        }
+        self.create_claim(caller, root.clone())?;
+        self.batches.insert(root, documents);
+        Ok(())
+    }
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+    fn on_finalize(&mut self, block_number: T::BlockNumber) -> DispatchResult {
+        for claim in self.expiring.get(&block_number).unwrap_or_default() {
+            if let Some(owner) = self.get_claim(&claim) {
+                self.remove_claim(&claim, &owner);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {
+    fn on_runtime_upgrade(&mut self) {
+        // The claims of each version, as stored by the previous one.
+        let claims_v1 = StorageMap::<T::Content, (T::AccountId, T::BlockNumber)>::new(
+            "ProofOfExistence",
+            "Claims",
+        );
+        if self.storage_version.get().unwrap_or(0) < 1 {
+            // Key the claims by the hash of their content. When they were made is unknown, so
+            // they are dated to the block of the migration.
+            let block_number = crate::system::Pallet::<T>::new().block_number();
+            let prefix = self.claims.prefix();
+            for (raw_key, raw_owner) in storage::iter_prefix(&prefix) {
+                // Entries which can't be decoded as old claims are already in the new format.
+                let raw_content = &raw_key[prefix.len()..];
+                let (Ok(content), Ok(owner)) =
+                    (Vec::<u8>::decode_all(raw_content), T::AccountId::decode_all(&raw_owner))
+                else {
+                    continue;
+                };
+                storage::remove(&raw_key);
+                let claim = T::Hashing::hash(&content);
+                let old_deposit_key = [self.deposits.prefix().as_slice(), raw_content].concat();
+                if let Some(raw_deposit) = storage::remove(&old_deposit_key) {
+                    if let Ok(deposit) = BalanceOf::<T>::decode_all(&raw_deposit) {
+                        self.deposits.insert(claim.clone(), deposit);
+                    }
+                }
+                claims_v1.insert(claim, (owner, block_number));
+            }
+        }
+        if self.storage_version.get().unwrap_or(0) < 2 {
+            // Start the history of each claim with its current owner.
+            let prefix = self.claims.prefix();
+            for (raw_key, raw_claim) in storage::iter_prefix(&prefix) {
+                // Entries which can't be decoded as old claims are already in the new format.
+                let claim = T::Content::decode_all(&raw_key[prefix.len()..]);
+                let old = <(T::AccountId, T::BlockNumber)>::decode_all(&raw_claim);
+                let (Ok(claim), Ok((owner, block_number))) = (claim, old) else {
+                    continue;
+                };
+                let change = OwnerChange { block_number, owner: Some(owner.clone()) };
+                self.history.insert(claim.clone(), vec![change]);
+                let details = Claim { owner, block_number, timestamp: None, description: None };
+                self.claims.insert(claim, details);
+            }
+        }
+        if self.storage_version.get().unwrap_or(0) < 3 {
+            // Index the claims of each account.
+            let mut claims_of = BTreeMap::<T::AccountId, Vec<T::Content>>::new();
+            for (claim, details) in self.claims.iter() {
+                claims_of.entry(details.owner).or_default().push(claim);
+            }
+            for (owner, claims) in claims_of {
+                self.claims_of.insert(owner, claims);
+            }
+        }
+        self.storage_version.put(STORAGE_VERSION);
+    }
+
+    fn on_genesis(&mut self) {
+        self.storage_version.put(STORAGE_VERSION);
+    }
+
+    fn pre_upgrade(&self) -> Result<Vec<u8>, &'static str> {
+        Ok((storage::iter_prefix(&self.claims.prefix()).len() as u32).encode())
+    }
+
+    fn post_upgrade(&self, state: Vec<u8>) -> DispatchResult {
+        let claims_before = u32::decode_all(&state)?;
+        if self.claims.iter().count() as u32 != claims_before {
+            return Err("migration changed the number of claims");
+        }
+        let now = crate::system::Pallet::<T>::new().block_number();
+        for (claim, details) in self.claims.iter() {
+            if self.owner_at(&claim, now).as_ref() != Some(&details.owner) {
+                return Err("migration left claims without history");
+            }
+            if !self.claims_of(&details.owner).contains(&claim) {
+                return Err("migration left claims out of the index");
+            }
+        }
+        if self.storage_version.get() != Some(STORAGE_VERSION) {
+            return Err("migration did not update the storage version");
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Pallet;
+    use super::{Claim, OwnerChange, Pallet};
+    use crate::support::{
+        hashing::{Blake2_256, Hasher},
+        merkle::{self, MerkleProof},
+        storage::{self, StorageMap},
+        Hooks, OnRuntimeUpgrade, RuntimeVersion, UnixTime,
+    };
+    use crate::{balances, system};
 
     struct TestConfig;
 
+    // A clock stopped at a second after the Unix epoch.
+    struct Clock;
+
+    impl UnixTime for Clock {
+        fn now() -> Option<u64> {
+            Some(1_000)
+        }
+    }
+
     impl super::Config for TestConfig {
-        type Content = &'static str;
+        type Content = [u8; 32];
+        type Hashing = Blake2_256;
+        type Currency = balances::Pallet<Self>;
+        type Time = Clock;
+        const CLAIM_DEPOSIT: u128 = 10;
+        const DESCRIPTION_LIMIT: u32 = 16;
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        const EXISTENTIAL_DEPOSIT: u128 = 1;
     }
 
     impl crate::system::Config for TestConfig {
-        type AccountId = &'static str;
+        type AccountId = u64;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = ();
+        const VERSIONS: &'static [RuntimeVersion] =
+            &[RuntimeVersion { spec_name: "test", spec_version: 1 }];
     }
 
     #[test]
     fn basic_proof_of_existence() {
         let mut poe: Pallet<TestConfig> = Pallet::new();
-        let test_claim = "my_claim";
-        let test_caller = "0x123";
+        let test_claim = Blake2_256::hash(b"my_claim");
+        let test_caller = 0x123;
+        balances::Pallet::<TestConfig>::new().set_balance(&test_caller, 100);
 
         assert!(poe.claims.is_empty());
         assert!(poe.get_claim(&test_claim).is_none());
         assert!(poe.create_claim(test_caller, test_claim).is_ok());
-        assert_eq!(poe.get_claim(&test_claim), Some(&test_caller));
+        assert_eq!(poe.get_claim(&test_claim), Some(test_caller));
         assert!(poe
             .create_claim(test_caller, test_claim)
             .is_err_and(|e| e == "this content has already been claimed"));
 
         assert!(poe
-            .revoke_claim(test_caller, "not inserted previously")
+            .revoke_claim(test_caller, Blake2_256::hash(b"not inserted previously"))
             .is_err_and(|e| e == "claim does not exist"));
         assert!(poe
-            .revoke_claim("0x456", test_claim)
+            .revoke_claim(0x456, test_claim)
             .is_err_and(|e| e == "claim does not belong to caller"));
         assert!(poe.revoke_claim(test_caller, test_claim).is_ok());
         assert!(poe.get_claim(&test_claim).is_none());
     }
+
+    #[test]
+    fn claim_deposit() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let mut balances = balances::Pallet::<TestConfig>::new();
+        let (alice, bob) = (1, 2);
+        balances.set_balance(&alice, 15);
+        balances.set_balance(&bob, 5);
+
+        assert_eq!(poe.create_claim_from_bytes(bob, b"bob's".to_vec()), Err("Not enough funds."));
+        assert_eq!(poe.create_claim_from_bytes(alice, b"first".to_vec()), Ok(()));
+        assert_eq!((balances.balance(&alice), balances.reserved_balance(&alice)), (5, 10));
+        assert_eq!(
+            poe.create_claim_from_bytes(alice, b"second".to_vec()),
+            Err("Not enough funds.")
+        );
+
+        assert_eq!(poe.revoke_claim(alice, Blake2_256::hash(b"first")), Ok(()));
+        assert_eq!((balances.balance(&alice), balances.reserved_balance(&alice)), (15, 0));
+
+        // Claims made before deposits existed are revoked for free.
+        let old = Blake2_256::hash(b"old");
+        let claim = Claim { owner: bob, block_number: 0, timestamp: None, description: None };
+        poe.claims.insert(old, claim);
+        assert_eq!(poe.revoke_claim(bob, old), Ok(()));
+        assert_eq!(balances.balance(&bob), 5);
+    }
+
+    #[test]
+    fn verify_content() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let alice = 1;
+        balances::Pallet::<TestConfig>::new().set_balance(&alice, 100);
+        system::Pallet::<TestConfig>::new().inc_block_number();
+
+        assert_eq!(poe.verify(b"hello"), None);
+        assert_eq!(poe.create_claim_from_bytes(alice, b"hello".to_vec()), Ok(()));
+        assert_eq!(poe.verify(b"hello"), Some((alice, 1)));
+        assert_eq!(poe.verify(b"hello!"), None);
+        // Only the hash of the content is stored.
+        assert_eq!(poe.get_claim(&Blake2_256::hash(b"hello")), Some(alice));
+        assert_eq!(
+            poe.create_claim(alice, Blake2_256::hash(b"hello")),
+            Err("this content has already been claimed")
+        );
+    }
+
+    #[test]
+    fn claim_metadata_and_history() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let mut system = system::Pallet::<TestConfig>::new();
+        let (alice, bob) = (1, 2);
+        balances::Pallet::<TestConfig>::new().set_balance(&alice, 100);
+        balances::Pallet::<TestConfig>::new().set_balance(&bob, 100);
+        let hello = Blake2_256::hash(b"hello");
+
+        system.inc_block_number();
+        assert_eq!(poe.create_claim(alice, hello), Ok(()));
+        let claim = poe.claim(&hello).unwrap();
+        assert_eq!(
+            (claim.block_number, claim.timestamp, claim.description),
+            (1, Some(1_000), None)
+        );
+
+        let description = |d: &str| Some(d.to_string());
+        assert_eq!(
+            poe.set_description(bob, hello, description("bob's")),
+            Err("claim does not belong to caller")
+        );
+        assert_eq!(
+            poe.set_description(alice, hello, description("https://example.com")),
+            Err("description is too long")
+        );
+        assert_eq!(poe.set_description(alice, hello, description("ipfs://hello")), Ok(()));
+        assert_eq!(poe.claim(&hello).unwrap().description, description("ipfs://hello"));
+
+        system.inc_block_number();
+        system.inc_block_number();
+        assert_eq!(poe.revoke_claim(alice, hello), Ok(()));
+        system.inc_block_number();
+        assert_eq!(poe.create_claim(bob, hello), Ok(()));
+        assert_eq!(poe.claim(&hello).unwrap().description, None);
+
+        assert_eq!(
+            poe.history(&hello),
+            vec![
+                OwnerChange { block_number: 1, owner: Some(alice) },
+                OwnerChange { block_number: 3, owner: None },
+                OwnerChange { block_number: 4, owner: Some(bob) },
+            ]
+        );
+        assert_eq!(poe.owner_at(&hello, 0), None);
+        assert_eq!(poe.owner_at(&hello, 2), Some(alice));
+        assert_eq!(poe.owner_at(&hello, 3), None);
+        assert_eq!(poe.owner_at(&hello, 9), Some(bob));
+    }
+
+    #[test]
+    fn transfer_and_delegate() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let mut balances = balances::Pallet::<TestConfig>::new();
+        let (alice, bob, charlie) = (1, 2, 3);
+        balances.set_balance(&alice, 100);
+        balances.set_balance(&bob, 5);
+        balances.set_balance(&charlie, 100);
+        let (first, second) = (Blake2_256::hash(b"first"), Blake2_256::hash(b"second"));
+        assert_eq!(poe.create_claim(alice, first), Ok(()));
+        assert_eq!(poe.create_claim(alice, second), Ok(()));
+        assert_eq!(poe.claims_of(&alice), vec![first, second]);
+
+        assert_eq!(poe.transfer_claim(bob, first, bob), Err("claim does not belong to caller"));
+        // Bob can't reserve the deposit of the claim.
+        assert_eq!(poe.transfer_claim(alice, first, bob), Err("Not enough funds."));
+        assert_eq!(poe.transfer_claim(alice, first, charlie), Ok(()));
+        assert_eq!(poe.get_claim(&first), Some(charlie));
+        assert_eq!((poe.claims_of(&alice), poe.claims_of(&charlie)), (vec![second], vec![first]));
+        assert_eq!(balances.reserved_balance(&alice), 10);
+        assert_eq!(balances.reserved_balance(&charlie), 10);
+
+        assert_eq!(
+            poe.transfer_approved_claim(bob, second, bob),
+            Err("caller is not approved to transfer the claim")
+        );
+        assert_eq!(poe.approve_transfer(bob, second, bob), Err("claim does not belong to caller"));
+        assert_eq!(poe.approve_transfer(alice, second, bob), Ok(()));
+        assert_eq!(poe.cancel_approval(alice, second), Ok(()));
+        assert_eq!(poe.cancel_approval(alice, second), Err("claim has no approval"));
+        assert_eq!(poe.approve_transfer(alice, second, bob), Ok(()));
+        assert_eq!(poe.transfer_approved_claim(bob, second, charlie), Ok(()));
+        assert_eq!(poe.approved(&second), None);
+        assert!(poe.claims_of(&alice).is_empty());
+        assert_eq!(poe.claims_of(&charlie), vec![first, second]);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+
+        assert_eq!(poe.revoke_claim(charlie, first), Ok(()));
+        assert_eq!(poe.claims_of(&charlie), vec![second]);
+        let owners = poe.history(&first).into_iter().map(|change| change.owner);
+        assert_eq!(owners.collect::<Vec<_>>(), vec![Some(alice), Some(charlie), None]);
+    }
+
+    #[test]
+    fn expiring_claims() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let mut balances = balances::Pallet::<TestConfig>::new();
+        let mut system = system::Pallet::<TestConfig>::new();
+        let (alice, bob) = (1, 2);
+        balances.set_balance(&alice, 100);
+        balances.set_balance(&bob, 100);
+        let (first, second) = (Blake2_256::hash(b"first"), Blake2_256::hash(b"second"));
+        system.inc_block_number();
+        system.inc_block_number();
+        assert_eq!(poe.create_claim(alice, first), Ok(()));
+        assert_eq!(poe.create_claim(alice, second), Ok(()));
+
+        assert_eq!(poe.set_expiry(bob, first, Some(5)), Err("claim does not belong to caller"));
+        assert_eq!(poe.set_expiry(alice, first, Some(1)), Err("expiry is in the past"));
+        assert_eq!(poe.set_expiry(alice, first, Some(3)), Ok(()));
+        assert_eq!(poe.set_expiry(alice, first, Some(5)), Ok(()));
+        assert_eq!(poe.set_expiry(alice, second, Some(5)), Ok(()));
+        assert_eq!(poe.set_expiry(alice, second, None), Ok(()));
+        assert_eq!((poe.expiry(&first), poe.expiry(&second)), (Some(5), None));
+        // The owner at expiry gets the deposit back.
+        assert_eq!(poe.transfer_claim(alice, first, bob), Ok(()));
+
+        assert_eq!(poe.on_finalize(3), Ok(()));
+        assert_eq!(poe.get_claim(&first), Some(bob));
+        assert_eq!(balances.reserved_balance(&bob), 10);
+        assert_eq!(poe.on_finalize(5), Ok(()));
+        assert_eq!(poe.get_claim(&first), None);
+        assert_eq!(poe.expiry(&first), None);
+        assert!(poe.claims_of(&bob).is_empty());
+        assert_eq!(balances.reserved_balance(&bob), 0);
+        assert_eq!(poe.get_claim(&second), Some(alice));
+        assert!(poe.expiring.is_empty());
+    }
+
+    #[test]
+    fn batch_claims() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let alice = 1;
+        balances::Pallet::<TestConfig>::new().set_balance(&alice, 100);
+        system::Pallet::<TestConfig>::new().inc_block_number();
+        let documents = (0..100).map(|i| Blake2_256::hash(&[i])).collect::<Vec<_>>();
+        let root = merkle::root::<Blake2_256>(&documents).unwrap();
+        let proof = merkle::proof::<Blake2_256>(&documents, 42).unwrap();
+        assert_eq!(poe.verify_proof(&proof), None);
+
+        assert_eq!(poe.anchor_batch(alice, root, 0), Err("batch is empty"));
+        assert_eq!(poe.anchor_batch(alice, root, 100), Ok(()));
+        assert_eq!(poe.batch_size(&root), Some(100));
+        assert_eq!(poe.verify_proof(&proof), Some((alice, 1)));
+        let mut forged = proof.clone();
+        forged.leaf = Blake2_256::hash(b"forged");
+        assert_eq!(poe.verify_proof(&forged), None);
+
+        // A plain claim is not a batch, even of a single document.
+        assert_eq!(poe.create_claim(alice, documents[0]), Ok(()));
+        let single = merkle::proof::<Blake2_256>(&documents[..1], 0).unwrap();
+        assert_eq!(poe.verify_proof(&single), None);
+        let single = MerkleProof { root: documents[0], ..single };
+        assert_eq!(poe.verify_proof(&single), None);
+
+        // A batch of a single document has a proof without siblings.
+        let single_root = merkle::root::<Blake2_256>(&documents[..1]).unwrap();
+        assert_eq!(poe.anchor_batch(alice, single_root, 1), Ok(()));
+        let single = merkle::proof::<Blake2_256>(&documents[..1], 0).unwrap();
+        assert!(single.siblings.is_empty());
+        assert_eq!(poe.verify_proof(&single), Some((alice, 1)));
+
+        // A batch claimed with fewer documents than its tree has is not deep enough for the proof.
+        let small = merkle::root::<Blake2_256>(&documents[..8]).unwrap();
+        assert_eq!(poe.anchor_batch(alice, small, 4), Ok(()));
+        let deep = merkle::proof::<Blake2_256>(&documents[..8], 5).unwrap();
+        assert_eq!(poe.verify_proof(&deep), None);
+
+        assert_eq!(poe.revoke_claim(alice, root), Ok(()));
+        assert_eq!(poe.batch_size(&root), None);
+        assert_eq!(poe.verify_proof(&proof), None);
+    }
+
+    #[test]
+    fn migrate_claims_to_hashes() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        let old_claims = StorageMap::<Vec<u8>, u64>::new("ProofOfExistence", "Claims");
+        let old_deposits = StorageMap::<Vec<u8>, u128>::new("ProofOfExistence", "Deposits");
+        old_claims.insert(b"first".to_vec(), 1);
+        old_claims.insert(b"second".to_vec(), 2);
+        old_deposits.insert(b"second".to_vec(), 10);
+        system::Pallet::<TestConfig>::new().inc_block_number();
+
+        assert_eq!(poe.try_on_runtime_upgrade(), Ok(()));
+        assert_eq!(poe.verify(b"first"), Some((1, 1)));
+        assert_eq!(poe.verify(b"second"), Some((2, 1)));
+        assert_eq!(poe.deposits.get(&Blake2_256::hash(b"second")), Some(10));
+        assert_eq!(poe.owner_at(&Blake2_256::hash(b"first"), 1), Some(1));
+        assert_eq!(poe.claims_of(&2), vec![Blake2_256::hash(b"second")]);
+        assert!(!old_deposits.contains_key(&b"second".to_vec()));
+
+        // Migrating again leaves the claims as they are.
+        assert_eq!(poe.try_on_runtime_upgrade(), Ok(()));
+        assert_eq!(poe.claims.iter().count(), 2);
+        assert_eq!(poe.verify(b"first"), Some((1, 1)));
+    }
+
+    #[test]
+    fn migrations_skip_new_chains() {
+        let mut poe: Pallet<TestConfig> = Pallet::new();
+        poe.on_genesis();
+        balances::Pallet::<TestConfig>::new().set_balance(&1, 100);
+        system::Pallet::<TestConfig>::new().inc_block_number();
+        assert_eq!(poe.create_claim_from_bytes(1, b"first".to_vec()), Ok(()));
+
+        let state = storage::snapshot();
+        assert_eq!(poe.try_on_runtime_upgrade(), Ok(()));
+        assert_eq!(storage::snapshot(), state);
+    }
 }
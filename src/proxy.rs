@@ -0,0 +1,617 @@
+use crate::support::{
+	hashing::blake2_256, storage::StorageMap, Currency, Decode, DispatchResult, Dispatchable,
+	Encode, Hooks, InstanceFilter, OnRuntimeUpgrade, Origin, ReservableCurrency,
+};
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, Zero};
+
+pub trait Config: crate::system::Config {
+	/// The call type of the runtime, which proxies dispatch.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The kinds of proxies, each filtering the calls a proxy can make.
+	type ProxyType: InstanceFilter<Self::RuntimeCall> + Debug + Clone + PartialEq + Encode + Decode;
+	/// The pallet holding the funds of accounts, usually `balances`.
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<EventOf<Self>> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The funds reserved from an account for each of its proxies.
+	const PROXY_DEPOSIT: BalanceOf<Self>;
+	/// The maximum number of proxies of an account.
+	const MAX_PROXIES: u32;
+	/// The maximum number of pending announcements of a proxy.
+	const MAX_PENDING: u32;
+}
+
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// The hash of an encoded call, which proxies with a delay announce.
+pub type CallHash = [u8; 32];
+
+/// A proxy of an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyDefinition<AccountId, ProxyType, BlockNumber> {
+	/// The account which can act on behalf of the account.
+	pub delegate: AccountId,
+	/// The kind of proxy, which filters the calls the delegate can make.
+	pub proxy_type: ProxyType,
+	/// The number of blocks between the announcement of a call and its dispatch. Without delay,
+	/// calls don't need to be announced.
+	pub delay: BlockNumber,
+}
+
+impl<AccountId: Encode, ProxyType: Encode, BlockNumber: Encode> Encode
+	for ProxyDefinition<AccountId, ProxyType, BlockNumber>
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.delegate.encode_to(dest);
+		self.proxy_type.encode_to(dest);
+		self.delay.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, ProxyType: Decode, BlockNumber: Decode> Decode
+	for ProxyDefinition<AccountId, ProxyType, BlockNumber>
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			delegate: Decode::decode(input)?,
+			proxy_type: Decode::decode(input)?,
+			delay: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A call a proxy announced it will dispatch on behalf of `real`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement<AccountId, BlockNumber> {
+	/// The account the call will be dispatched on behalf of.
+	pub real: AccountId,
+	/// The hash of the call.
+	pub call_hash: CallHash,
+	/// The block the call was announced at.
+	pub height: BlockNumber,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for Announcement<AccountId, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.real.encode_to(dest);
+		self.call_hash.encode_to(dest);
+		self.height.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Decode for Announcement<AccountId, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			real: Decode::decode(input)?,
+			call_hash: Decode::decode(input)?,
+			height: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A proxy, for a runtime `T`.
+pub type ProxyDefinitionOf<T> = ProxyDefinition<
+	<T as crate::system::Config>::AccountId,
+	<T as Config>::ProxyType,
+	<T as crate::system::Config>::BlockNumber,
+>;
+
+/// An announcement, for a runtime `T`.
+pub type AnnouncementOf<T> = Announcement<
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::BlockNumber,
+>;
+
+/// The proxies of each account.
+type Proxies<AccountId, ProxyType, BlockNumber> =
+	StorageMap<AccountId, Vec<ProxyDefinition<AccountId, ProxyType, BlockNumber>>>;
+
+/// The pending announcements of each proxy.
+type Announcements<AccountId, BlockNumber> =
+	StorageMap<AccountId, Vec<Announcement<AccountId, BlockNumber>>>;
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<
+	<T as crate::system::Config>::AccountId,
+	<T as Config>::ProxyType,
+	<T as crate::system::Config>::BlockNumber,
+>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId, ProxyType, BlockNumber> {
+	/// A proxy dispatched a call, with the given result.
+	ProxyExecuted { result: Result<(), String> },
+	/// `delegator` added `delegatee` as a proxy.
+	ProxyAdded {
+		delegator: AccountId,
+		delegatee: AccountId,
+		proxy_type: ProxyType,
+		delay: BlockNumber,
+	},
+	/// `delegator` removed `delegatee` as a proxy.
+	ProxyRemoved {
+		delegator: AccountId,
+		delegatee: AccountId,
+		proxy_type: ProxyType,
+		delay: BlockNumber,
+	},
+	/// `proxy` announced the call `call_hash`, to dispatch on behalf of `real`.
+	Announced { real: AccountId, proxy: AccountId, call_hash: CallHash },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode, ProxyType: Encode, BlockNumber: Encode> Encode
+	for Event<AccountId, ProxyType, BlockNumber>
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::ProxyExecuted { result } => (0u8, result).encode_to(dest),
+			Event::ProxyAdded { delegator, delegatee, proxy_type, delay } => {
+				(1u8, (delegator, delegatee, (proxy_type, delay))).encode_to(dest)
+			},
+			Event::ProxyRemoved { delegator, delegatee, proxy_type, delay } => {
+				(2u8, (delegator, delegatee, (proxy_type, delay))).encode_to(dest)
+			},
+			Event::Announced { real, proxy, call_hash } => {
+				(3u8, (real, proxy, call_hash)).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<AccountId: Decode, ProxyType: Decode, BlockNumber: Decode> Decode
+	for Event<AccountId, ProxyType, BlockNumber>
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => Event::ProxyExecuted { result: Decode::decode(input)? },
+			1 => {
+				let (delegator, delegatee, (proxy_type, delay)) = Decode::decode(input)?;
+				Event::ProxyAdded { delegator, delegatee, proxy_type, delay }
+			},
+			2 => {
+				let (delegator, delegatee, (proxy_type, delay)) = Decode::decode(input)?;
+				Event::ProxyRemoved { delegator, delegatee, proxy_type, delay }
+			},
+			3 => {
+				let (real, proxy, call_hash) = Decode::decode(input)?;
+				Event::Announced { real, proxy, call_hash }
+			},
+			_ => return Err("invalid proxy event"),
+		})
+	}
+}
+
+/// This is the Proxy Module.
+/// It lets accounts register proxies, which can dispatch some of their calls on their behalf.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The proxies of each account.
+	proxies: Proxies<T::AccountId, T::ProxyType, T::BlockNumber>,
+	// The pending announcements of each proxy.
+	announcements: Announcements<T::AccountId, T::BlockNumber>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the proxy module.
+	pub fn new() -> Self {
+		Self {
+			proxies: StorageMap::new("Proxy", "Proxies"),
+			announcements: StorageMap::new("Proxy", "Announcements"),
+		}
+	}
+
+	/// The proxies of `real`.
+	pub fn proxies(&self, real: &T::AccountId) -> Vec<ProxyDefinitionOf<T>> {
+		self.proxies.get(real).unwrap_or_default()
+	}
+
+	/// The pending announcements of `delegate`.
+	pub fn announcements(&self, delegate: &T::AccountId) -> Vec<AnnouncementOf<T>> {
+		self.announcements.get(delegate).unwrap_or_default()
+	}
+
+	/// The hash of `call`, which proxies with a delay announce.
+	pub fn call_hash(call: &T::RuntimeCall) -> CallHash {
+		blake2_256(&call.encode())
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// The proxy `delegate` of `real`, of the type `force_proxy_type` if given.
+	fn find_proxy(
+		&self,
+		real: &T::AccountId,
+		delegate: &T::AccountId,
+		force_proxy_type: Option<T::ProxyType>,
+	) -> Result<ProxyDefinitionOf<T>, &'static str> {
+		self.proxies(real)
+			.into_iter()
+			.find(|proxy| {
+				&proxy.delegate == delegate
+					&& force_proxy_type.as_ref().is_none_or(|ty| ty == &proxy.proxy_type)
+			})
+			.ok_or("caller is not a proxy of the account")
+	}
+
+	/// Remove the announcement of the call `call_hash`, which `delegate` made for `real`.
+	fn remove_announcement_of(
+		&mut self,
+		delegate: &T::AccountId,
+		real: &T::AccountId,
+		call_hash: &CallHash,
+	) -> DispatchResult {
+		let mut announcements = self.announcements(delegate);
+		let position = announcements
+			.iter()
+			.position(|announcement| {
+				&announcement.real == real && &announcement.call_hash == call_hash
+			})
+			.ok_or("call was not announced")?;
+		announcements.remove(position);
+		if announcements.is_empty() {
+			self.announcements.remove(delegate);
+		} else {
+			self.announcements.insert(delegate.clone(), announcements);
+		}
+		Ok(())
+	}
+
+	/// Ensure the type of `proxy` allows `call`.
+	fn ensure_allowed(proxy: &ProxyDefinitionOf<T>, call: &T::RuntimeCall) -> DispatchResult {
+		if !proxy.proxy_type.filter(call) {
+			return Err("call is not allowed for this proxy type");
+		}
+		Ok(())
+	}
+
+	/// Dispatch `call` on behalf of `real`, recording its result in a `ProxyExecuted` event.
+	fn do_proxy(&mut self, real: T::AccountId, call: T::RuntimeCall) -> DispatchResult {
+		let result = call.dispatch(Origin::Signed(real)).map_err(String::from);
+		self.deposit_event(Event::ProxyExecuted { result });
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `call` on behalf of `real`, which the `caller` is a proxy of, without delay. The
+	/// type of the proxy must allow the call. This call succeeds even if `call` fails: its result
+	/// is recorded in a `ProxyExecuted` event.
+	// The calls are boxed, since they are part of the call type of the runtime.
+	#[allow(clippy::boxed_local)]
+	#[call_index(0)]
+	pub fn proxy(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		force_proxy_type: Option<T::ProxyType>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let proxy = self.find_proxy(&real, &caller, force_proxy_type)?;
+		if !proxy.delay.is_zero() {
+			return Err("proxy must announce the call first");
+		}
+		Self::ensure_allowed(&proxy, &call)?;
+		self.do_proxy(real, *call)
+	}
+
+	/// Make `delegate` a proxy of the `caller`, of the type `proxy_type`, with the given `delay`.
+	/// The `caller` reserves a deposit until the proxy is removed.
+	#[call_index(1)]
+	pub fn add_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		if delegate == caller {
+			return Err("account cannot be its own proxy");
+		}
+		let proxy = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		let mut proxies = self.proxies(&caller);
+		if proxies.contains(&proxy) {
+			return Err("proxy already exists");
+		}
+		if proxies.len() >= T::MAX_PROXIES as usize {
+			return Err("too many proxies");
+		}
+		T::Currency::default().reserve(&caller, T::PROXY_DEPOSIT)?;
+		let ProxyDefinition { proxy_type, delay, .. } = proxy.clone();
+		proxies.push(proxy);
+		self.proxies.insert(caller.clone(), proxies);
+		self.deposit_event(Event::ProxyAdded {
+			delegator: caller,
+			delegatee: delegate,
+			proxy_type,
+			delay,
+		});
+		Ok(())
+	}
+
+	/// Remove the proxy `delegate` of the `caller`, which returns its deposit.
+	#[call_index(2)]
+	pub fn remove_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		let proxy = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		let mut proxies = self.proxies(&caller);
+		let position =
+			proxies.iter().position(|other| other == &proxy).ok_or("proxy does not exist")?;
+		proxies.remove(position);
+		if proxies.is_empty() {
+			self.proxies.remove(&caller);
+		} else {
+			self.proxies.insert(caller.clone(), proxies);
+		}
+		T::Currency::default().unreserve(&caller, T::PROXY_DEPOSIT);
+		let ProxyDefinition { proxy_type, delay, .. } = proxy;
+		self.deposit_event(Event::ProxyRemoved {
+			delegator: caller,
+			delegatee: delegate,
+			proxy_type,
+			delay,
+		});
+		Ok(())
+	}
+
+	/// Announce the call `call_hash`, which the `caller` will dispatch on behalf of `real` with
+	/// `proxy_announced`, once the delay of its proxy has passed.
+	#[call_index(3)]
+	pub fn announce(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		self.find_proxy(&real, &caller, None)?;
+		let mut announcements = self.announcements(&caller);
+		if announcements.len() >= T::MAX_PENDING as usize {
+			return Err("too many announcements");
+		}
+		let height = crate::system::Pallet::<T>::new().block_number();
+		announcements.push(Announcement { real: real.clone(), call_hash, height });
+		self.announcements.insert(caller.clone(), announcements);
+		self.deposit_event(Event::Announced { real, proxy: caller, call_hash });
+		Ok(())
+	}
+
+	/// Withdraw an announcement the `caller` made.
+	#[call_index(4)]
+	pub fn remove_announcement(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		self.remove_announcement_of(&caller, &real, &call_hash)
+	}
+
+	/// Reject an announcement `delegate` made on behalf of the `caller`.
+	#[call_index(5)]
+	pub fn reject_announcement(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		call_hash: CallHash,
+	) -> DispatchResult {
+		self.remove_announcement_of(&delegate, &caller, &call_hash)
+	}
+
+	/// Dispatch `call`, which the proxy `delegate` announced, on behalf of `real`, once the delay
+	/// of the proxy has passed. Anyone can make this call, which succeeds even if `call` fails:
+	/// its result is recorded in a `ProxyExecuted` event.
+	#[allow(clippy::boxed_local)]
+	#[call_index(6)]
+	pub fn proxy_announced(
+		&mut self,
+		_caller: T::AccountId,
+		delegate: T::AccountId,
+		real: T::AccountId,
+		force_proxy_type: Option<T::ProxyType>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let proxy = self.find_proxy(&real, &delegate, force_proxy_type)?;
+		// The announcement is kept if the call is not allowed.
+		Self::ensure_allowed(&proxy, &call)?;
+		let call_hash = Self::call_hash(&call);
+		let now = crate::system::Pallet::<T>::new().block_number();
+		let announcement = self
+			.announcements(&delegate)
+			.into_iter()
+			.find(|announcement| announcement.real == real && announcement.call_hash == call_hash)
+			.ok_or("call was not announced")?;
+		// A delay too long to be represented is never over.
+		let due = announcement.height.checked_add(&proxy.delay);
+		if due.is_none_or(|due| now < due) {
+			return Err("announcement is not due yet");
+		}
+		self.remove_announcement_of(&delegate, &real, &call_hash)?;
+		self.do_proxy(real, *call)
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, EventOf, Pallet};
+	use crate::support::{
+		testing::{self, TestCall},
+		InstanceFilter, Origin, RuntimeVersion,
+	};
+	use crate::{balances, system};
+
+	// A proxy type is the largest value of the calls the proxy can make.
+	impl InstanceFilter<TestCall> for u32 {
+		fn filter(&self, call: &TestCall) -> bool {
+			call.value <= *self
+		}
+	}
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type ProxyType = u32;
+		type Currency = balances::Pallet<Self>;
+		type RuntimeEvent = EventOf<Self>;
+		const PROXY_DEPOSIT: u128 = 10;
+		const MAX_PROXIES: u32 = 2;
+		const MAX_PENDING: u32 = 2;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+	const CHARLIE: u64 = 3;
+	const DAVE: u64 = 4;
+
+	fn setup() -> (Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance(&ALICE, 100);
+		balances.set_balance(&BOB, 5);
+		(Pallet::new(), balances)
+	}
+
+	fn events() -> Vec<EventOf<TestConfig>> {
+		system::Pallet::<TestConfig>::new().events()
+	}
+
+	#[test]
+	fn add_and_remove_proxies() {
+		let (mut proxy, balances) = setup();
+
+		assert_eq!(proxy.add_proxy(ALICE, ALICE, 1, 0), Err("account cannot be its own proxy"));
+		assert_eq!(proxy.add_proxy(BOB, ALICE, 1, 0), Err("Not enough funds."));
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 1, 0), Ok(()));
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 1, 0), Err("proxy already exists"));
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 2, 0), Ok(()));
+		assert_eq!(proxy.add_proxy(ALICE, CHARLIE, 1, 0), Err("too many proxies"));
+		assert_eq!(balances.reserved_balance(&ALICE), 20);
+
+		assert_eq!(proxy.remove_proxy(ALICE, BOB, 1, 1), Err("proxy does not exist"));
+		assert_eq!(proxy.remove_proxy(ALICE, BOB, 1, 0), Ok(()));
+		assert_eq!(proxy.remove_proxy(ALICE, BOB, 2, 0), Ok(()));
+		assert!(proxy.proxies(&ALICE).is_empty());
+		assert_eq!(balances.reserved_balance(&ALICE), 0);
+		testing::assert_round_trip(events());
+	}
+
+	#[test]
+	fn proxy() {
+		let (mut proxy, _) = setup();
+		assert_eq!(
+			proxy.proxy(BOB, ALICE, None, Box::new(TestCall::new(1))),
+			Err("caller is not a proxy of the account")
+		);
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 5, 0), Ok(()));
+		assert_eq!(proxy.add_proxy(ALICE, CHARLIE, 5, 1), Ok(()));
+
+		assert_eq!(
+			proxy.proxy(BOB, ALICE, Some(4), Box::new(TestCall::new(1))),
+			Err("caller is not a proxy of the account")
+		);
+		assert_eq!(
+			proxy.proxy(BOB, ALICE, None, Box::new(TestCall::new(6))),
+			Err("call is not allowed for this proxy type")
+		);
+		assert_eq!(
+			proxy.proxy(CHARLIE, ALICE, None, Box::new(TestCall::new(1))),
+			Err("proxy must announce the call first")
+		);
+		assert_eq!(testing::dispatched(), vec![]);
+
+		assert_eq!(proxy.proxy(BOB, ALICE, Some(5), Box::new(TestCall::new(5))), Ok(()));
+		assert_eq!(testing::dispatched(), vec![(5, Origin::Signed(ALICE))]);
+		assert_eq!(proxy.proxy(BOB, ALICE, None, Box::new(TestCall::new(0))), Ok(()));
+		let executed = events().split_off(2);
+		assert_eq!(
+			executed,
+			vec![
+				Event::ProxyExecuted { result: Ok(()) },
+				Event::ProxyExecuted { result: Err("zero".to_string()) },
+			]
+		);
+	}
+
+	#[test]
+	fn announcements() {
+		let (mut proxy, _) = setup();
+		let mut system = system::Pallet::<TestConfig>::new();
+		let hash = |value| Pallet::<TestConfig>::call_hash(&TestCall::new(value));
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 5, 2), Ok(()));
+
+		assert_eq!(
+			proxy.announce(CHARLIE, ALICE, hash(1)),
+			Err("caller is not a proxy of the account")
+		);
+		assert_eq!(proxy.announce(BOB, ALICE, hash(1)), Ok(()));
+		assert_eq!(proxy.announce(BOB, ALICE, hash(6)), Ok(()));
+		assert_eq!(proxy.announce(BOB, ALICE, hash(2)), Err("too many announcements"));
+		assert_eq!(proxy.remove_announcement(BOB, ALICE, hash(2)), Err("call was not announced"));
+		assert_eq!(proxy.reject_announcement(DAVE, BOB, hash(1)), Err("call was not announced"));
+
+		let proxy_announced = |proxy: &mut Pallet<TestConfig>, value| {
+			proxy.proxy_announced(DAVE, BOB, ALICE, None, Box::new(TestCall::new(value)))
+		};
+		assert_eq!(proxy_announced(&mut proxy, 1), Err("announcement is not due yet"));
+		system.inc_block_number();
+		system.inc_block_number();
+		assert_eq!(proxy_announced(&mut proxy, 2), Err("call was not announced"));
+		// A call the proxy is not allowed to make keeps its announcement.
+		assert_eq!(proxy_announced(&mut proxy, 6), Err("call is not allowed for this proxy type"));
+		assert_eq!(proxy.announcements(&BOB).len(), 2);
+		assert_eq!(proxy_announced(&mut proxy, 1), Ok(()));
+		assert_eq!(testing::dispatched(), vec![(1, Origin::Signed(ALICE))]);
+		assert_eq!(proxy_announced(&mut proxy, 1), Err("call was not announced"));
+
+		assert_eq!(proxy.reject_announcement(ALICE, BOB, hash(6)), Ok(()));
+		assert!(proxy.announcements(&BOB).is_empty());
+	}
+
+	#[test]
+	fn huge_delays_are_never_over() {
+		let (mut proxy, _) = setup();
+		system::Pallet::<TestConfig>::new().inc_block_number();
+		let call = Box::new(TestCall::new(1));
+		assert_eq!(proxy.add_proxy(ALICE, BOB, 5, u32::MAX), Ok(()));
+		assert_eq!(proxy.announce(BOB, ALICE, Pallet::<TestConfig>::call_hash(&call)), Ok(()));
+
+		assert_eq!(
+			proxy.proxy_announced(DAVE, BOB, ALICE, None, call),
+			Err("announcement is not due yet")
+		);
+		assert_eq!(proxy.announcements(&BOB).len(), 1);
+	}
+}
@@ -0,0 +1,1427 @@
+use crate::support::{
+    self, ChargeTransaction, Decode, Dispatch, DispatchResult, Encode, InstanceFilter, Reasons,
+    RuntimeVersion,
+};
+use crate::{
+    assets, balances, democracy, multisig, nfts, proof_of_existence, proxy, scheduler, sudo,
+    system, timestamp, treasury, utility, vesting,
+};
+
+// These are the concrete types we will use in our simple state machine.
+// Modules are configured for these types directly, and they satisfy all of our
+// trait requirements.
+pub mod types {
+    pub type AccountId = String;
+    pub type Balance = u128;
+    pub type BlockNumber = u32;
+    pub type Nonce = u32;
+    pub type Content = [u8; 32];
+    pub type AssetId = u32;
+    pub type CollectionId = u32;
+    pub type ItemId = u32;
+    pub type Extrinsic = crate::support::Extrinsic<AccountId, super::RuntimeCall>;
+    pub type Header = crate::support::Header<BlockNumber>;
+    pub type Block = crate::support::Block<Header, Extrinsic>;
+}
+
+// This is our main Runtime.
+// It accumulates all of the different pallets we want to use.
+#[derive(Debug)]
+#[macros::runtime]
+pub struct Runtime {
+    #[pallet_index(0)]
+    pub system: system::Pallet<Self>,
+    #[pallet_index(1)]
+    pub balances: balances::Pallet<Self>,
+    #[pallet_index(2)]
+    pub proof_of_existence: proof_of_existence::Pallet<Self>,
+    #[pallet_index(3)]
+    pub assets: assets::Pallet<Self>,
+    #[pallet_index(4)]
+    #[pallet_event]
+    pub nfts: nfts::Pallet<Self>,
+    #[pallet_index(5)]
+    pub vesting: vesting::Pallet<Self>,
+    #[pallet_index(6)]
+    pub timestamp: timestamp::Pallet<Self>,
+    #[pallet_index(7)]
+    #[pallet_event]
+    pub sudo: sudo::Pallet<Self>,
+    #[pallet_index(8)]
+    #[pallet_event(Event)]
+    pub utility: utility::Pallet<Self>,
+    #[pallet_index(9)]
+    #[pallet_event]
+    pub multisig: multisig::Pallet<Self>,
+    #[pallet_index(10)]
+    #[pallet_event]
+    pub proxy: proxy::Pallet<Self>,
+    #[pallet_index(11)]
+    #[pallet_event]
+    pub scheduler: scheduler::Pallet<Self>,
+    #[pallet_index(12)]
+    #[pallet_event]
+    pub democracy: democracy::Pallet<Self>,
+    #[pallet_index(13)]
+    #[pallet_event]
+    pub treasury: treasury::Pallet<Self>,
+}
+
+/// The kinds of proxies, which filter the calls a proxy can make on behalf of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyType {
+    /// Any call.
+    Any,
+    /// The calls of the balances pallet.
+    BalancesOnly,
+    /// The calls of the proof of existence pallet.
+    ClaimsOnly,
+    /// Any call which can't move funds, assets, items or claims, or approve someone else to.
+    /// Calls dispatching other calls are excluded, since they could contain transfers.
+    NonTransfer,
+}
+
+impl InstanceFilter<RuntimeCall> for ProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            ProxyType::Any => true,
+            ProxyType::BalancesOnly => matches!(call, RuntimeCall::balances(_)),
+            ProxyType::ClaimsOnly => matches!(call, RuntimeCall::proof_of_existence(_)),
+            ProxyType::NonTransfer => !matches!(
+                call,
+                RuntimeCall::balances(_)
+                    | RuntimeCall::assets(assets::Call::transfer { .. })
+                    | RuntimeCall::assets(assets::Call::approve_transfer { .. })
+                    | RuntimeCall::assets(assets::Call::transfer_approved { .. })
+                    | RuntimeCall::nfts(nfts::Call::transfer { .. })
+                    | RuntimeCall::nfts(nfts::Call::approve_transfer { .. })
+                    | RuntimeCall::proof_of_existence(
+                        proof_of_existence::Call::transfer_claim { .. }
+                            | proof_of_existence::Call::approve_transfer { .. }
+                            | proof_of_existence::Call::transfer_approved_claim { .. }
+                    )
+                    | RuntimeCall::vesting(vesting::Call::vested_transfer { .. })
+                    | RuntimeCall::sudo(_)
+                    | RuntimeCall::utility(_)
+                    | RuntimeCall::multisig(_)
+                    | RuntimeCall::proxy(_)
+                    | RuntimeCall::scheduler(_)
+            ),
+        }
+    }
+}
+
+/// Each proxy type is encoded as the index of its variant.
+impl Encode for ProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for ProxyType {
+    fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode(input)? {
+            0 => Ok(ProxyType::Any),
+            1 => Ok(ProxyType::BalancesOnly),
+            2 => Ok(ProxyType::ClaimsOnly),
+            3 => Ok(ProxyType::NonTransfer),
+            _ => Err("invalid proxy type"),
+        }
+    }
+}
+
+/// The versions of the runtime known to this binary, oldest first. They differ by the layout of
+/// the stored data, which the migrations update, and by the dispatch logic of `NativeVersions`.
+/// - 1: the initial runtime.
+/// - 2: balances no longer stores accounts with a zero balance.
+/// - 3: transfers reap senders left with less than the existential deposit. No data is migrated:
+///   accounts already below it are kept.
+/// - 4: balances tracks the total issuance.
+/// - 5: proof of existence claims are keyed by the hash of their content.
+/// - 6: proof of existence keeps the metadata and the history of the owners of claims.
+/// - 7: proof of existence indexes the claims of each account.
+/// - 8: `balances::transfer` keeps the sender alive, like `transfer_keep_alive`.
+pub const VERSIONS: &[RuntimeVersion] = &[
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 1 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 2 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 3 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 4 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 5 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 6 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 7 },
+    RuntimeVersion { spec_name: "rust-state-machine", spec_version: 8 },
+];
+
+impl support::NativeVersions for Runtime {
+    const DISPATCH: &'static [(u32, support::DispatchFn<Self>)] = &[(8, dispatch_v8)];
+}
+
+// From version 8, `balances::transfer` can no longer reap the sender.
+fn dispatch_v8(
+    runtime: &mut Runtime,
+    caller: support::Origin<types::AccountId>,
+    call: RuntimeCall,
+) -> DispatchResult {
+    let call = match call {
+        RuntimeCall::balances(balances::Call::transfer { to, amount }) => {
+            RuntimeCall::balances(balances::Call::transfer_keep_alive { to, amount })
+        }
+        call => call,
+    };
+    runtime.dispatch(caller, call)
+}
+
+impl system::Config for Runtime {
+    type AccountId = types::AccountId;
+    type BlockNumber = types::BlockNumber;
+    type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    const VERSIONS: &'static [RuntimeVersion] = VERSIONS;
+}
+
+impl balances::Config for Runtime {
+    type Balance = types::Balance;
+    const EXISTENTIAL_DEPOSIT: types::Balance = 5;
+}
+
+impl proof_of_existence::Config for Runtime {
+    type Content = types::Content;
+    type Hashing = support::hashing::Blake2_256;
+    type Currency = balances::Pallet<Self>;
+    type Time = timestamp::Pallet<Self>;
+    const CLAIM_DEPOSIT: types::Balance = 10;
+    const DESCRIPTION_LIMIT: u32 = 256;
+}
+
+impl assets::Config for Runtime {
+    type AssetId = types::AssetId;
+    type Balance = types::Balance;
+    const STRING_LIMIT: u32 = 32;
+}
+
+impl nfts::Config for Runtime {
+    type CollectionId = types::CollectionId;
+    type ItemId = types::ItemId;
+    type RuntimeEvent = RuntimeEvent;
+    const KEY_LIMIT: u32 = 32;
+    const VALUE_LIMIT: u32 = 256;
+}
+
+impl vesting::Config for Runtime {
+    type Currency = balances::Pallet<Self>;
+    const MIN_VESTED_TRANSFER: types::Balance = 10;
+    const MAX_VESTING_SCHEDULES: u32 = 8;
+}
+
+impl timestamp::Config for Runtime {
+    const MINIMUM_PERIOD: u64 = 3_000;
+}
+
+impl sudo::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type RuntimeEvent = RuntimeEvent;
+}
+
+impl utility::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type RuntimeEvent = RuntimeEvent;
+}
+
+impl multisig::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type Currency = balances::Pallet<Self>;
+    type RuntimeEvent = RuntimeEvent;
+    const DEPOSIT: types::Balance = 20;
+    const MAX_SIGNATORIES: u32 = 16;
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = ProxyType;
+    type Currency = balances::Pallet<Self>;
+    type RuntimeEvent = RuntimeEvent;
+    const PROXY_DEPOSIT: types::Balance = 10;
+    const MAX_PROXIES: u32 = 32;
+    const MAX_PENDING: u32 = 32;
+}
+
+impl scheduler::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type RuntimeEvent = RuntimeEvent;
+    const MAX_SCHEDULED_PER_BLOCK: u32 = 50;
+    const MAX_CALLS_PER_BLOCK: u32 = 10;
+}
+
+impl democracy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type Currency = balances::Pallet<Self>;
+    type RuntimeEvent = RuntimeEvent;
+    const MINIMUM_DEPOSIT: types::Balance = 10;
+    const LAUNCH_PERIOD: types::BlockNumber = 5;
+    const VOTING_PERIOD: types::BlockNumber = 5;
+    const ENACTMENT_PERIOD: types::BlockNumber = 2;
+    const VOTE_LOCKING_PERIOD: types::BlockNumber = 5;
+    const MAX_PROPOSALS: u32 = 32;
+}
+
+impl treasury::Config for Runtime {
+    type Currency = balances::Pallet<Self>;
+    type RuntimeEvent = RuntimeEvent;
+    const PROPOSAL_BOND: u32 = 5;
+    const PROPOSAL_BOND_MINIMUM: types::Balance = 10;
+    const SPEND_PERIOD: types::BlockNumber = 10;
+    const BURN: u32 = 50;
+    const MAX_APPROVALS: u32 = 32;
+}
+
+/// The fee of every signed extrinsic, paid even if its call fails.
+pub const TRANSACTION_FEE: types::Balance = 10;
+
+/// The part of `TRANSACTION_FEE` which goes to the treasury. The rest is burnt.
+pub const TREASURY_FEE: types::Balance = 8;
+
+impl ChargeTransaction<types::AccountId> for Runtime {
+    fn charge_transaction(&mut self, who: &types::AccountId) -> DispatchResult {
+        let fee = self.balances.withdraw(who, TRANSACTION_FEE, Reasons::Fee, false)?;
+        let (to_treasury, _burnt) = fee.split(TREASURY_FEE);
+        self.balances.resolve_creating(&treasury::Pallet::<Self>::account_id(), to_treasury);
+        Ok(())
+    }
+}
+
+/// This runtime compiled to wasm by the build script, or `None` if it was not built.
+#[cfg(not(target_arch = "wasm32"))]
+pub const WASM_BINARY: Option<&[u8]> = {
+    let code = include_bytes!(concat!(env!("OUT_DIR"), "/runtime.wasm"));
+    if code.is_empty() {
+        None
+    } else {
+        Some(code)
+    }
+};
+
+/// The fuel the wasm runtime may consume to execute a block, see `support::wasm`.
+#[cfg(not(target_arch = "wasm32"))]
+pub const BLOCK_FUEL: u64 = 100_000_000;
+
+/// How `Runtime::execute_block_with` executes blocks.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionStrategy {
+    /// Use this native runtime if it knows the runtime version of the chain, the wasm code
+    /// otherwise.
+    NativeElseWasm,
+    /// Always use the wasm code: the one set with `system::set_wasm_code`, or `WASM_BINARY`.
+    AlwaysWasm,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Runtime {
+    /// Execute a block with the runtime logic the chain is running, following `strategy`.
+    pub fn execute_block_with(
+        &mut self,
+        strategy: ExecutionStrategy,
+        block: types::Block,
+    ) -> Result<(), String> {
+        if strategy == ExecutionStrategy::NativeElseWasm && self.system.runtime_version().is_some()
+        {
+            return self.execute_block(block).map_err(String::from);
+        }
+        let code = match self.system.code() {
+            Some(code) => code,
+            None => WASM_BINARY.ok_or("the wasm runtime was not built")?.to_vec(),
+        };
+        support::wasm::WasmRuntime::new(&code)?.execute_block(&block.encode(), BLOCK_FUEL)
+    }
+}
+
+/// The entry points of this runtime when it is compiled to wasm, see `support::wasm`.
+#[cfg(target_arch = "wasm32")]
+mod wasm_api {
+    use super::{types, Runtime, VERSIONS};
+    use crate::support::{Decode, Encode};
+
+    /// Hand `output` over to the node, as `ptr << 32 | len`. The memory is never freed, since
+    /// every call runs in a fresh instance of the runtime.
+    fn output(output: Vec<u8>) -> u64 {
+        let output = output.leak();
+        (output.as_ptr() as u64) << 32 | output.len() as u64
+    }
+
+    /// Allocate `len` bytes for the node to write the input of an entry point.
+    #[no_mangle]
+    pub extern "C" fn alloc(len: u32) -> *mut u8 {
+        vec![0u8; len as usize].leak().as_mut_ptr()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn version(_ptr: *const u8, _len: u32) -> u64 {
+        output(VERSIONS.last().expect("no runtime version registered").encode())
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` bytes allocated with `alloc`.
+    #[no_mangle]
+    pub unsafe extern "C" fn execute_block(ptr: *const u8, len: u32) -> u64 {
+        let input = core::slice::from_raw_parts(ptr, len as usize);
+        let result =
+            types::Block::decode_all(input).and_then(|block| Runtime::new().execute_block(block));
+        output(result.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        types, ExecutionStrategy, ProxyType, Runtime, RuntimeCall, RuntimeEvent, BLOCK_FUEL,
+        VERSIONS, WASM_BINARY,
+    };
+    use crate::support::{
+        self, storage, testing, Decode, Dispatch, Encode, GetRuntimeMetadata, InstanceFilter,
+        OnRuntimeUpgrade, Origin,
+    };
+    use crate::{
+        assets, balances, democracy, multisig, nfts, proof_of_existence, proxy, scheduler, sudo,
+        system, timestamp, treasury, utility,
+    };
+
+    fn signed(who: &str) -> Origin<String> {
+        Origin::Signed(who.to_string())
+    }
+
+    // Execute the next block, with the timestamp inherent every block needs before `extrinsics`.
+    fn execute_next_block(
+        runtime: &mut Runtime,
+        extrinsics: Vec<types::Extrinsic>,
+    ) -> support::DispatchResult {
+        let block_number = runtime.system.block_number() + 1;
+        let set_time = types::Extrinsic {
+            caller: None,
+            call: RuntimeCall::timestamp(timestamp::Call::set { now: 6_000 * block_number as u64 }),
+        };
+        let header = support::Header { block_number };
+        let extrinsics = std::iter::once(set_time).chain(extrinsics).collect();
+        runtime.execute_block(types::Block { header, extrinsics })
+    }
+
+    #[test]
+    fn encode_runtime_call() {
+        let call =
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim_from_bytes {
+                content: b"hi".to_vec(),
+            });
+        assert_eq!(call.encode(), vec![2, 2, 2, 0, 0, 0, b'h', b'i']);
+    }
+
+    #[test]
+    fn runtime_metadata() {
+        let metadata = RuntimeCall::runtime_metadata();
+        let pallets = metadata.iter().map(|p| (p.index, p.name)).collect::<Vec<_>>();
+        assert_eq!(
+            pallets,
+            vec![
+                (0, "system"),
+                (1, "balances"),
+                (2, "proof_of_existence"),
+                (3, "assets"),
+                (4, "nfts"),
+                (5, "vesting"),
+                (6, "timestamp"),
+                (7, "sudo"),
+                (8, "utility"),
+                (9, "multisig"),
+                (10, "proxy"),
+                (11, "scheduler"),
+                (12, "democracy"),
+                (13, "treasury")
+            ]
+        );
+
+        let calls = metadata[2].calls.iter().map(|c| (c.index, c.name)).collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            vec![
+                (0, "create_claim"),
+                (1, "revoke_claim"),
+                (2, "create_claim_from_bytes"),
+                (3, "set_description"),
+                (4, "transfer_claim"),
+                (5, "approve_transfer"),
+                (6, "cancel_approval"),
+                (7, "transfer_approved_claim"),
+                (8, "set_expiry"),
+                (9, "anchor_batch")
+            ]
+        );
+    }
+
+    // A snapshot of a chain running the first version of the runtime, which stored zero balances
+    // and claims keyed by their content.
+    fn old_state() -> storage::State {
+        let mut state = storage::State::new();
+        storage::execute_with(&mut state, || {
+            let balances = storage::StorageMap::<String, u128>::new("Balances", "Balances");
+            balances.insert("alice".to_string(), 50);
+            balances.insert("bob".to_string(), 0);
+            balances.insert("charlie".to_string(), 0);
+            let claims = storage::StorageMap::<String, String>::new("ProofOfExistence", "Claims");
+            claims.insert("hi".to_string(), "alice".to_string());
+            storage::StorageValue::<u32>::new("System", "BlockNumber").put(7);
+            storage::StorageValue::<u32>::new("System", "SpecVersion").put(1);
+            storage::StorageValue::<u32>::new("System", "LastRuntimeUpgrade").put(1);
+        });
+        state
+    }
+
+    #[test]
+    fn migrations_keep_invariants() {
+        let mut state = old_state();
+        storage::execute_with(&mut state, || {
+            let mut runtime = Runtime::new();
+            assert_eq!(runtime.try_on_runtime_upgrade(), Ok(()));
+            assert_eq!(runtime.balances.balance(&"alice".to_string()), 50);
+            assert_eq!(runtime.system.block_number(), 7);
+            let claim = runtime.proof_of_existence.verify(b"hi");
+            assert_eq!(claim, Some(("alice".to_string(), 7)));
+        });
+    }
+
+    #[test]
+    fn events_last_one_block() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+        let create = RuntimeCall::nfts(nfts::Call::create { collection: 7, max_supply: None });
+        let extrinsic = types::Extrinsic { caller: Some("alice".to_string()), call: create };
+        assert_eq!(execute_next_block(&mut runtime, vec![extrinsic]), Ok(()));
+        let created =
+            RuntimeEvent::nfts(nfts::Event::Created { collection: 7, owner: "alice".to_string() });
+        assert_eq!(runtime.system.events(), vec![created.clone()]);
+        // Events are encoded after the index of their pallet.
+        assert_eq!(created.encode()[0], 4);
+        assert_eq!(RuntimeEvent::decode_all(&created.encode()), Ok(created));
+
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert!(runtime.system.events().is_empty());
+    }
+
+    #[test]
+    fn claims_expire_at_the_end_of_the_block() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+        let claim = support::hashing::blake2_256(b"hello");
+        let extrinsic = |call| types::Extrinsic { caller: Some("alice".to_string()), call };
+        let extrinsics = vec![
+            extrinsic(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim,
+            })),
+            extrinsic(RuntimeCall::proof_of_existence(proof_of_existence::Call::set_expiry {
+                claim,
+                expiry: Some(1),
+            })),
+        ];
+        assert_eq!(execute_next_block(&mut runtime, extrinsics), Ok(()));
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim), None);
+        assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+    }
+
+    #[test]
+    fn inherents() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+        let set_time = |now| types::Extrinsic {
+            caller: None,
+            call: RuntimeCall::timestamp(timestamp::Call::set { now }),
+        };
+        let create_claim = || types::Extrinsic {
+            caller: Some("alice".to_string()),
+            call: RuntimeCall::proof_of_existence(
+                proof_of_existence::Call::create_claim_from_bytes { content: b"hello".to_vec() },
+            ),
+        };
+        let block = |block_number, extrinsics| types::Block {
+            header: support::Header { block_number },
+            extrinsics,
+        };
+
+        assert_eq!(runtime.execute_block(block(1, vec![set_time(6_000), create_claim()])), Ok(()));
+        let claim = runtime.proof_of_existence.claim(&support::hashing::blake2_256(b"hello"));
+        assert_eq!(claim.unwrap().timestamp, Some(6_000));
+
+        // A failing inherent makes the block invalid, unlike other extrinsics. The chain then
+        // drops the state the block left.
+        assert_eq!(
+            runtime.execute_block(block(2, vec![set_time(7_000)])),
+            Err("timestamp must increment by at least the minimum period")
+        );
+        assert_eq!(
+            runtime.execute_block(block(3, vec![create_claim(), set_time(20_000)])),
+            Err("inherents must come before signed extrinsics")
+        );
+        let unsigned_claim = types::Extrinsic { caller: None, ..create_claim() };
+        assert_eq!(
+            runtime.execute_block(block(4, vec![unsigned_claim])),
+            Err("bad origin: expected signed")
+        );
+    }
+
+    #[test]
+    fn unpaid_extrinsics_leave_no_trace() {
+        let mut runtime = Runtime::new();
+        let [alice, dave] = ["alice", "dave"].map(String::from);
+        runtime.balances.set_balance(&alice, 100);
+        let transfer = |who: &str| types::Extrinsic {
+            caller: Some(who.to_string()),
+            call: RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 20,
+            }),
+        };
+
+        assert_eq!(
+            execute_next_block(&mut runtime, vec![transfer(&alice), transfer(&dave)]),
+            Ok(())
+        );
+        assert_eq!(runtime.system.nonce(&alice), 1);
+        // Dave can't pay the fee, so nothing is stored about him, not even his nonce.
+        assert_eq!(runtime.system.nonce(&dave), 0);
+        assert!(!storage::snapshot().keys().any(|key| key.ends_with(&dave.encode())));
+    }
+
+    #[test]
+    fn sudo() {
+        let mut runtime = Runtime::new();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        runtime.sudo.initialize_key(alice.clone());
+        let force_set_balance = |amount| {
+            Box::new(RuntimeCall::balances(balances::Call::force_set_balance {
+                who: bob.clone(),
+                amount,
+            }))
+        };
+        let sudo = |call| RuntimeCall::sudo(sudo::Call::sudo { call });
+
+        // Setting a balance needs the root origin, which only the sudo key can dispatch with.
+        assert_eq!(
+            runtime.dispatch(signed(&alice), *force_set_balance(100)),
+            Err("bad origin: expected root")
+        );
+        assert_eq!(
+            runtime.dispatch(signed(&bob), sudo(force_set_balance(100))),
+            Err("caller is not the sudo key")
+        );
+        assert_eq!(runtime.dispatch(signed(&alice), sudo(force_set_balance(100))), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 100);
+
+        // Bob pays alice, on behalf of bob.
+        let transfer = Box::new(RuntimeCall::balances(balances::Call::transfer {
+            to: alice.clone(),
+            amount: 30,
+        }));
+        let sudo_as = RuntimeCall::sudo(sudo::Call::sudo_as { who: bob.clone(), call: transfer });
+        assert_eq!(runtime.dispatch(signed(&alice), sudo_as), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 70);
+
+        // The sudo call succeeds even if the call it dispatches fails.
+        let overdraft = Box::new(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.clone(),
+            amount: 1_000,
+        }));
+        let sudo_as =
+            RuntimeCall::sudo(sudo::Call::sudo_as { who: alice.clone(), call: overdraft });
+        assert_eq!(runtime.dispatch(signed(&alice), sudo_as), Ok(()));
+
+        let set_key = RuntimeCall::sudo(sudo::Call::set_key { new: bob.clone() });
+        assert_eq!(runtime.dispatch(signed(&alice), set_key), Ok(()));
+        assert_eq!(runtime.sudo.key(), Some(bob.clone()));
+        assert_eq!(
+            runtime.dispatch(signed(&alice), sudo(force_set_balance(0))),
+            Err("caller is not the sudo key")
+        );
+
+        let events = runtime.system.events();
+        assert_eq!(
+            events,
+            vec![
+                RuntimeEvent::sudo(sudo::Event::Sudid { sudo_result: Ok(()) }),
+                RuntimeEvent::sudo(sudo::Event::SudoAsDone { sudo_result: Ok(()) }),
+                RuntimeEvent::sudo(sudo::Event::SudoAsDone {
+                    sudo_result: Err("Not enough funds.".to_string())
+                }),
+                RuntimeEvent::sudo(sudo::Event::KeyChanged { old: Some(alice), new: bob }),
+            ]
+        );
+        testing::assert_round_trip(events);
+    }
+
+    #[test]
+    fn batches() {
+        let mut runtime = Runtime::new();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        runtime.balances.set_balance(&alice, 100);
+        let transfer =
+            |amount| RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount });
+        let claim = |content: &[u8]| {
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim_from_bytes {
+                content: content.to_vec(),
+            })
+        };
+
+        // The batch stops at the failing transfer, but keeps the first one.
+        let batch = RuntimeCall::utility(utility::Call::batch {
+            calls: vec![transfer(10), transfer(1_000), transfer(10)],
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), batch), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+
+        // Nothing is kept from a failing `batch_all`, not even its events.
+        let batch_all = RuntimeCall::utility(utility::Call::batch_all {
+            calls: vec![transfer(10), claim(b"hello"), transfer(1_000)],
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), batch_all), Err("Not enough funds."));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        let hello = support::hashing::blake2_256(b"hello");
+        assert_eq!(runtime.proof_of_existence.get_claim(&hello), None);
+
+        let batch_all = RuntimeCall::utility(utility::Call::batch_all {
+            calls: vec![transfer(10), claim(b"hello")],
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), batch_all), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 20);
+        assert_eq!(runtime.proof_of_existence.get_claim(&hello), Some(alice.clone()));
+
+        let force_batch = RuntimeCall::utility(utility::Call::force_batch {
+            calls: vec![claim(b"hello"), transfer(10)],
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), force_batch), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 30);
+
+        let events = runtime.system.events();
+        assert_eq!(
+            events,
+            vec![
+                RuntimeEvent::utility(utility::Event::BatchInterrupted {
+                    index: 1,
+                    error: "Not enough funds.".to_string()
+                }),
+                RuntimeEvent::utility(utility::Event::BatchCompleted),
+                RuntimeEvent::utility(utility::Event::ItemFailed {
+                    error: "this content has already been claimed".to_string()
+                }),
+                RuntimeEvent::utility(utility::Event::ItemCompleted),
+                RuntimeEvent::utility(utility::Event::BatchCompletedWithErrors),
+            ]
+        );
+        testing::assert_round_trip(events);
+    }
+
+    #[test]
+    fn multisig() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+        for who in [&alice, &bob, &charlie] {
+            runtime.balances.set_balance(who, 100);
+        }
+        // The multisig account doesn't depend on the order of the signatories.
+        let shared = multisig::Pallet::<Runtime>::multi_account_id(
+            &[bob.clone(), alice.clone(), charlie.clone()],
+            2,
+        );
+        assert_eq!(
+            shared,
+            multisig::Pallet::<Runtime>::multi_account_id(
+                &[alice.clone(), bob.clone(), charlie.clone()],
+                2
+            )
+        );
+        assert_ne!(
+            shared,
+            multisig::Pallet::<Runtime>::multi_account_id(
+                &[alice.clone(), bob.clone(), charlie.clone()],
+                3
+            )
+        );
+        runtime.balances.set_balance(&shared, 50);
+
+        let call =
+            || RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 30 });
+        let call_hash = multisig::Pallet::<Runtime>::call_hash(&call());
+        let as_multi = |others: &[&String]| {
+            RuntimeCall::multisig(multisig::Call::as_multi {
+                threshold: 2,
+                other_signatories: others.iter().map(|who| who.to_string()).collect(),
+                call: Box::new(call()),
+            })
+        };
+
+        // Alice starts the operation, and holds the deposit until it is executed.
+        assert_eq!(
+            runtime.dispatch(
+                signed(&alice),
+                RuntimeCall::multisig(multisig::Call::approve_as_multi {
+                    threshold: 2,
+                    other_signatories: vec![bob.clone(), alice.clone()],
+                    call_hash,
+                })
+            ),
+            Err("signatories must be unique")
+        );
+        assert_eq!(
+            runtime.dispatch(
+                signed(&alice),
+                RuntimeCall::multisig(multisig::Call::approve_as_multi {
+                    threshold: 2,
+                    other_signatories: vec![charlie.clone(), bob.clone()],
+                    call_hash,
+                })
+            ),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+        assert_eq!(
+            runtime.dispatch(signed(&alice), as_multi(&[&bob, &charlie])),
+            Err("caller already approved the operation")
+        );
+        assert_eq!(runtime.balances.balance(&shared), 50);
+
+        // Bob's approval is the second one, so the call is dispatched from the multisig account.
+        assert_eq!(runtime.dispatch(signed(&bob), as_multi(&[&alice, &charlie])), Ok(()));
+        assert_eq!(runtime.balances.balance(&shared), 20);
+        assert_eq!(runtime.balances.balance(&charlie), 130);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.multisig.multisig(&shared, &call_hash), None);
+
+        // Only the signatory which started an operation can cancel it.
+        assert_eq!(runtime.dispatch(signed(&charlie), as_multi(&[&alice, &bob])), Ok(()));
+        let cancel = |others: Vec<String>| {
+            RuntimeCall::multisig(multisig::Call::cancel_as_multi {
+                threshold: 2,
+                other_signatories: others,
+                call_hash,
+            })
+        };
+        assert_eq!(
+            runtime.dispatch(signed(&bob), cancel(vec![alice.clone(), charlie.clone()])),
+            Err("only the signatory which started the operation can cancel it")
+        );
+        assert_eq!(
+            runtime.dispatch(signed(&charlie), cancel(vec![alice.clone(), bob.clone()])),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.reserved_balance(&charlie), 0);
+
+        let events = runtime.system.events();
+        assert_eq!(
+            events,
+            vec![
+                RuntimeEvent::multisig(multisig::Event::NewMultisig {
+                    approving: alice,
+                    multisig: shared.clone(),
+                    call_hash
+                }),
+                RuntimeEvent::multisig(multisig::Event::MultisigExecuted {
+                    approving: bob,
+                    multisig: shared.clone(),
+                    call_hash,
+                    result: Ok(())
+                }),
+                RuntimeEvent::multisig(multisig::Event::NewMultisig {
+                    approving: charlie.clone(),
+                    multisig: shared.clone(),
+                    call_hash
+                }),
+                RuntimeEvent::multisig(multisig::Event::MultisigCancelled {
+                    cancelling: charlie,
+                    multisig: shared,
+                    call_hash
+                }),
+            ]
+        );
+        testing::assert_round_trip(events);
+    }
+
+    #[test]
+    fn proxies() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+        runtime.balances.set_balance(&alice, 100);
+        let transfer =
+            || RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 10 });
+        let claim = || {
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim_from_bytes {
+                content: b"hello".to_vec(),
+            })
+        };
+        let add_proxy = |proxy_type, delay| {
+            RuntimeCall::proxy(proxy::Call::add_proxy { delegate: bob.clone(), proxy_type, delay })
+        };
+        let proxy = |call| {
+            RuntimeCall::proxy(proxy::Call::proxy {
+                real: alice.clone(),
+                force_proxy_type: None,
+                call: Box::new(call),
+            })
+        };
+
+        // Bob can only make claims on behalf of alice.
+        assert_eq!(
+            runtime.dispatch(signed(&bob), proxy(claim())),
+            Err("caller is not a proxy of the account")
+        );
+        assert_eq!(runtime.dispatch(signed(&alice), add_proxy(ProxyType::ClaimsOnly, 0)), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert_eq!(
+            runtime.dispatch(signed(&bob), proxy(transfer())),
+            Err("call is not allowed for this proxy type")
+        );
+        assert_eq!(runtime.dispatch(signed(&bob), proxy(claim())), Ok(()));
+        let hello = support::hashing::blake2_256(b"hello");
+        assert_eq!(runtime.proof_of_existence.get_claim(&hello), Some(alice.clone()));
+
+        // A non transfer proxy can't transfer funds, not even in a batch.
+        assert!(ProxyType::NonTransfer.filter(&claim()));
+        assert!(!ProxyType::NonTransfer.filter(&transfer()));
+        let batch = RuntimeCall::utility(utility::Call::batch { calls: vec![transfer()] });
+        assert!(!ProxyType::NonTransfer.filter(&batch));
+        assert!(ProxyType::BalancesOnly.filter(&transfer()));
+
+        // With a delay, the call must be announced, and can only be dispatched after the delay.
+        assert_eq!(runtime.dispatch(signed(&alice), add_proxy(ProxyType::Any, 2)), Ok(()));
+        let any = Some(ProxyType::Any);
+        let forced = RuntimeCall::proxy(proxy::Call::proxy {
+            real: alice.clone(),
+            force_proxy_type: any,
+            call: Box::new(transfer()),
+        });
+        assert_eq!(
+            runtime.dispatch(signed(&bob), forced),
+            Err("proxy must announce the call first")
+        );
+        let call_hash = proxy::Pallet::<Runtime>::call_hash(&transfer());
+        let announce = RuntimeCall::proxy(proxy::Call::announce { real: alice.clone(), call_hash });
+        assert_eq!(runtime.dispatch(signed(&bob), announce), Ok(()));
+        let announced = || {
+            RuntimeCall::proxy(proxy::Call::proxy_announced {
+                delegate: bob.clone(),
+                real: alice.clone(),
+                force_proxy_type: any,
+                call: Box::new(transfer()),
+            })
+        };
+        assert_eq!(
+            runtime.dispatch(signed(&charlie), announced()),
+            Err("announcement is not due yet")
+        );
+        let block_number = runtime.system.block_number();
+        for _ in 0..2 {
+            runtime.system.inc_block_number();
+        }
+        assert_eq!(runtime.dispatch(signed(&charlie), announced()), Ok(()));
+        assert_eq!(runtime.balances.balance(&charlie), 10);
+        assert_eq!(runtime.dispatch(signed(&charlie), announced()), Err("call was not announced"));
+
+        let remove_proxy = RuntimeCall::proxy(proxy::Call::remove_proxy {
+            delegate: bob.clone(),
+            proxy_type: ProxyType::ClaimsOnly,
+            delay: 0,
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), remove_proxy), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+        assert_eq!(runtime.proxy.proxies(&alice).len(), 1);
+
+        // A non transfer proxy can't schedule a transfer either.
+        let add_non_transfer = RuntimeCall::proxy(proxy::Call::add_proxy {
+            delegate: charlie.clone(),
+            proxy_type: ProxyType::NonTransfer,
+            delay: 0,
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), add_non_transfer), Ok(()));
+        let schedule = RuntimeCall::scheduler(scheduler::Call::schedule {
+            when: 10,
+            maybe_periodic: None,
+            call: Box::new(transfer()),
+        });
+        assert_eq!(
+            runtime.dispatch(signed(&charlie), proxy(schedule)),
+            Err("call is not allowed for this proxy type")
+        );
+        assert!(runtime.scheduler.agenda(10).is_empty());
+
+        // Nor transfer or approve a transfer of assets, items or claims.
+        let transfers = [
+            RuntimeCall::assets(assets::Call::approve_transfer {
+                id: 1,
+                delegate: bob.clone(),
+                amount: 10,
+            }),
+            RuntimeCall::nfts(nfts::Call::transfer { collection: 1, item: 1, to: bob.clone() }),
+            RuntimeCall::nfts(nfts::Call::approve_transfer {
+                collection: 1,
+                item: 1,
+                delegate: bob.clone(),
+            }),
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::transfer_claim {
+                claim: hello,
+                to: bob.clone(),
+            }),
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::approve_transfer {
+                claim: hello,
+                delegate: bob.clone(),
+            }),
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::transfer_approved_claim {
+                claim: hello,
+                to: bob.clone(),
+            }),
+        ];
+        for call in transfers {
+            assert_eq!(
+                runtime.dispatch(signed(&charlie), proxy(call)),
+                Err("call is not allowed for this proxy type")
+            );
+        }
+        assert_eq!(runtime.proof_of_existence.get_claim(&hello), Some(alice.clone()));
+
+        let events = runtime.system.events();
+        assert_eq!(
+            events[..4],
+            [
+                RuntimeEvent::proxy(proxy::Event::ProxyAdded {
+                    delegator: alice.clone(),
+                    delegatee: bob.clone(),
+                    proxy_type: ProxyType::ClaimsOnly,
+                    delay: 0
+                }),
+                RuntimeEvent::proxy(proxy::Event::ProxyExecuted { result: Ok(()) }),
+                RuntimeEvent::proxy(proxy::Event::ProxyAdded {
+                    delegator: alice.clone(),
+                    delegatee: bob.clone(),
+                    proxy_type: ProxyType::Any,
+                    delay: 2
+                }),
+                RuntimeEvent::proxy(proxy::Event::Announced { real: alice, proxy: bob, call_hash }),
+            ]
+        );
+        assert_eq!(runtime.system.block_number(), block_number + 2);
+        testing::assert_round_trip(events);
+    }
+
+    #[test]
+    fn scheduled_calls() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+        runtime.balances.set_balance(&alice, 200);
+        let transfer = |to: &String, amount| {
+            Box::new(RuntimeCall::balances(balances::Call::transfer { to: to.clone(), amount }))
+        };
+
+        // Alice pays bob at blocks 1, 3 and 5.
+        let schedule = RuntimeCall::scheduler(scheduler::Call::schedule {
+            when: 1,
+            maybe_periodic: Some((2, 3)),
+            call: transfer(&bob, 10),
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), schedule), Ok(()));
+
+        // Only alice can cancel the tasks dispatched as alice.
+        let schedule_named = RuntimeCall::scheduler(scheduler::Call::schedule_named {
+            id: b"pay".to_vec(),
+            when: 2,
+            maybe_periodic: None,
+            call: transfer(&bob, 100),
+        });
+        assert_eq!(runtime.dispatch(signed(&alice), schedule_named), Ok(()));
+        assert_eq!(runtime.scheduler.lookup(b"pay"), Some((2, 0)));
+        let cancel_named =
+            || RuntimeCall::scheduler(scheduler::Call::cancel_named { id: b"pay".to_vec() });
+        assert_eq!(
+            runtime.dispatch(signed(&bob), cancel_named()),
+            Err("origin cannot cancel the task")
+        );
+        assert_eq!(runtime.dispatch(signed(&alice), cancel_named()), Ok(()));
+        assert_eq!(runtime.scheduler.lookup(b"pay"), None);
+
+        // Tasks over the limit of a block are postponed to the next one.
+        for _ in 0..11 {
+            let schedule = RuntimeCall::scheduler(scheduler::Call::schedule {
+                when: 2,
+                maybe_periodic: None,
+                call: transfer(&charlie, 5),
+            });
+            assert_eq!(runtime.dispatch(signed(&alice), schedule), Ok(()));
+        }
+
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&charlie), 50);
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 20);
+        assert_eq!(runtime.balances.balance(&charlie), 55);
+        let events = runtime.system.events();
+        let dispatched = |task| {
+            RuntimeEvent::scheduler(scheduler::Event::Dispatched { task, id: None, result: Ok(()) })
+        };
+        assert_eq!(events, vec![dispatched((3, 0)), dispatched((3, 1))]);
+        testing::assert_round_trip(events);
+
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert!(runtime.scheduler.agenda(7).is_empty());
+    }
+
+    #[test]
+    fn referendums() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 100);
+        let vote = |aye, conviction, balance| democracy::Vote { aye, conviction, balance };
+
+        // Alice proposes to set the balance of charlie, and bob seconds the proposal.
+        let propose = |value| {
+            let proposal = RuntimeCall::balances(balances::Call::force_set_balance {
+                who: charlie.clone(),
+                amount: 1_000,
+            });
+            RuntimeCall::democracy(democracy::Call::propose { proposal: Box::new(proposal), value })
+        };
+        assert_eq!(
+            runtime.dispatch(signed(&alice), propose(5)),
+            Err("deposit is below the minimum")
+        );
+        assert_eq!(runtime.dispatch(signed(&alice), propose(10)), Ok(()));
+        let second = RuntimeCall::democracy(democracy::Call::second { proposal: 0 });
+        assert_eq!(runtime.dispatch(signed(&bob), second), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&bob), 10);
+
+        // The proposal is launched at the next block, and the deposits are returned.
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert!(runtime.democracy.public_props().is_empty());
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.balances.reserved_balance(&bob), 0);
+        assert_eq!(
+            runtime.system.events(),
+            vec![RuntimeEvent::democracy(democracy::Event::Started { ref_index: 0 })]
+        );
+
+        // Conviction outweighs bob's larger turnout, and locks alice's funds.
+        let alice_vote = vote(true, democracy::Conviction::Locked2x, 50);
+        let bob_vote = vote(false, democracy::Conviction::None, 100);
+        for (who, vote) in [(&alice, alice_vote), (&bob, bob_vote)] {
+            let vote = RuntimeCall::democracy(democracy::Call::vote { ref_index: 0, vote });
+            assert_eq!(runtime.dispatch(signed(who), vote), Ok(()));
+        }
+        let transfer =
+            || RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 60 });
+        assert_eq!(runtime.dispatch(signed(&alice), transfer()), Err("Balance is locked."));
+        match runtime.democracy.referendum_info(0) {
+            Some(democracy::ReferendumInfo::Ongoing { end, tally, .. }) => {
+                assert_eq!(end, 6);
+                assert_eq!(tally, democracy::Tally { ayes: 100, nays: 10, turnout: 150 });
+            }
+            info => panic!("unexpected referendum {info:?}"),
+        }
+
+        // The referendum passes at its end, and the proposal is dispatched as root two blocks
+        // later.
+        for _ in 2..=6 {
+            assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        }
+        assert_eq!(
+            runtime.democracy.referendum_info(0),
+            Some(democracy::ReferendumInfo::Finished { approved: true, end: 6 })
+        );
+        assert_eq!(
+            runtime.system.events(),
+            vec![RuntimeEvent::democracy(democracy::Event::Passed { ref_index: 0 })]
+        );
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        assert_eq!(runtime.balances.balance(&charlie), 1_000);
+        let events = runtime.system.events();
+        assert_eq!(
+            events,
+            vec![RuntimeEvent::democracy(democracy::Event::Executed {
+                ref_index: 0,
+                result: Ok(())
+            })]
+        );
+        testing::assert_round_trip(events);
+
+        // Bob lost, so his funds are unlocked with his vote. Alice's stay locked for two lock
+        // periods after the end of the referendum.
+        let remove_vote = || RuntimeCall::democracy(democracy::Call::remove_vote { ref_index: 0 });
+        assert_eq!(runtime.dispatch(signed(&bob), remove_vote()), Ok(()));
+        assert_eq!(runtime.democracy.voting_of(&bob), democracy::Voting::default());
+        assert_eq!(runtime.dispatch(signed(&alice), remove_vote()), Ok(()));
+        assert_eq!(runtime.democracy.voting_of(&alice).prior, Some((16, 50)));
+        assert_eq!(runtime.dispatch(signed(&alice), transfer()), Err("Balance is locked."));
+        while runtime.system.block_number() < 16 {
+            assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        }
+        let unlock = RuntimeCall::democracy(democracy::Call::unlock { target: alice.clone() });
+        assert_eq!(runtime.dispatch(signed(&bob), unlock), Ok(()));
+        assert_eq!(runtime.dispatch(signed(&alice), transfer()), Ok(()));
+    }
+
+    #[test]
+    fn treasury_spends() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie, dave] = ["alice", "bob", "charlie", "dave"].map(String::from);
+        let pot = treasury::Pallet::<Runtime>::account_id();
+        runtime.balances.set_balance(&alice, 1_000);
+        runtime.balances.set_balance(&bob, 100);
+
+        // The treasury gets most of the fee of every signed extrinsic, and the rest is burnt.
+        let transfer = |to: &String, amount| {
+            RuntimeCall::balances(balances::Call::transfer { to: to.clone(), amount })
+        };
+        let extrinsic =
+            types::Extrinsic { caller: Some(alice.clone()), call: transfer(&charlie, 100) };
+        assert_eq!(execute_next_block(&mut runtime, vec![extrinsic]), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 890);
+        assert_eq!(runtime.balances.balance(&pot), 8);
+        assert_eq!(runtime.balances.total_issuance(), 1_098);
+        assert_eq!(runtime.dispatch(signed(&alice), transfer(&pot, 200)), Ok(()));
+
+        // Proposals reserve a bond of 5% of their value, and at least 10.
+        let propose_spend = |value, beneficiary: &String| {
+            RuntimeCall::treasury(treasury::Call::propose_spend {
+                value,
+                beneficiary: beneficiary.clone(),
+            })
+        };
+        assert_eq!(
+            runtime.dispatch(signed(&bob), propose_spend(4, &charlie)),
+            Err("value is below the minimum balance")
+        );
+        assert_eq!(runtime.dispatch(signed(&bob), propose_spend(100, &charlie)), Ok(()));
+        assert_eq!(runtime.dispatch(signed(&bob), propose_spend(50, &dave)), Ok(()));
+        assert_eq!(runtime.dispatch(signed(&alice), propose_spend(500, &dave)), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&bob), 20);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 25);
+
+        // Only root approves and rejects proposals. Rejected proposals lose their bond to the pot.
+        let approve =
+            |proposal_id| RuntimeCall::treasury(treasury::Call::approve_proposal { proposal_id });
+        assert_eq!(runtime.dispatch(signed(&bob), approve(0)), Err("bad origin: expected root"));
+        assert_eq!(runtime.dispatch(Origin::Root, approve(0)), Ok(()));
+        assert_eq!(runtime.dispatch(Origin::Root, approve(0)), Err("proposal is already approved"));
+        assert_eq!(runtime.dispatch(Origin::Root, approve(2)), Ok(()));
+        let reject = RuntimeCall::treasury(treasury::Call::reject_proposal { proposal_id: 1 });
+        assert_eq!(runtime.dispatch(Origin::Root, reject), Ok(()));
+        assert_eq!(runtime.dispatch(Origin::Root, approve(1)), Err("proposal does not exist"));
+        assert_eq!(runtime.balances.reserved_balance(&bob), 10);
+        assert_eq!(runtime.balances.balance(&pot), 218);
+
+        // At the end of the spend period, the pot pays what it can afford, and burns half of the
+        // funds left. The proposal it can't afford waits for the next spend period.
+        while runtime.system.block_number() < 11 {
+            assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+        }
+        assert_eq!(runtime.balances.balance(&charlie), 200);
+        assert_eq!(runtime.balances.reserved_balance(&bob), 0);
+        assert_eq!(runtime.treasury.approvals(), vec![2]);
+        assert_eq!(runtime.treasury.pot(), 57);
+        let events = runtime.system.events();
+        assert_eq!(
+            events,
+            vec![
+                RuntimeEvent::treasury(treasury::Event::Spending { budget_remaining: 213 }),
+                RuntimeEvent::treasury(treasury::Event::Awarded {
+                    proposal_index: 0,
+                    award: 100,
+                    account: charlie,
+                }),
+                RuntimeEvent::treasury(treasury::Event::Burnt { burnt_funds: 56 }),
+                RuntimeEvent::treasury(treasury::Event::Rollover { rollover_balance: 57 }),
+            ]
+        );
+        testing::assert_round_trip(events);
+    }
+
+    #[test]
+    fn set_code_runs_migrations() {
+        let mut state = old_state();
+        storage::execute_with(&mut state, || {
+            let mut runtime = Runtime::new();
+            let zero_balances = storage::StorageMap::<String, u128>::new("Balances", "Balances");
+            let set_code = || RuntimeCall::system(system::Call::set_code { spec_version: 2 });
+
+            assert_eq!(
+                runtime.dispatch(signed("alice"), set_code()),
+                Err("bad origin: expected root")
+            );
+            assert_eq!(runtime.dispatch(Origin::Root, set_code()), Ok(()));
+            assert_eq!(runtime.system.runtime_version(), Some(VERSIONS[1]));
+
+            // Nothing is migrated until the next block is executed.
+            assert!(zero_balances.contains_key(&"bob".to_string()));
+            assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+            assert!(!zero_balances.contains_key(&"bob".to_string()));
+            assert_eq!(runtime.balances.balance(&"alice".to_string()), 50);
+        });
+    }
+
+    #[test]
+    fn set_code_switches_dispatch_logic() {
+        let mut state = storage::State::new();
+        storage::execute_with(&mut state, || {
+            storage::StorageValue::<u32>::new("System", "SpecVersion").put(7);
+            storage::StorageValue::<u32>::new("System", "LastRuntimeUpgrade").put(7);
+            let mut runtime = Runtime::new();
+            let (alice, bob) = ("alice".to_string(), "bob".to_string());
+            runtime.balances.set_balance(&alice, 100);
+            let transfer = |amount| {
+                RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount })
+            };
+
+            // Version 7 lets `transfer` reap the sender.
+            assert_eq!(runtime.dispatch_versioned(signed("alice"), transfer(50)), Ok(()));
+            assert_eq!(runtime.dispatch_versioned(signed("alice"), transfer(48)), Ok(()));
+            assert_eq!(runtime.balances.balance(&alice), 0);
+
+            let set_code = RuntimeCall::system(system::Call::set_code { spec_version: 8 });
+            assert_eq!(runtime.dispatch(Origin::Root, set_code), Ok(()));
+            // The new logic is only used from the next block.
+            assert_eq!(runtime.system.active_spec_version(), 7);
+            assert_eq!(execute_next_block(&mut runtime, vec![]), Ok(()));
+            assert_eq!(runtime.system.active_spec_version(), 8);
+
+            runtime.balances.set_balance(&bob, 100);
+            let transfer_back = || {
+                RuntimeCall::balances(balances::Call::transfer { to: alice.clone(), amount: 98 })
+            };
+            assert_eq!(
+                runtime.dispatch_versioned(signed("bob"), transfer_back()),
+                Err("Transfer would kill the account.")
+            );
+            // Calls dispatched by pallets use the same logic.
+            assert_eq!(
+                support::Dispatchable::dispatch(transfer_back(), signed("bob")),
+                Err("Transfer would kill the account.")
+            );
+            assert_eq!(runtime.balances.balance(&bob), 100);
+        });
+    }
+
+    // A block exercising every pallet, including a failing extrinsic.
+    fn block(block_number: u32) -> types::Block {
+        let extrinsic =
+            |caller: &str, call| types::Extrinsic { caller: Some(caller.to_string()), call };
+        types::Block {
+            header: support::Header { block_number },
+            extrinsics: vec![
+                types::Extrinsic {
+                    caller: None,
+                    call: RuntimeCall::timestamp(timestamp::Call::set {
+                        now: 6_000 * block_number as u64,
+                    }),
+                },
+                extrinsic(
+                    "alice",
+                    RuntimeCall::balances(balances::Call::transfer {
+                        to: "bob".to_string(),
+                        amount: 30,
+                    }),
+                ),
+                extrinsic(
+                    "bob",
+                    RuntimeCall::balances(balances::Call::transfer {
+                        to: "charlie".to_string(),
+                        amount: 31,
+                    }),
+                ),
+                extrinsic(
+                    "bob",
+                    RuntimeCall::proof_of_existence(
+                        proof_of_existence::Call::create_claim_from_bytes {
+                            content: b"hello".to_vec(),
+                        },
+                    ),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn wasm_runtime_version() {
+        let Some(code) = WASM_BINARY else { return };
+        let version = VERSIONS.last().unwrap();
+        assert_eq!(
+            support::runtime_version(code),
+            Some((version.spec_name.to_string(), version.spec_version))
+        );
+    }
+
+    #[test]
+    fn native_and_wasm_agree() {
+        if WASM_BINARY.is_none() {
+            return;
+        }
+        let execute = |strategy| {
+            let mut state = old_state();
+            storage::execute_with(&mut state, || {
+                let mut runtime = Runtime::new();
+                assert_eq!(runtime.execute_block_with(strategy, block(8)), Ok(()));
+                // Bob got 30, paid the fees of his two extrinsics, and reserved 10 for his claim.
+                assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+                assert_eq!(runtime.balances.reserved_balance(&"bob".to_string()), 10);
+                assert_eq!(
+                    runtime.execute_block_with(strategy, block(10)),
+                    Err("block number does not match what is expected".to_string())
+                );
+            });
+            state
+        };
+        assert_eq!(
+            execute(ExecutionStrategy::NativeElseWasm),
+            execute(ExecutionStrategy::AlwaysWasm)
+        );
+    }
+
+    #[test]
+    fn wasm_blocks_have_a_fuel_budget() {
+        let Some(code) = WASM_BINARY else { return };
+        let wasm = support::wasm::WasmRuntime::new(code).unwrap();
+        let execute = |fuel| {
+            let mut state = old_state();
+            storage::execute_with(&mut state, || wasm.execute_block(&block(8).encode(), fuel))
+        };
+        assert_eq!(execute(BLOCK_FUEL), Ok(()));
+        assert_eq!(execute(100_000), Err("the wasm runtime ran out of fuel".to_string()));
+    }
+
+    #[test]
+    fn set_wasm_code() {
+        let Some(code) = WASM_BINARY else { return };
+        let mut state = old_state();
+        storage::execute_with(&mut state, || {
+            let mut runtime = Runtime::new();
+            let set_wasm_code =
+                RuntimeCall::system(system::Call::set_wasm_code { code: code.to_vec() });
+            assert_eq!(runtime.dispatch(Origin::Root, set_wasm_code), Ok(()));
+            assert_eq!(runtime.system.spec_version(), VERSIONS.last().unwrap().spec_version);
+            assert_eq!(runtime.system.code().as_deref(), Some(code));
+
+            // A chain running a version unknown to this binary can only execute the wasm code.
+            let unknown = VERSIONS.last().unwrap().spec_version + 1;
+            storage::StorageValue::<u32>::new("System", "SpecVersion").put(unknown);
+            assert_eq!(runtime.system.runtime_version(), None);
+            assert_eq!(
+                runtime.execute_block_with(ExecutionStrategy::NativeElseWasm, block(8)),
+                Ok(())
+            );
+            assert_eq!(runtime.system.block_number(), 8);
+            // The wasm runtime ran the migrations.
+            let zero_balances = storage::StorageMap::<String, u128>::new("Balances", "Balances");
+            assert!(!zero_balances.contains_key(&"charlie".to_string()));
+            assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+        });
+    }
+}
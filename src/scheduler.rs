@@ -0,0 +1,511 @@
+use crate::support::{
+	storage::StorageMap, Decode, DispatchResult, Dispatchable, Encode, Hooks, OnRuntimeUpgrade,
+	Origin,
+};
+use num::traits::{CheckedAdd, One, Zero};
+
+pub trait Config: crate::system::Config {
+	/// The call type of the runtime, whose calls can be scheduled.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<EventOf<Self>> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The maximum number of tasks which can be scheduled at the same block.
+	const MAX_SCHEDULED_PER_BLOCK: u32;
+	/// The maximum number of tasks dispatched at the start of a block, which bounds the time
+	/// `on_initialize` takes. The other tasks are postponed to the next block with room for them.
+	const MAX_CALLS_PER_BLOCK: u32;
+}
+
+/// A task, identified by the block it is scheduled at and its index in the agenda of that block.
+pub type TaskAddress<BlockNumber> = (BlockNumber, u32);
+
+/// A call scheduled to be dispatched at a future block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduled<AccountId, BlockNumber> {
+	/// The name of the task, if it has one.
+	pub maybe_id: Option<Vec<u8>>,
+	/// The origin the call is dispatched with.
+	pub origin: Origin<AccountId>,
+	/// The encoded call. It is decoded when it is dispatched, since a runtime upgrade may change
+	/// the calls of the runtime in the meantime.
+	pub call: Vec<u8>,
+	/// If the task is periodic, its period and the number of times it is still dispatched.
+	pub maybe_periodic: Option<(BlockNumber, u32)>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for Scheduled<AccountId, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.maybe_id.encode_to(dest);
+		self.origin.encode_to(dest);
+		self.call.encode_to(dest);
+		self.maybe_periodic.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Decode for Scheduled<AccountId, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			maybe_id: Decode::decode(input)?,
+			origin: Decode::decode(input)?,
+			call: Decode::decode(input)?,
+			maybe_periodic: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A scheduled call, for a runtime `T`.
+pub type ScheduledOf<T> =
+	Scheduled<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// The tasks scheduled at each block. Cancelled tasks leave an empty slot, so that the index of
+/// the other tasks doesn't change.
+type Agenda<AccountId, BlockNumber> =
+	StorageMap<BlockNumber, Vec<Option<Scheduled<AccountId, BlockNumber>>>>;
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::BlockNumber>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<BlockNumber> {
+	/// A task was scheduled at the block `when`.
+	Scheduled { when: BlockNumber, index: u32 },
+	/// A task was cancelled.
+	Canceled { when: BlockNumber, index: u32 },
+	/// The call of a task was dispatched, with the given result.
+	Dispatched { task: TaskAddress<BlockNumber>, id: Option<Vec<u8>>, result: Result<(), String> },
+	/// The call of a task could not be decoded, so the task was dropped.
+	CallUnavailable { task: TaskAddress<BlockNumber>, id: Option<Vec<u8>> },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<BlockNumber: Encode> Encode for Event<BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::Scheduled { when, index } => (0u8, (when, index)).encode_to(dest),
+			Event::Canceled { when, index } => (1u8, (when, index)).encode_to(dest),
+			Event::Dispatched { task, id, result } => (2u8, (task, id, result)).encode_to(dest),
+			Event::CallUnavailable { task, id } => (3u8, (task, id)).encode_to(dest),
+		}
+	}
+}
+
+impl<BlockNumber: Decode> Decode for Event<BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => {
+				let (when, index) = Decode::decode(input)?;
+				Event::Scheduled { when, index }
+			},
+			1 => {
+				let (when, index) = Decode::decode(input)?;
+				Event::Canceled { when, index }
+			},
+			2 => {
+				let (task, id, result) = Decode::decode(input)?;
+				Event::Dispatched { task, id, result }
+			},
+			3 => {
+				let (task, id) = Decode::decode(input)?;
+				Event::CallUnavailable { task, id }
+			},
+			_ => return Err("invalid scheduler event"),
+		})
+	}
+}
+
+/// This is the Scheduler Module.
+/// It dispatches calls at future blocks, once or periodically, at the start of the block.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The tasks scheduled at each block.
+	agenda: Agenda<T::AccountId, T::BlockNumber>,
+	// The address of each named task.
+	lookup: StorageMap<Vec<u8>, TaskAddress<T::BlockNumber>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the scheduler module.
+	pub fn new() -> Self {
+		Self {
+			agenda: StorageMap::new("Scheduler", "Agenda"),
+			lookup: StorageMap::new("Scheduler", "Lookup"),
+		}
+	}
+
+	/// The tasks scheduled at the block `when`, with an empty slot for each cancelled task.
+	pub fn agenda(&self, when: T::BlockNumber) -> Vec<Option<ScheduledOf<T>>> {
+		self.agenda.get(&when).unwrap_or_default()
+	}
+
+	/// The address of the task named `id`, if it is scheduled.
+	pub fn lookup(&self, id: &[u8]) -> Option<TaskAddress<T::BlockNumber>> {
+		self.lookup.get(&id.to_vec())
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	// Whether the block `when` already has `MAX_SCHEDULED_PER_BLOCK` tasks.
+	fn is_full(&self, when: T::BlockNumber) -> bool {
+		self.agenda(when).iter().flatten().count() >= T::MAX_SCHEDULED_PER_BLOCK as usize
+	}
+
+	/// Add `task` to the agenda of the block `when`, returning its index, unless the agenda is
+	/// full.
+	fn place(&mut self, when: T::BlockNumber, task: ScheduledOf<T>) -> Result<u32, &'static str> {
+		if self.is_full(when) {
+			return Err("agenda is full");
+		}
+		let mut agenda = self.agenda(when);
+		let index = agenda.len() as u32;
+		if let Some(id) = &task.maybe_id {
+			self.lookup.insert(id.clone(), (when, index));
+		}
+		agenda.push(Some(task));
+		self.agenda.insert(when, agenda);
+		Ok(index)
+	}
+
+	/// Add `task` to the agenda of the first block from `when` on which is not full. The task is
+	/// dropped if there is none before the block numbers overflow.
+	fn place_from(&mut self, mut when: T::BlockNumber, task: ScheduledOf<T>) {
+		while self.is_full(when) {
+			let Some(next) = when.checked_add(&T::BlockNumber::one()) else { return };
+			when = next;
+		}
+		let _ = self.place(when, task);
+	}
+
+	fn do_schedule(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		maybe_id: Option<Vec<u8>>,
+		when: T::BlockNumber,
+		maybe_periodic: Option<(T::BlockNumber, u32)>,
+		call: T::RuntimeCall,
+	) -> DispatchResult {
+		// The task would be dispatched as an inherent, which only the block author can make.
+		if origin == Origin::None {
+			return Err("bad origin: expected signed or root");
+		}
+		if when <= crate::system::Pallet::<T>::new().block_number() {
+			return Err("scheduled block must be in the future");
+		}
+		if maybe_periodic.is_some_and(|(period, _)| period.is_zero()) {
+			return Err("period must not be zero");
+		}
+		// A task dispatched only once is not periodic.
+		let maybe_periodic = maybe_periodic.filter(|(_, count)| *count > 1);
+		if maybe_periodic.is_some_and(|(period, _)| when.checked_add(&period).is_none()) {
+			return Err("period overflows the block number");
+		}
+		if maybe_id.as_ref().is_some_and(|id| self.lookup.contains_key(id)) {
+			return Err("task name is already used");
+		}
+		let task = Scheduled { maybe_id, origin, call: call.encode(), maybe_periodic };
+		let index = self.place(when, task)?;
+		self.deposit_event(Event::Scheduled { when, index });
+		Ok(())
+	}
+
+	fn do_cancel(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		(when, index): TaskAddress<T::BlockNumber>,
+	) -> DispatchResult {
+		let mut agenda = self.agenda(when);
+		let slot = agenda.get_mut(index as usize).ok_or("task does not exist")?;
+		let task = slot.as_ref().ok_or("task does not exist")?;
+		// Only the origin a task is dispatched with can cancel it, besides `Root`.
+		if origin != Origin::Root && origin != task.origin {
+			return Err("origin cannot cancel the task");
+		}
+		if let Some(id) = &task.maybe_id {
+			self.lookup.remove(id);
+		}
+		*slot = None;
+		if agenda.iter().all(Option::is_none) {
+			self.agenda.remove(&when);
+		} else {
+			self.agenda.insert(when, agenda);
+		}
+		self.deposit_event(Event::Canceled { when, index });
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Schedule `call` to be dispatched with the `origin` of this call at the start of the block
+	/// `when`. A periodic call, with `maybe_periodic` set to its period and the number of times
+	/// it is dispatched, is then scheduled again every period.
+	// The calls are boxed, since they are part of the call type of the runtime.
+	#[allow(clippy::boxed_local)]
+	#[call_index(0)]
+	pub fn schedule(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		when: T::BlockNumber,
+		maybe_periodic: Option<(T::BlockNumber, u32)>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		self.do_schedule(origin, None, when, maybe_periodic, *call)
+	}
+
+	/// Cancel the task at `index` in the agenda of the block `when`. Only `Root` and the origin
+	/// the task is dispatched with can cancel it.
+	#[call_index(1)]
+	pub fn cancel(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		when: T::BlockNumber,
+		index: u32,
+	) -> DispatchResult {
+		self.do_cancel(origin, (when, index))
+	}
+
+	/// Like `schedule`, but the task is named `id`, so it can be cancelled by name.
+	#[allow(clippy::boxed_local)]
+	#[call_index(2)]
+	pub fn schedule_named(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		id: Vec<u8>,
+		when: T::BlockNumber,
+		maybe_periodic: Option<(T::BlockNumber, u32)>,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		self.do_schedule(origin, Some(id), when, maybe_periodic, *call)
+	}
+
+	/// Cancel the task named `id`.
+	#[call_index(3)]
+	pub fn cancel_named(&mut self, origin: Origin<T::AccountId>, id: Vec<u8>) -> DispatchResult {
+		let address = self.lookup(&id).ok_or("task does not exist")?;
+		self.do_cancel(origin, address)
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// Dispatch the tasks scheduled at `now`, up to `MAX_CALLS_PER_BLOCK` of them. The others are
+	/// postponed to the next block with room for them, after the tasks already scheduled there.
+	fn on_initialize(&mut self, now: T::BlockNumber) {
+		let Some(agenda) = self.agenda.remove(&now) else { return };
+		let next = now.checked_add(&T::BlockNumber::one());
+		let mut dispatched = 0;
+		for (index, task) in agenda.into_iter().enumerate() {
+			let Some(task) = task else { continue };
+			if let Some(id) = &task.maybe_id {
+				self.lookup.remove(id);
+			}
+			if dispatched >= T::MAX_CALLS_PER_BLOCK {
+				if let Some(next) = next {
+					self.place_from(next, task);
+				}
+				continue;
+			}
+			dispatched += 1;
+
+			let address = (now, index as u32);
+			let id = task.maybe_id.clone();
+			let Ok(call) = T::RuntimeCall::decode_all(&task.call) else {
+				self.deposit_event(Event::CallUnavailable { task: address, id });
+				continue;
+			};
+			let result = call.dispatch(task.origin.clone()).map_err(String::from);
+			self.deposit_event(Event::Dispatched { task: address, id, result });
+
+			// A periodic task ends early once its next occurrence overflows the block number.
+			if let Some((period, count)) = task.maybe_periodic {
+				let maybe_periodic = Some((period, count - 1)).filter(|(_, count)| *count > 1);
+				if let Some(when) = now.checked_add(&period) {
+					self.place_from(when, Scheduled { maybe_periodic, ..task });
+				}
+			}
+		}
+	}
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, EventOf, Pallet};
+	use crate::support::{
+		testing::{self, TestCall},
+		Encode, Hooks, Origin, RuntimeVersion,
+	};
+	use crate::system;
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type RuntimeEvent = EventOf<Self>;
+		const MAX_SCHEDULED_PER_BLOCK: u32 = 2;
+		const MAX_CALLS_PER_BLOCK: u32 = 1;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+
+	fn alice() -> Origin<u64> {
+		Origin::Signed(ALICE)
+	}
+
+	fn stored() -> Option<u32> {
+		testing::dispatched().last().map(|(value, _)| *value)
+	}
+
+	#[test]
+	fn schedule() {
+		let mut scheduler = Pallet::<TestConfig>::new();
+		let call = |value| Box::new(TestCall::new(value));
+		system::Pallet::<TestConfig>::new().inc_block_number();
+
+		assert_eq!(
+			scheduler.schedule(alice(), 1, None, call(1)),
+			Err("scheduled block must be in the future")
+		);
+		assert_eq!(
+			scheduler.schedule(alice(), 2, Some((0, 2)), call(1)),
+			Err("period must not be zero")
+		);
+		assert_eq!(scheduler.schedule_named(alice(), b"a".to_vec(), 2, None, call(1)), Ok(()));
+		assert_eq!(
+			scheduler.schedule_named(alice(), b"a".to_vec(), 3, None, call(1)),
+			Err("task name is already used")
+		);
+		// A periodic task dispatched once is not periodic.
+		assert_eq!(scheduler.schedule(alice(), 2, Some((1, 1)), call(2)), Ok(()));
+		assert_eq!(scheduler.agenda(2)[1].as_ref().unwrap().maybe_periodic, None);
+		assert_eq!(scheduler.schedule(alice(), 2, None, call(3)), Err("agenda is full"));
+		assert_eq!(
+			scheduler.schedule(Origin::None, 3, None, call(1)),
+			Err("bad origin: expected signed or root")
+		);
+		assert_eq!(
+			scheduler.schedule_named(Origin::None, b"b".to_vec(), 3, None, call(1)),
+			Err("bad origin: expected signed or root")
+		);
+	}
+
+	#[test]
+	fn periods_overflowing_the_block_number() {
+		let mut scheduler = Pallet::<TestConfig>::new();
+		let call = |value| Box::new(TestCall::new(value));
+		assert_eq!(
+			scheduler.schedule(alice(), u32::MAX, Some((1, 2)), call(1)),
+			Err("period overflows the block number")
+		);
+
+		// The task ends once its next occurrence can't be represented.
+		assert_eq!(scheduler.schedule(alice(), u32::MAX - 1, Some((1, 3)), call(2)), Ok(()));
+		scheduler.on_initialize(u32::MAX - 1);
+		scheduler.on_initialize(u32::MAX);
+		assert_eq!(testing::dispatched(), vec![(2, alice()), (2, alice())]);
+		assert!(scheduler.agenda(u32::MAX).is_empty());
+	}
+
+	#[test]
+	fn full_agendas_are_skipped() {
+		let mut scheduler = Pallet::<TestConfig>::new();
+		let call = |value| Box::new(TestCall::new(value));
+		assert_eq!(scheduler.schedule(alice(), 1, Some((1, 2)), call(1)), Ok(()));
+		assert_eq!(scheduler.schedule(alice(), 1, None, call(2)), Ok(()));
+		assert_eq!(scheduler.schedule(alice(), 2, None, call(3)), Ok(()));
+		assert_eq!(scheduler.schedule(alice(), 2, None, call(4)), Ok(()));
+
+		// Neither the periodic task nor the postponed one fit in the agenda of block 2.
+		scheduler.on_initialize(1);
+		let calls = |when| {
+			scheduler.agenda(when).into_iter().flatten().map(|task| task.call).collect::<Vec<_>>()
+		};
+		assert_eq!(calls(2), vec![3u32.encode(), 4u32.encode()]);
+		assert_eq!(calls(3), vec![1u32.encode(), 2u32.encode()]);
+	}
+
+	#[test]
+	fn cancel() {
+		let mut scheduler = Pallet::<TestConfig>::new();
+		assert_eq!(scheduler.cancel(alice(), 1, 0), Err("task does not exist"));
+		assert_eq!(scheduler.cancel_named(alice(), b"a".to_vec()), Err("task does not exist"));
+		assert_eq!(scheduler.schedule(alice(), 1, None, Box::new(TestCall::new(1))), Ok(()));
+		assert_eq!(
+			scheduler.schedule_named(alice(), b"a".to_vec(), 1, None, Box::new(TestCall::new(2))),
+			Ok(())
+		);
+
+		assert_eq!(
+			scheduler.cancel(Origin::Signed(BOB), 1, 0),
+			Err("origin cannot cancel the task")
+		);
+		assert_eq!(scheduler.cancel(Origin::None, 1, 0), Err("origin cannot cancel the task"));
+		assert_eq!(scheduler.cancel(alice(), 1, 2), Err("task does not exist"));
+		assert_eq!(scheduler.cancel(alice(), 1, 0), Ok(()));
+		assert_eq!(scheduler.cancel(alice(), 1, 0), Err("task does not exist"));
+		// Cancelling a named task by its address frees its name.
+		assert_eq!(scheduler.cancel(Origin::Root, 1, 1), Ok(()));
+		assert_eq!(scheduler.lookup(b"a"), None);
+		assert!(scheduler.agenda(1).is_empty());
+
+		let events = system::Pallet::<TestConfig>::new().events();
+		assert_eq!(
+			events[2..],
+			[Event::Canceled { when: 1, index: 0 }, Event::Canceled { when: 1, index: 1 }]
+		);
+	}
+
+	#[test]
+	fn dispatch() {
+		let mut scheduler = Pallet::<TestConfig>::new();
+		assert_eq!(scheduler.schedule(alice(), 1, None, Box::new(TestCall::new(1))), Ok(()));
+		assert_eq!(scheduler.schedule(alice(), 1, None, Box::new(TestCall::new(2))), Ok(()));
+		// The task can't be decoded anymore, as after a runtime upgrade.
+		let mut agenda = scheduler.agenda.get(&2).unwrap_or_default();
+		let undecodable = super::Scheduled {
+			maybe_id: None,
+			origin: alice(),
+			call: vec![0],
+			maybe_periodic: None,
+		};
+		agenda.push(Some(undecodable));
+		scheduler.agenda.insert(2, agenda);
+
+		// One task per block, the other one is postponed.
+		scheduler.on_initialize(1);
+		assert_eq!(stored(), Some(1));
+		assert_eq!(scheduler.agenda(2).len(), 2);
+		scheduler.on_initialize(2);
+		assert_eq!(stored(), Some(1));
+		scheduler.on_initialize(3);
+		assert_eq!(stored(), Some(2));
+
+		let events = system::Pallet::<TestConfig>::new().events();
+		assert_eq!(
+			events[2..],
+			[
+				Event::Dispatched { task: (1, 0), id: None, result: Ok(()) },
+				Event::CallUnavailable { task: (2, 0), id: None },
+				Event::Dispatched { task: (3, 0), id: None, result: Ok(()) },
+			]
+		);
+		testing::assert_round_trip(events);
+	}
+}
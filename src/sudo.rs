@@ -0,0 +1,219 @@
+use crate::support::{
+	storage::{self, StorageValue},
+	Decode, DispatchResult, Dispatchable, Encode, Hooks, OnRuntimeUpgrade, Origin,
+};
+
+pub trait Config: crate::system::Config {
+	/// The call type of the runtime, which the sudo key can dispatch with any origin.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<Event<Self::AccountId>>
+		+ Into<<Self as crate::system::Config>::RuntimeEvent>;
+}
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId> {
+	/// The sudo key dispatched a call as `Root`.
+	Sudid { sudo_result: Result<(), String> },
+	/// The sudo key changed from `old` to `new`.
+	KeyChanged { old: Option<AccountId>, new: AccountId },
+	/// The sudo key dispatched a call on behalf of another account.
+	SudoAsDone { sudo_result: Result<(), String> },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode> Encode for Event<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::Sudid { sudo_result } => (0u8, sudo_result).encode_to(dest),
+			Event::KeyChanged { old, new } => (1u8, (old, new)).encode_to(dest),
+			Event::SudoAsDone { sudo_result } => (2u8, sudo_result).encode_to(dest),
+		}
+	}
+}
+
+impl<AccountId: Decode> Decode for Event<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => Event::Sudid { sudo_result: Decode::decode(input)? },
+			1 => {
+				let (old, new) = Decode::decode(input)?;
+				Event::KeyChanged { old, new }
+			},
+			2 => Event::SudoAsDone { sudo_result: Decode::decode(input)? },
+			_ => return Err("invalid sudo event"),
+		})
+	}
+}
+
+/// This is the Sudo Module.
+/// It lets a single account, the sudo key, dispatch any call as `Root` or as any other account.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The sudo key, if any.
+	key: StorageValue<T::AccountId>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the sudo module.
+	pub fn new() -> Self {
+		Self { key: StorageValue::new("Sudo", "Key") }
+	}
+
+	/// Get the sudo key, if any.
+	pub fn key(&self) -> Option<T::AccountId> {
+		self.key.get()
+	}
+
+	/// Set the sudo key at genesis. Afterwards, only the key itself can change it, see `set_key`.
+	pub fn initialize_key(&mut self, key: T::AccountId) {
+		self.key.put(key);
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// Ensure `who` is the sudo key.
+	fn ensure_key(&self, who: &T::AccountId) -> DispatchResult {
+		if self.key().as_ref() != Some(who) {
+			return Err("caller is not the sudo key");
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `call` as `Root` on behalf of the `caller`, which must be the sudo key.
+	/// This call succeeds even if `call` fails: its changes are reverted and its result is recorded
+	/// in a `Sudid` event.
+	// The calls are boxed, since they are part of the call type of the runtime.
+	#[allow(clippy::boxed_local)]
+	#[call_index(0)]
+	pub fn sudo(&mut self, caller: T::AccountId, call: Box<T::RuntimeCall>) -> DispatchResult {
+		self.ensure_key(&caller)?;
+		let sudo_result =
+			storage::with_transaction(|| call.dispatch(Origin::Root)).map_err(String::from);
+		self.deposit_event(Event::Sudid { sudo_result });
+		Ok(())
+	}
+
+	/// Dispatch `call` as signed by `who` on behalf of the `caller`, which must be the sudo key.
+	/// This call succeeds even if `call` fails: its changes are reverted and its result is recorded
+	/// in a `SudoAsDone` event.
+	#[allow(clippy::boxed_local)]
+	#[call_index(1)]
+	pub fn sudo_as(
+		&mut self,
+		caller: T::AccountId,
+		who: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		self.ensure_key(&caller)?;
+		let sudo_result =
+			storage::with_transaction(|| call.dispatch(Origin::Signed(who))).map_err(String::from);
+		self.deposit_event(Event::SudoAsDone { sudo_result });
+		Ok(())
+	}
+
+	/// Make `new` the sudo key, on behalf of the `caller`, which must be the current key.
+	#[call_index(2)]
+	pub fn set_key(&mut self, caller: T::AccountId, new: T::AccountId) -> DispatchResult {
+		self.ensure_key(&caller)?;
+		let old = self.key.get();
+		self.key.put(new.clone());
+		self.deposit_event(Event::KeyChanged { old, new });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, Event, EventOf, Pallet};
+	use crate::support::{
+		testing::{self, TestCall},
+		Origin, RuntimeVersion,
+	};
+	use crate::system;
+
+	struct TestConfig;
+	impl Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type RuntimeEvent = EventOf<Self>;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+
+	fn events() -> Vec<EventOf<TestConfig>> {
+		system::Pallet::<TestConfig>::new().events()
+	}
+
+	#[test]
+	fn only_the_key_dispatches() {
+		let mut sudo = Pallet::<TestConfig>::new();
+		assert_eq!(sudo.sudo(ALICE, Box::new(TestCall::new(1))), Err("caller is not the sudo key"));
+		assert_eq!(
+			sudo.sudo_as(ALICE, BOB, Box::new(TestCall::new(1))),
+			Err("caller is not the sudo key")
+		);
+		assert_eq!(sudo.set_key(ALICE, ALICE), Err("caller is not the sudo key"));
+
+		sudo.initialize_key(ALICE);
+		assert_eq!(sudo.sudo(BOB, Box::new(TestCall::new(1))), Err("caller is not the sudo key"));
+		assert_eq!(sudo.set_key(ALICE, BOB), Ok(()));
+		assert_eq!(sudo.sudo(ALICE, Box::new(TestCall::new(1))), Err("caller is not the sudo key"));
+		assert_eq!(testing::dispatched(), vec![]);
+		assert_eq!(events(), vec![Event::KeyChanged { old: Some(ALICE), new: BOB }]);
+	}
+
+	#[test]
+	fn failing_calls_are_reverted() {
+		let mut sudo = Pallet::<TestConfig>::new();
+		sudo.initialize_key(ALICE);
+
+		assert_eq!(sudo.sudo(ALICE, Box::new(TestCall::new(1))), Ok(()));
+		assert_eq!(sudo.sudo_as(ALICE, BOB, Box::new(TestCall::new(2))), Ok(()));
+		let dispatched = vec![(1, Origin::Root), (2, Origin::Signed(BOB))];
+		assert_eq!(testing::dispatched(), dispatched);
+
+		// The sudo calls succeed, but what the failing calls stored is reverted.
+		assert_eq!(sudo.sudo(ALICE, Box::new(TestCall::new(0))), Ok(()));
+		assert_eq!(sudo.sudo_as(ALICE, BOB, Box::new(TestCall::new(0))), Ok(()));
+		assert_eq!(testing::dispatched(), dispatched);
+		assert_eq!(
+			events(),
+			vec![
+				Event::Sudid { sudo_result: Ok(()) },
+				Event::SudoAsDone { sudo_result: Ok(()) },
+				Event::Sudid { sudo_result: Err("zero".to_string()) },
+				Event::SudoAsDone { sudo_result: Err("zero".to_string()) },
+			]
+		);
+		testing::assert_round_trip(events());
+	}
+}
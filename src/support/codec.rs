@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+
+/// A trait for types which can be encoded into a stable sequence of bytes.
+///
+/// The format is deliberately simple: integers are fixed width little endian, and anything with a
+/// variable length (strings, vectors) is prefixed with its length as a `u32`.
+pub trait Encode {
+	/// Append the encoded representation of `self` to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Encode `self` into a new vector of bytes.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+/// A trait for types which can be decoded from the bytes produced by `Encode`.
+pub trait Decode: Sized {
+	/// Decode a value from the start of `input`, advancing `input` past the bytes that were read.
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str>;
+
+	/// Decode a value which must take up all of `input`.
+	fn decode_all(mut input: &[u8]) -> Result<Self, &'static str> {
+		let value = Self::decode(&mut input)?;
+		if !input.is_empty() {
+			return Err("input has trailing bytes");
+		}
+		Ok(value)
+	}
+}
+
+/// Split the first `len` bytes off `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], &'static str> {
+	if input.len() < len {
+		return Err("not enough data to decode");
+	}
+	let (head, tail) = input.split_at(len);
+	*input = tail;
+	Ok(head)
+}
+
+macro_rules! impl_codec_for_int {
+	($($t:ty),*) => {
+		$(
+			impl Encode for $t {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					dest.extend_from_slice(&self.to_le_bytes());
+				}
+			}
+
+			impl Decode for $t {
+				fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+					let bytes = take(input, core::mem::size_of::<$t>())?;
+					Ok(<$t>::from_le_bytes(bytes.try_into().expect("length was checked by take")))
+				}
+			}
+		)*
+	};
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+impl Decode for bool {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err("invalid bool"),
+		}
+	}
+}
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_bytes().encode_to(dest);
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest);
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		String::from_utf8(Vec::<u8>::decode(input)?).map_err(|_| "invalid utf8 string")
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+/// Boxes are encoded like their content. Calls box the calls they contain, see `sudo`.
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Box::new(T::decode(input)?))
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_slice().encode_to(dest);
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let len = u32::decode(input)? as usize;
+		// Don't trust the length to reserve memory, the input may be malicious.
+		let mut items = Vec::with_capacity(len.min(input.len()));
+		for _ in 0..len {
+			items.push(T::decode(input)?);
+		}
+		Ok(items)
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => dest.push(0),
+			Some(value) => {
+				dest.push(1);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(None),
+			1 => Ok(Some(T::decode(input)?)),
+			_ => Err("invalid option"),
+		}
+	}
+}
+
+/// Arrays have a fixed length, so it is not encoded.
+impl<T: Encode, const N: usize> Encode for [T; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let items = (0..N).map(|_| T::decode(input)).collect::<Result<Vec<_>, _>>()?;
+		Ok(items.try_into().unwrap_or_else(|_| unreachable!("exactly N items were decoded")))
+	}
+}
+
+/// Maps are encoded as a vector of their entries, ordered by key.
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		for entry in self {
+			entry.encode_to(dest);
+		}
+	}
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Vec::<(K, V)>::decode(input)?.into_iter().collect())
+	}
+}
+
+impl Encode for () {
+	fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+	fn decode(_input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(())
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok((A::decode(input)?, B::decode(input)?))
+	}
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+		self.2.encode_to(dest);
+	}
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok((A::decode(input)?, B::decode(input)?, C::decode(input)?))
+	}
+}
+
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Ok(value) => {
+				dest.push(0);
+				value.encode_to(dest);
+			},
+			Err(error) => {
+				dest.push(1);
+				error.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode, E: Decode> Decode for Result<T, E> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Ok(T::decode(input)?)),
+			1 => Ok(Err(E::decode(input)?)),
+			_ => Err("invalid result"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use super::{Decode, Encode};
+
+	#[test]
+	fn roundtrip() {
+		let (a, b, c, d) = (42u32, "hello".to_string(), vec![Some(1u128), None], true);
+
+		let mut encoded = a.encode();
+		b.encode_to(&mut encoded);
+		c.encode_to(&mut encoded);
+		d.encode_to(&mut encoded);
+
+		let input = &mut &encoded[..];
+		assert_eq!(u32::decode(input), Ok(a));
+		assert_eq!(String::decode(input), Ok(b));
+		assert_eq!(Vec::<Option<u128>>::decode(input), Ok(c));
+		assert_eq!(bool::decode(input), Ok(d));
+		assert!(input.is_empty());
+
+		let result: Result<(u8, u64), String> = Err("oops".to_string());
+		assert_eq!(Result::decode_all(&result.encode()), Ok(result));
+		let ok: Result<(u8, u64), &str> = Ok((1, 2));
+		assert_eq!(Result::<(u8, u64), String>::decode_all(&ok.encode()), Ok(Ok((1, 2))));
+
+		assert_eq!(<(u8, bool, u16)>::decode_all(&[1, 1, 2, 0]), Ok((1, true, 2)));
+		assert_eq!([1u16, 2].encode(), vec![1, 0, 2, 0]);
+		assert_eq!(<[u16; 2]>::decode_all(&[1, 0, 2, 0]), Ok([1, 2]));
+		let map = BTreeMap::from([(2u8, "b".to_string()), (1, "a".to_string())]);
+		assert_eq!(BTreeMap::decode_all(&map.encode()), Ok(map));
+		assert_eq!(Box::<u16>::decode_all(&Box::new(1u16).encode()), Ok(Box::new(1)));
+	}
+
+	#[test]
+	fn decode_errors() {
+		assert_eq!(u32::decode(&mut &[1, 2, 3][..]), Err("not enough data to decode"));
+		assert_eq!(bool::decode(&mut &[2][..]), Err("invalid bool"));
+		assert_eq!(u8::decode_all(&[1, 2]), Err("input has trailing bytes"));
+		assert_eq!(Vec::<u8>::decode(&mut &[5, 0, 0, 0, 1][..]), Err("not enough data to decode"));
+	}
+}
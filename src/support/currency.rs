@@ -0,0 +1,170 @@
+//! Traits for pallets holding the funds of accounts, so that other pallets can use them without
+//! depending on a concrete pallet.
+//!
+//! A pallet needing funds declares an associated type in its `Config`, e.g.
+//! `type Currency: ReservableCurrency<Self::AccountId>`, which the runtime sets to its balances
+//! pallet. Like any pallet, the currency only holds handles to the global storage, so the pallet
+//! using it creates its own instance with `Default`.
+
+use core::fmt::Debug;
+
+use num::traits::{Saturating, Zero};
+
+use super::{Decode, DispatchResult, Encode};
+
+/// The name of a lock, so that the pallet which set it can change or remove it.
+pub type LockIdentifier = [u8; 8];
+
+/// What locked funds can't be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reasons {
+	/// Paying transaction fees.
+	Fee,
+	/// Anything but paying transaction fees, e.g. transfers and reserves.
+	Misc,
+	/// Anything.
+	All,
+}
+
+impl Reasons {
+	/// Whether a lock for `self` prevents using funds for `other`.
+	pub fn intersects(self, other: Reasons) -> bool {
+		self == Reasons::All || other == Reasons::All || self == other
+	}
+
+	/// The reasons covering both `self` and `other`.
+	pub fn union(self, other: Reasons) -> Reasons {
+		if self == other {
+			self
+		} else {
+			Reasons::All
+		}
+	}
+}
+
+impl Encode for Reasons {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+impl Decode for Reasons {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Reasons::Fee),
+			1 => Ok(Reasons::Misc),
+			2 => Ok(Reasons::All),
+			_ => Err("invalid lock reasons"),
+		}
+	}
+}
+
+/// Where funds moved by `repatriate_reserved` end up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStatus {
+	/// In the balance of the beneficiary, which it can use.
+	Free,
+	/// In the reserved balance of the beneficiary.
+	Reserved,
+}
+
+/// Funds held by accounts.
+pub trait Currency<AccountId>: Default {
+	/// The type used to store amounts of funds.
+	type Balance: Copy + PartialOrd + Zero + Saturating + Encode + Decode + Debug;
+
+	/// All the funds of `who`, reserved funds included.
+	fn total_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// The funds of `who` which are not reserved. Some of them may be locked.
+	fn free_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// The sum of the funds of all accounts.
+	fn total_issuance(&self) -> Self::Balance;
+
+	/// The minimum amount of funds an account must hold to exist.
+	fn minimum_balance(&self) -> Self::Balance;
+
+	/// Transfer `value` from `from` to `to`. If `from` is left with less than the minimum balance,
+	/// this fails when `keep_alive` is set, otherwise `from` is reaped.
+	fn transfer(
+		&mut self,
+		from: &AccountId,
+		to: &AccountId,
+		value: Self::Balance,
+		keep_alive: bool,
+	) -> DispatchResult;
+
+	/// Create `value` of new funds in the account `who`. Returns the funds created, which are
+	/// zero if `who` would be left with less than the minimum balance.
+	fn deposit_creating(&mut self, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Destroy `value` of the free funds of `who` which are not locked for `reasons`.
+	fn withdraw(
+		&mut self,
+		who: &AccountId,
+		value: Self::Balance,
+		reasons: Reasons,
+		keep_alive: bool,
+	) -> DispatchResult;
+
+	/// Destroy as much as possible of `value` of the free funds of `who`, even if they are
+	/// locked. Returns what could not be destroyed.
+	fn slash(&mut self, who: &AccountId, value: Self::Balance) -> Self::Balance;
+}
+
+/// Funds which can be set aside, e.g. as a deposit. Reserved funds still belong to the account,
+/// but it can't use them until they are unreserved.
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+	/// The reserved funds of `who`.
+	fn reserved_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Whether `value` of the free funds of `who` can be reserved.
+	fn can_reserve(&self, who: &AccountId, value: Self::Balance) -> bool;
+
+	/// Reserve `value` of the free funds of `who`.
+	fn reserve(&mut self, who: &AccountId, value: Self::Balance) -> DispatchResult;
+
+	/// Unreserve as much as possible of `value` of the reserved funds of `who`. Returns what could
+	/// not be unreserved.
+	fn unreserve(&mut self, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Destroy as much as possible of `value` of the reserved funds of `who`. Returns what could
+	/// not be destroyed.
+	fn slash_reserved(&mut self, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Move as much as possible of `value` of the reserved funds of `slashed` to `beneficiary`,
+	/// with the given `status`. Returns what could not be moved.
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Self::Balance,
+		status: BalanceStatus,
+	) -> Result<Self::Balance, &'static str>;
+}
+
+/// Funds which can be locked. Locked funds stay free, but can't be used for some reasons. Locks
+/// are named, and overlap instead of adding up.
+pub trait LockableCurrency<AccountId>: Currency<AccountId> {
+	/// Lock `amount` of the funds of `who` for `reasons`, replacing the lock named `id`.
+	fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &AccountId,
+		amount: Self::Balance,
+		reasons: Reasons,
+	);
+
+	/// Like `set_lock`, but the lock named `id` keeps its amount and reasons if they are bigger.
+	fn extend_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &AccountId,
+		amount: Self::Balance,
+		reasons: Reasons,
+	);
+
+	/// Remove the lock named `id` from the funds of `who`.
+	fn remove_lock(&mut self, id: LockIdentifier, who: &AccountId);
+}
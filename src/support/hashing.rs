@@ -0,0 +1,278 @@
+//! Hash functions available to the runtime.
+//!
+//! Hashing is costly to interpret, so a wasm runtime asks the node executing it to hash data with
+//! its native implementation, see `host`.
+
+/// A hash function, so that pallets can let the runtime choose which one they use.
+pub trait Hasher {
+	/// The type of the digests.
+	type Output;
+
+	/// Hash `data`.
+	fn hash(data: &[u8]) -> Self::Output;
+}
+
+/// The 256-bit Blake2b hash function, see `blake2_256`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake2_256;
+
+impl Hasher for Blake2_256 {
+	type Output = [u8; 32];
+
+	fn hash(data: &[u8]) -> [u8; 32] {
+		blake2_256(data)
+	}
+}
+
+/// The SHA-256 hash function, see `sha2_256`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha2_256;
+
+impl Hasher for Sha2_256 {
+	type Output = [u8; 32];
+
+	fn hash(data: &[u8]) -> [u8; 32] {
+		sha2_256(data)
+	}
+}
+
+/// The 256-bit Blake2b hash of `data`.
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+	#[cfg(not(target_arch = "wasm32"))]
+	return blake2b::hash_256(data);
+	#[cfg(target_arch = "wasm32")]
+	return super::host::blake2_256(data);
+}
+
+/// The SHA-256 hash of `data`.
+pub fn sha2_256(data: &[u8]) -> [u8; 32] {
+	#[cfg(not(target_arch = "wasm32"))]
+	return sha256::hash(data);
+	#[cfg(target_arch = "wasm32")]
+	return super::host::sha2_256(data);
+}
+
+/// A pure Rust implementation of Blake2b (RFC 7693), without a key.
+#[cfg(not(target_arch = "wasm32"))]
+mod blake2b {
+	const IV: [u64; 8] = [
+		0x6a09e667f3bcc908,
+		0xbb67ae8584caa73b,
+		0x3c6ef372fe94f82b,
+		0xa54ff53a5f1d36f1,
+		0x510e527fade682d1,
+		0x9b05688c2b3e6c1f,
+		0x1f83d9abfb41bd6b,
+		0x5be0cd19137e2179,
+	];
+
+	const SIGMA: [[usize; 16]; 10] = [
+		[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+		[14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+		[11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+		[7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+		[9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+		[2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+		[12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+		[13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+		[6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+		[10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+	];
+
+	const BLOCK_LEN: usize = 128;
+
+	/// The mixing function, applied to the columns then the diagonals of the working vector.
+	fn g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+		v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+		v[d] = (v[d] ^ v[a]).rotate_right(32);
+		v[c] = v[c].wrapping_add(v[d]);
+		v[b] = (v[b] ^ v[c]).rotate_right(24);
+		v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+		v[d] = (v[d] ^ v[a]).rotate_right(16);
+		v[c] = v[c].wrapping_add(v[d]);
+		v[b] = (v[b] ^ v[c]).rotate_right(63);
+	}
+
+	/// Compress a `block`, given the number of bytes hashed so far including it.
+	fn compress(h: &mut [u64; 8], block: &[u8; BLOCK_LEN], counter: u128, last: bool) {
+		let mut m = [0u64; 16];
+		for (word, bytes) in m.iter_mut().zip(block.chunks_exact(8)) {
+			*word = u64::from_le_bytes(bytes.try_into().expect("chunks are 8 bytes long"));
+		}
+
+		let mut v = [0u64; 16];
+		v[..8].copy_from_slice(h);
+		v[8..].copy_from_slice(&IV);
+		v[12] ^= counter as u64;
+		v[13] ^= (counter >> 64) as u64;
+		if last {
+			v[14] = !v[14];
+		}
+
+		for round in 0..12 {
+			let s = &SIGMA[round % 10];
+			g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+			g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+			g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+			g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+			g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+			g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+			g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+			g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+		}
+
+		for i in 0..8 {
+			h[i] ^= v[i] ^ v[i + 8];
+		}
+	}
+
+	pub fn hash_256(data: &[u8]) -> [u8; 32] {
+		let mut h = IV;
+		// Parameter block: 32 bytes digest, no key, fanout and depth of 1.
+		h[0] ^= 0x0101_0000 ^ 32;
+
+		// The last block is always compressed separately, even when `data` is empty.
+		let full_blocks = data.len().saturating_sub(1) / BLOCK_LEN;
+		for (i, chunk) in data.chunks_exact(BLOCK_LEN).take(full_blocks).enumerate() {
+			let block = chunk.try_into().expect("chunks are a block long");
+			compress(&mut h, block, ((i + 1) * BLOCK_LEN) as u128, false);
+		}
+		let rest = &data[full_blocks * BLOCK_LEN..];
+		let mut block = [0u8; BLOCK_LEN];
+		block[..rest.len()].copy_from_slice(rest);
+		compress(&mut h, &block, data.len() as u128, true);
+
+		let mut out = [0u8; 32];
+		for (bytes, word) in out.chunks_exact_mut(8).zip(h) {
+			bytes.copy_from_slice(&word.to_le_bytes());
+		}
+		out
+	}
+}
+
+/// A pure Rust implementation of SHA-256 (FIPS 180-4).
+#[cfg(not(target_arch = "wasm32"))]
+mod sha256 {
+	const H0: [u32; 8] = [
+		0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+		0x5be0cd19,
+	];
+
+	const K: [u32; 64] = [
+		0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+		0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+		0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+		0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+		0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+		0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+		0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+		0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+		0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+		0xc67178f2,
+	];
+
+	const BLOCK_LEN: usize = 64;
+
+	/// Compress a 64 bytes `block` into the state `h`.
+	fn compress(h: &mut [u32; 8], block: &[u8]) {
+		let mut w = [0u32; 64];
+		for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+			*word = u32::from_be_bytes(bytes.try_into().expect("chunks are 4 bytes long"));
+		}
+		for i in 16..64 {
+			let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+			let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+			w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+		}
+
+		let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = *h;
+		for i in 0..64 {
+			let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+			let ch = (e & f) ^ (!e & g);
+			let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+			let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+			let maj = (a & b) ^ (a & c) ^ (b & c);
+			let t2 = s0.wrapping_add(maj);
+			hh = g;
+			g = f;
+			f = e;
+			e = d.wrapping_add(t1);
+			d = c;
+			c = b;
+			b = a;
+			a = t1.wrapping_add(t2);
+		}
+
+		for (word, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+			*word = word.wrapping_add(value);
+		}
+	}
+
+	pub fn hash(data: &[u8]) -> [u8; 32] {
+		let mut h = H0;
+
+		// The data is padded with a one bit, zeros, and its length in bits, to a whole number of
+		// blocks.
+		let mut padded = data.to_vec();
+		padded.push(0x80);
+		while padded.len() % BLOCK_LEN != BLOCK_LEN - 8 {
+			padded.push(0);
+		}
+		padded.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+		for block in padded.chunks_exact(BLOCK_LEN) {
+			compress(&mut h, block);
+		}
+
+		let mut out = [0u8; 32];
+		for (bytes, word) in out.chunks_exact_mut(4).zip(h) {
+			bytes.copy_from_slice(&word.to_be_bytes());
+		}
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{blake2_256, sha2_256};
+
+	fn hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+	}
+
+	#[test]
+	fn blake2_256_vectors() {
+		assert_eq!(
+			hex(&blake2_256(b"")),
+			"0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+		);
+		assert_eq!(
+			hex(&blake2_256(b"abc")),
+			"bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+		);
+		// Ends exactly on a block boundary.
+		assert_eq!(
+			hex(&blake2_256(&[7; 256])),
+			"b49f21d17acf05b322ebbc2983fdfcc3fe1f77d80c122dca03806a8163f0416a"
+		);
+		assert_eq!(
+			hex(&blake2_256(&[7; 129])),
+			"a04de7247309966b2b2afc367838071ea523f897e6a405286f2b5627032a8dea"
+		);
+	}
+	#[test]
+	fn sha2_256_vectors() {
+		assert_eq!(
+			hex(&sha2_256(b"")),
+			"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+		);
+		assert_eq!(
+			hex(&sha2_256(b"abc")),
+			"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+		);
+		// The padding does not fit in the first block.
+		assert_eq!(
+			hex(&sha2_256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+			"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+		);
+	}
+}
@@ -0,0 +1,85 @@
+//! The functions a wasm runtime imports from the node executing it, see `wasm` for the other side.
+//!
+//! Data is passed as a pointer and a length into the memory of the runtime. Functions returning
+//! data of unknown length write it to an output buffer, and return its full length: the runtime
+//! calls them again with a big enough buffer if the data did not fit.
+
+extern "C" {
+	fn ext_storage_get(key_ptr: *const u8, key_len: u32, out_ptr: *mut u8, out_len: u32) -> i64;
+	fn ext_storage_set(key_ptr: *const u8, key_len: u32, value_ptr: *const u8, value_len: u32);
+	fn ext_storage_remove(key_ptr: *const u8, key_len: u32);
+	fn ext_storage_prefix(
+		prefix_ptr: *const u8,
+		prefix_len: u32,
+		out_ptr: *mut u8,
+		out_len: u32,
+	) -> i64;
+	fn ext_hashing_blake2_256(data_ptr: *const u8, data_len: u32, out_ptr: *mut u8);
+	fn ext_hashing_sha2_256(data_ptr: *const u8, data_len: u32, out_ptr: *mut u8);
+	fn ext_logging_log(message_ptr: *const u8, message_len: u32);
+	fn ext_misc_runtime_version(
+		code_ptr: *const u8,
+		code_len: u32,
+		out_ptr: *mut u8,
+		out_len: u32,
+	) -> i64;
+}
+
+/// Read data of unknown length with `f`, which is given an output buffer and returns the full
+/// length of the data, or a negative value if there is none.
+fn read_with(f: impl Fn(*mut u8, u32) -> i64) -> Option<Vec<u8>> {
+	let mut out = vec![0; 256];
+	let len = usize::try_from(f(out.as_mut_ptr(), out.len() as u32)).ok()?;
+	if len > out.len() {
+		out.resize(len, 0);
+		f(out.as_mut_ptr(), out.len() as u32);
+	}
+	out.truncate(len);
+	Some(out)
+}
+
+pub fn storage_get(key: &[u8]) -> Option<Vec<u8>> {
+	read_with(|out_ptr, out_len| unsafe {
+		ext_storage_get(key.as_ptr(), key.len() as u32, out_ptr, out_len)
+	})
+}
+
+pub fn storage_set(key: &[u8], value: &[u8]) {
+	unsafe { ext_storage_set(key.as_ptr(), key.len() as u32, value.as_ptr(), value.len() as u32) }
+}
+
+pub fn storage_remove(key: &[u8]) {
+	unsafe { ext_storage_remove(key.as_ptr(), key.len() as u32) }
+}
+
+/// The entries whose key starts with `prefix`, encoded as a `Vec<(Vec<u8>, Vec<u8>)>`.
+pub fn storage_prefix(prefix: &[u8]) -> Vec<u8> {
+	read_with(|out_ptr, out_len| unsafe {
+		ext_storage_prefix(prefix.as_ptr(), prefix.len() as u32, out_ptr, out_len)
+	})
+	.unwrap_or_default()
+}
+
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+	let mut out = [0; 32];
+	unsafe { ext_hashing_blake2_256(data.as_ptr(), data.len() as u32, out.as_mut_ptr()) };
+	out
+}
+
+pub fn sha2_256(data: &[u8]) -> [u8; 32] {
+	let mut out = [0; 32];
+	unsafe { ext_hashing_sha2_256(data.as_ptr(), data.len() as u32, out.as_mut_ptr()) };
+	out
+}
+
+pub fn log(message: &str) {
+	unsafe { ext_logging_log(message.as_ptr(), message.len() as u32) }
+}
+
+/// The version of the wasm runtime `code`, encoded as an `Option<(String, u32)>`.
+pub fn runtime_version(code: &[u8]) -> Vec<u8> {
+	read_with(|out_ptr, out_len| unsafe {
+		ext_misc_runtime_version(code.as_ptr(), code.len() as u32, out_ptr, out_len)
+	})
+	.unwrap_or_default()
+}
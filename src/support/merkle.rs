@@ -0,0 +1,177 @@
+//! Merkle trees, to commit to many hashes with a single one.
+//!
+//! The leaves are hashes, and each node is the hash of its two children, smallest first. Ordering
+//! the children means that a proof only needs the siblings on the path from a leaf to the root, not
+//! which side they are on. When a level has an odd number of nodes, the last one is moved up as is.
+//!
+//! Leaves are hashed again, after a `LEAF` tag, to be put in the tree, and nodes are hashed after a
+//! `NODE` tag. This way a node can't pass as a leaf, and a leaf can't be the root of a tree.
+
+use super::{hashing::Hasher, Decode, Encode};
+
+/// The tag prepended to a leaf before hashing it into the tree.
+const LEAF: u8 = 0;
+
+/// The tag prepended to the two children of a node before hashing them.
+const NODE: u8 = 1;
+
+/// A proof that `leaf` is one of the leaves of the Merkle tree with the root `root`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof<Hash> {
+	/// The hash proven to be in the tree.
+	pub leaf: Hash,
+	/// The siblings of the nodes on the path from the leaf to the root, bottom up.
+	pub siblings: Vec<Hash>,
+	/// The root of the tree.
+	pub root: Hash,
+}
+
+impl<Hash: AsRef<[u8]> + Clone + Ord> MerkleProof<Hash> {
+	/// Whether the path of the proof leads from `leaf` to `root`, hashing nodes with `H`.
+	pub fn verify<H: Hasher<Output = Hash>>(&self) -> bool {
+		let root = self
+			.siblings
+			.iter()
+			.fold(hash_leaf::<H>(&self.leaf), |node, sibling| hash_pair::<H>(&node, sibling));
+		root == self.root
+	}
+}
+
+impl<Hash: Encode> Encode for MerkleProof<Hash> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.leaf.encode_to(dest);
+		self.siblings.encode_to(dest);
+		self.root.encode_to(dest);
+	}
+}
+
+impl<Hash: Decode> Decode for MerkleProof<Hash> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			leaf: Decode::decode(input)?,
+			siblings: Decode::decode(input)?,
+			root: Decode::decode(input)?,
+		})
+	}
+}
+
+/// The node of the tree for the leaf `leaf`.
+fn hash_leaf<H: Hasher>(leaf: &H::Output) -> H::Output
+where
+	H::Output: AsRef<[u8]>,
+{
+	H::hash(&[&[LEAF], leaf.as_ref()].concat())
+}
+
+/// The parent of the nodes `a` and `b`.
+fn hash_pair<H: Hasher>(a: &H::Output, b: &H::Output) -> H::Output
+where
+	H::Output: AsRef<[u8]> + Ord,
+{
+	let (left, right) = if a <= b { (a, b) } else { (b, a) };
+	H::hash(&[&[NODE], left.as_ref(), right.as_ref()].concat())
+}
+
+/// The nodes of the tree for the `leaves`.
+fn hash_leaves<H: Hasher>(leaves: &[H::Output]) -> Vec<H::Output>
+where
+	H::Output: AsRef<[u8]>,
+{
+	leaves.iter().map(hash_leaf::<H>).collect()
+}
+
+/// The maximum number of siblings in a proof for a tree with `leaves` leaves, which is the depth of
+/// the tree.
+pub fn max_depth(leaves: u32) -> u32 {
+	leaves.next_power_of_two().trailing_zeros()
+}
+
+/// The nodes of the level above `level`.
+fn parents<H: Hasher>(level: &[H::Output]) -> Vec<H::Output>
+where
+	H::Output: AsRef<[u8]> + Clone + Ord,
+{
+	level
+		.chunks(2)
+		.map(|pair| match pair {
+			[left, right] => hash_pair::<H>(left, right),
+			[last] => last.clone(),
+			_ => unreachable!("chunks have one or two nodes"),
+		})
+		.collect()
+}
+
+/// The root of the tree with the `leaves`, or `None` if there are none.
+pub fn root<H: Hasher>(leaves: &[H::Output]) -> Option<H::Output>
+where
+	H::Output: AsRef<[u8]> + Clone + Ord,
+{
+	let mut level = hash_leaves::<H>(leaves);
+	while level.len() > 1 {
+		level = parents::<H>(&level);
+	}
+	level.pop()
+}
+
+/// The proof that the leaf at `index` is in the tree with the `leaves`, or `None` if there is no
+/// such leaf. This is how a client notarizing many documents at once builds the proof of each one.
+pub fn proof<H: Hasher>(leaves: &[H::Output], index: usize) -> Option<MerkleProof<H::Output>>
+where
+	H::Output: AsRef<[u8]> + Clone + Ord,
+{
+	let leaf = leaves.get(index)?.clone();
+	let mut siblings = Vec::new();
+	let (mut level, mut index) = (hash_leaves::<H>(leaves), index);
+	while level.len() > 1 {
+		// The last node of a level with an odd number of nodes has no sibling.
+		if let Some(sibling) = level.get(index ^ 1) {
+			siblings.push(sibling.clone());
+		}
+		level = parents::<H>(&level);
+		index /= 2;
+	}
+	let root = level.pop()?;
+	Some(MerkleProof { leaf, siblings, root })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{max_depth, proof, root, MerkleProof};
+	use crate::support::{
+		hashing::{blake2_256, Blake2_256, Sha2_256},
+		Decode, Encode,
+	};
+
+	#[test]
+	fn proofs() {
+		let leaves = ["a", "b", "c", "d", "e"].map(|doc| blake2_256(doc.as_bytes()));
+		assert_eq!(root::<Blake2_256>(&[]), None);
+		// A leaf is not the root of the tree with only itself.
+		assert_ne!(root::<Blake2_256>(&leaves[..1]), Some(leaves[0]));
+
+		let root = root::<Blake2_256>(&leaves).unwrap();
+		for index in 0..leaves.len() {
+			let proof = proof::<Blake2_256>(&leaves, index).unwrap();
+			assert_eq!((proof.leaf, proof.root), (leaves[index], root));
+			assert!(proof.verify::<Blake2_256>());
+			assert!(!proof.verify::<Sha2_256>());
+			assert_eq!(MerkleProof::decode_all(&proof.encode()), Ok(proof));
+		}
+		// The last leaf is moved up to the level with two nodes.
+		assert_eq!(proof::<Blake2_256>(&leaves, 4).unwrap().siblings.len(), 1);
+		assert_eq!(proof::<Blake2_256>(&leaves, 5), None);
+
+		let mut forged = proof::<Blake2_256>(&leaves, 2).unwrap();
+		forged.leaf = blake2_256(b"f");
+		assert!(!forged.verify::<Blake2_256>());
+
+		// The parent of the first two leaves is not a leaf of the tree of the first four.
+		let node = super::root::<Blake2_256>(&leaves[..2]).unwrap();
+		let sibling = super::root::<Blake2_256>(&leaves[2..4]).unwrap();
+		let root = super::root::<Blake2_256>(&leaves[..4]).unwrap();
+		let forged = MerkleProof { leaf: node, siblings: vec![sibling], root };
+		assert!(!forged.verify::<Blake2_256>());
+
+		assert_eq!([1, 2, 3, 4, 5, 100].map(max_depth), [0, 1, 2, 2, 3, 7]);
+	}
+}
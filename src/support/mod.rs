@@ -0,0 +1,341 @@
+mod codec;
+mod currency;
+pub mod hashing;
+#[cfg(target_arch = "wasm32")]
+mod host;
+pub mod merkle;
+pub mod storage;
+#[cfg(test)]
+pub mod testing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod wasm;
+
+pub use codec::{Decode, Encode};
+pub use currency::{
+	BalanceStatus, Currency, LockIdentifier, LockableCurrency, Reasons, ReservableCurrency,
+};
+
+/// The most primitive representation of a Blockchain block.
+pub struct Block<Header, Extrinsic> {
+	/// The block header contains metadata about the block.
+	pub header: Header,
+	/// The extrinsics represent the state transitions to be executed in this block.
+	pub extrinsics: Vec<Extrinsic>,
+}
+
+/// We are using an extremely simplified header which only contains the current block number.
+/// On a real blockchain, you would expect to also find:
+/// - parent block hash
+/// - state root
+/// - extrinsics root
+/// - etc...
+pub struct Header<BlockNumber> {
+	pub block_number: BlockNumber,
+}
+
+/// This is an "extrinsic": literally an external message from outside of the blockchain.
+/// This simplified version of an extrinsic tells us who is making the call, and which call they are
+/// making.
+///
+/// Extrinsics without a caller are "inherents": data added to the block by its author, like the
+/// time, and dispatched with `Origin::None`. They come before the signed extrinsics, and a block
+/// whose inherents fail is invalid.
+pub struct Extrinsic<Caller, Call> {
+	pub caller: Option<Caller>,
+	pub call: Call,
+}
+
+impl<Header: Encode, Extrinsic: Encode> Encode for Block<Header, Extrinsic> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.header.encode_to(dest);
+		self.extrinsics.encode_to(dest);
+	}
+}
+
+impl<Header: Decode, Extrinsic: Decode> Decode for Block<Header, Extrinsic> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { header: Header::decode(input)?, extrinsics: Vec::decode(input)? })
+	}
+}
+
+impl<BlockNumber: Encode> Encode for Header<BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.block_number.encode_to(dest);
+	}
+}
+
+impl<BlockNumber: Decode> Decode for Header<BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { block_number: BlockNumber::decode(input)? })
+	}
+}
+
+impl<Caller: Encode, Call: Encode> Encode for Extrinsic<Caller, Call> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.caller.encode_to(dest);
+		self.call.encode_to(dest);
+	}
+}
+
+impl<Caller: Decode, Call: Decode> Decode for Extrinsic<Caller, Call> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self { caller: Option::decode(input)?, call: Call::decode(input)? })
+	}
+}
+
+/// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
+/// otherwise return a static error message.
+pub type DispatchResult = Result<(), &'static str>;
+
+/// The origin of a call: who, or what, is making it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin<AccountId> {
+	/// The system itself. It has the highest privileges and is not tied to any account.
+	Root,
+	/// A call signed by an account, e.g. coming from an extrinsic.
+	Signed(AccountId),
+	/// An inherent, which no account signed.
+	None,
+}
+
+/// Each origin is encoded as the index of its variant, followed by its account if it has one.
+impl<AccountId: Encode> Encode for Origin<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Origin::Root => 0u8.encode_to(dest),
+			Origin::Signed(who) => (1u8, who).encode_to(dest),
+			Origin::None => 2u8.encode_to(dest),
+		}
+	}
+}
+
+impl<AccountId: Decode> Decode for Origin<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Origin::Root),
+			1 => Ok(Origin::Signed(AccountId::decode(input)?)),
+			2 => Ok(Origin::None),
+			_ => Err("invalid origin"),
+		}
+	}
+}
+
+/// Ensure the call was made by `Root`.
+pub fn ensure_root<AccountId>(origin: Origin<AccountId>) -> DispatchResult {
+	match origin {
+		Origin::Root => Ok(()),
+		_ => Err("bad origin: expected root"),
+	}
+}
+
+/// Ensure the call was signed, returning the account which signed it.
+pub fn ensure_signed<AccountId>(origin: Origin<AccountId>) -> Result<AccountId, &'static str> {
+	match origin {
+		Origin::Signed(who) => Ok(who),
+		_ => Err("bad origin: expected signed"),
+	}
+}
+
+/// Ensure the call is an inherent.
+pub fn ensure_none<AccountId>(origin: Origin<AccountId>) -> DispatchResult {
+	match origin {
+		Origin::None => Ok(()),
+		_ => Err("bad origin: expected none"),
+	}
+}
+
+/// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
+/// function call.
+pub trait Dispatch {
+	/// The type used to identify the caller of the function, usually an `Origin`.
+	type Caller;
+	/// The state transition function call the caller is trying to access.
+	type Call;
+
+	/// This function takes a `caller` and the `call` they want to make, and returns a `Result`
+	/// based on the outcome of that function call.
+	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+}
+
+/// A call which dispatches itself, like the `RuntimeCall` generated by `#[macros::runtime]`. This
+/// is how pallets dispatch the calls they are given, e.g. `sudo`.
+pub trait Dispatchable<AccountId> {
+	/// Dispatch this call with `origin`.
+	fn dispatch(self, origin: Origin<AccountId>) -> DispatchResult;
+}
+
+/// A function dispatching calls to a runtime `R`, with the logic of one of its versions.
+pub type DispatchFn<R> =
+	fn(&mut R, <R as Dispatch>::Caller, <R as Dispatch>::Call) -> DispatchResult;
+
+/// The dispatch logic of the runtime versions this binary knows natively, which `system::set_code`
+/// switches between. `#[macros::runtime]` dispatches every call with the logic of the version the
+/// current block runs.
+pub trait NativeVersions: Dispatch + Sized + 'static
+where
+	Self::Caller: 'static,
+	Self::Call: 'static,
+{
+	/// The dispatch function of each spec version whose logic differs from `Dispatch::dispatch`.
+	const DISPATCH: &'static [(u32, DispatchFn<Self>)];
+}
+
+/// A filter over calls, like the calls a kind of proxy can make, see `proxy`.
+pub trait InstanceFilter<Call> {
+	/// Whether `call` passes the filter.
+	fn filter(&self, call: &Call) -> bool;
+}
+
+/// Describes a single argument of a callable function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgMetadata {
+	pub name: &'static str,
+	pub ty: &'static str,
+}
+
+/// Describes a callable function of a pallet, including the index used to encode it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallMetadata {
+	pub index: u8,
+	pub name: &'static str,
+	pub args: Vec<ArgMetadata>,
+}
+
+/// Describes a pallet included in the runtime, including the index used to encode its calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PalletMetadata {
+	pub index: u8,
+	pub name: &'static str,
+	pub calls: Vec<CallMetadata>,
+}
+
+/// Implemented by the `Call` enum generated for each pallet by `#[macros::call]`.
+pub trait GetCallMetadata {
+	/// The metadata of every callable function, ordered by call index.
+	fn call_metadata() -> Vec<CallMetadata>;
+}
+
+/// Implemented by the `RuntimeCall` enum generated by `#[macros::runtime]`.
+pub trait GetRuntimeMetadata {
+	/// The metadata of every callable pallet, ordered by pallet index.
+	fn runtime_metadata() -> Vec<PalletMetadata>;
+}
+
+/// The version of the runtime logic.
+///
+/// The chain records which `spec_version` it runs in the system pallet. Changing it, through
+/// `system::set_code`, triggers the storage migrations of every pallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeVersion {
+	/// The name of the runtime. All the versions of a runtime share the same name.
+	pub spec_name: &'static str,
+	/// The version of the runtime logic. Always increases with upgrades.
+	pub spec_version: u32,
+}
+
+/// Encoded like a `(String, u32)`, which is how the node decodes the version of a wasm runtime.
+impl Encode for RuntimeVersion {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.spec_name.encode_to(dest);
+		self.spec_version.encode_to(dest);
+	}
+}
+
+/// Log a message of the runtime for the node operator.
+pub fn log(message: &str) {
+	#[cfg(not(target_arch = "wasm32"))]
+	eprintln!("{message}");
+	#[cfg(target_arch = "wasm32")]
+	host::log(message);
+}
+
+/// The `spec_name` and `spec_version` of the wasm runtime `code`, or `None` if it is not a valid
+/// runtime.
+pub fn runtime_version(code: &[u8]) -> Option<(String, u32)> {
+	#[cfg(not(target_arch = "wasm32"))]
+	return wasm::WasmRuntime::new(code).and_then(|runtime| runtime.version()).ok();
+	#[cfg(target_arch = "wasm32")]
+	return Decode::decode_all(&host::runtime_version(code)).ok().flatten();
+}
+
+/// Hooks run by each pallet at fixed points of the execution of every block.
+pub trait Hooks<BlockNumber> {
+	/// Run before the extrinsics of the block `block_number` are executed, inherents included.
+	fn on_initialize(&mut self, _block_number: BlockNumber) {}
+
+	/// Run after the extrinsics of the block `block_number` were executed. If this fails, the
+	/// block is invalid.
+	fn on_finalize(&mut self, _block_number: BlockNumber) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// How the runtime charges the fee of signed extrinsics, before dispatching them.
+pub trait ChargeTransaction<AccountId> {
+	/// Charge the fee of an extrinsic signed by `who`. The call is not dispatched if this fails.
+	fn charge_transaction(&mut self, who: &AccountId) -> DispatchResult;
+}
+
+/// A hook run once by each pallet after the runtime was upgraded, before executing the first block
+/// with the new `spec_version`. This is where a pallet migrates its stored data to a new format.
+pub trait OnRuntimeUpgrade {
+	/// Migrate the stored data of this pallet.
+	fn on_runtime_upgrade(&mut self) {}
+
+	/// Set up a new chain, whose stored data is already in the current format: this is where a
+	/// pallet records its storage version, so that its migrations never run on that data.
+	fn on_genesis(&mut self) {}
+
+	/// Check the state before the migration, returning data needed by `post_upgrade`.
+	fn pre_upgrade(&self) -> Result<Vec<u8>, &'static str> {
+		Ok(Vec::new())
+	}
+
+	/// Check the invariants of the state after the migration, given the data from `pre_upgrade`.
+	fn post_upgrade(&self, _state: Vec<u8>) -> DispatchResult {
+		Ok(())
+	}
+
+	/// Run the migration along with the checks of `pre_upgrade` and `post_upgrade`. This is meant
+	/// to test migrations against a snapshot of a real state, see `storage::execute_with`.
+	fn try_on_runtime_upgrade(&mut self) -> DispatchResult {
+		let state = self.pre_upgrade()?;
+		self.on_runtime_upgrade();
+		self.post_upgrade(state)
+	}
+}
+
+/// A source of the current time, for pallets which record when things happen.
+pub trait UnixTime {
+	/// The current time in milliseconds since the Unix epoch, or `None` if it is not known.
+	fn now() -> Option<u64>;
+}
+
+/// The time is never known, for runtimes without a source of time.
+impl UnixTime for () {
+	fn now() -> Option<u64> {
+		None
+	}
+}
+
+/// Accounts which no key controls, but a pallet does, like the account of a multisig. They are
+/// derived from a seed unique to what controls them, which makes them deterministic.
+pub trait DeriveAccount {
+	/// The account derived from `seed`.
+	fn derive_account(seed: &[u8]) -> Self;
+}
+
+/// Derived accounts are named after the hash of their seed, in hex.
+impl DeriveAccount for String {
+	fn derive_account(seed: &[u8]) -> Self {
+		let hash = hashing::blake2_256(seed);
+		hash.iter().fold("0x".to_string(), |name, byte| name + &format!("{byte:02x}"))
+	}
+}
+
+impl DeriveAccount for u64 {
+	fn derive_account(seed: &[u8]) -> Self {
+		let hash = hashing::blake2_256(seed);
+		u64::from_le_bytes(hash[..8].try_into().expect("the hash has more than 8 bytes"))
+	}
+}
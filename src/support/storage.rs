@@ -0,0 +1,383 @@
+//! The state of the blockchain.
+//!
+//! All pallets read and write a single key-value store, which makes it possible to snapshot the
+//! whole state, migrate the raw stored data, and for pallets to use each other's storage.
+//! Pallets use it through the typed `StorageValue` and `StorageMap` handles, whose keys are
+//! prefixed with the pallet and item names so that they never collide.
+//!
+//! Natively, the state is kept in memory by this module. A wasm runtime has no state of its own,
+//! and reads and writes the state of the node executing it instead, see `host`.
+
+use core::{fmt::Debug, marker::PhantomData};
+use std::collections::BTreeMap;
+
+use super::{Decode, Encode};
+
+/// The whole state of the blockchain, as raw keys and values.
+pub type State = BTreeMap<Vec<u8>, Vec<u8>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+	/// The state which is currently being read and written.
+	static STATE: std::cell::RefCell<State> = const { std::cell::RefCell::new(State::new()) };
+}
+
+/// The value each key had before a transaction first changed it, or `None` if it had no value.
+type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+thread_local! {
+	/// The changes of the open transactions, innermost last, see `with_transaction`.
+	static TRANSACTIONS: std::cell::RefCell<Vec<Changes>> =
+		const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Get the raw value stored at `key`.
+pub fn get(key: &[u8]) -> Option<Vec<u8>> {
+	#[cfg(not(target_arch = "wasm32"))]
+	return STATE.with(|state| state.borrow().get(key).cloned());
+	#[cfg(target_arch = "wasm32")]
+	return super::host::storage_get(key);
+}
+
+/// Store the raw `value` at `key`.
+pub fn set(key: &[u8], value: Vec<u8>) {
+	note_change(key);
+	#[cfg(not(target_arch = "wasm32"))]
+	STATE.with(|state| state.borrow_mut().insert(key.to_vec(), value));
+	#[cfg(target_arch = "wasm32")]
+	super::host::storage_set(key, &value);
+}
+
+/// Remove the raw value stored at `key`, returning it.
+pub fn remove(key: &[u8]) -> Option<Vec<u8>> {
+	note_change(key);
+	#[cfg(not(target_arch = "wasm32"))]
+	return STATE.with(|state| state.borrow_mut().remove(key));
+	#[cfg(target_arch = "wasm32")]
+	return get(key).inspect(|_| super::host::storage_remove(key));
+}
+
+/// All the raw entries whose key starts with `prefix`, ordered by key.
+pub fn iter_prefix(prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+	#[cfg(not(target_arch = "wasm32"))]
+	return STATE.with(|state| {
+		state
+			.borrow()
+			.range(prefix.to_vec()..)
+			.take_while(|(key, _)| key.starts_with(prefix))
+			.map(|(key, value)| (key.clone(), value.clone()))
+			.collect()
+	});
+	#[cfg(target_arch = "wasm32")]
+	return Decode::decode_all(&super::host::storage_prefix(prefix))
+		.expect("the node encodes storage entries correctly");
+}
+
+/// Remember the value of `key` before the innermost open transaction first changes it.
+fn note_change(key: &[u8]) {
+	TRANSACTIONS.with(|transactions| {
+		if let Some(changes) = transactions.borrow_mut().last_mut() {
+			changes.entry(key.to_vec()).or_insert_with(|| get(key));
+		}
+	});
+}
+
+/// Execute `f` in a transaction: if it returns an error, every change it made to the state is
+/// reverted. Transactions can be nested, and this works the same in a wasm runtime.
+pub fn with_transaction<R, E>(f: impl FnOnce() -> Result<R, E>) -> Result<R, E> {
+	TRANSACTIONS.with(|transactions| transactions.borrow_mut().push(Changes::new()));
+	let result = f();
+	let changes = TRANSACTIONS.with(|transactions| {
+		let mut transactions = transactions.borrow_mut();
+		let changes = transactions.pop().expect("the transaction was opened above");
+		// The outer transaction now has to revert the changes of this one too.
+		if let Some(outer) = transactions.last_mut() {
+			for (key, value) in &changes {
+				outer.entry(key.clone()).or_insert_with(|| value.clone());
+			}
+		}
+		changes
+	});
+	if result.is_err() {
+		for (key, value) in changes {
+			match value {
+				Some(value) => set(&key, value),
+				None => {
+					remove(&key);
+				},
+			}
+		}
+	}
+	result
+}
+
+/// A copy of the current state.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn snapshot() -> State {
+	STATE.with(|state| state.borrow().clone())
+}
+
+/// Execute `f` with `state` as the current state. Any change made by `f` is written to `state`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn execute_with<R>(state: &mut State, f: impl FnOnce() -> R) -> R {
+	STATE.with(|current| core::mem::swap(&mut *current.borrow_mut(), state));
+	let result = f();
+	STATE.with(|current| core::mem::swap(&mut *current.borrow_mut(), state));
+	result
+}
+
+/// The root hash of `state`, which commits to all of its keys and values.
+pub fn root(state: &State) -> [u8; 32] {
+	super::hashing::blake2_256(&state.encode())
+}
+
+/// The key prefix of the storage item `name` of the pallet `pallet`.
+fn prefix(pallet: &str, name: &str) -> Vec<u8> {
+	let mut prefix = pallet.encode();
+	name.encode_to(&mut prefix);
+	prefix
+}
+
+/// A single value kept in storage.
+pub struct StorageValue<V> {
+	pallet: &'static str,
+	name: &'static str,
+	_value: PhantomData<V>,
+}
+
+impl<V: Encode + Decode> StorageValue<V> {
+	/// Create a handle to the storage item `name` of the pallet `pallet`.
+	pub const fn new(pallet: &'static str, name: &'static str) -> Self {
+		Self { pallet, name, _value: PhantomData }
+	}
+
+	/// The raw key this value is stored at.
+	pub fn key(&self) -> Vec<u8> {
+		prefix(self.pallet, self.name)
+	}
+
+	/// Get the stored value, if there is one which can be decoded.
+	pub fn get(&self) -> Option<V> {
+		get(&self.key()).and_then(|raw| V::decode_all(&raw).ok())
+	}
+
+	/// Store `value`.
+	pub fn put(&self, value: V) {
+		set(&self.key(), value.encode());
+	}
+
+	/// Remove the stored value, returning it.
+	pub fn take(&self) -> Option<V> {
+		remove(&self.key()).and_then(|raw| V::decode_all(&raw).ok())
+	}
+
+	/// Whether a value is stored.
+	pub fn exists(&self) -> bool {
+		get(&self.key()).is_some()
+	}
+
+	/// Replace the stored value by decoding it as the old type `O` and converting it with `f`.
+	/// Returning `None` removes the value. This is meant for migrations.
+	pub fn translate<O: Decode>(&self, f: impl FnOnce(Option<O>) -> Option<V>) {
+		let old = get(&self.key()).and_then(|raw| O::decode_all(&raw).ok());
+		match f(old) {
+			Some(value) => self.put(value),
+			None => {
+				remove(&self.key());
+			},
+		}
+	}
+}
+
+impl<V: Encode + Decode + Debug> Debug for StorageValue<V> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		self.get().fmt(f)
+	}
+}
+
+/// A map from keys to values kept in storage.
+pub struct StorageMap<K, V> {
+	pallet: &'static str,
+	name: &'static str,
+	_entry: PhantomData<(K, V)>,
+}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+	/// Create a handle to the storage item `name` of the pallet `pallet`.
+	pub const fn new(pallet: &'static str, name: &'static str) -> Self {
+		Self { pallet, name, _entry: PhantomData }
+	}
+
+	/// The raw key prefix shared by all the entries of this map.
+	pub fn prefix(&self) -> Vec<u8> {
+		prefix(self.pallet, self.name)
+	}
+
+	/// The raw key the value of `key` is stored at.
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		let mut raw = self.prefix();
+		key.encode_to(&mut raw);
+		raw
+	}
+
+	/// Get the value stored for `key`, if there is one which can be decoded.
+	pub fn get(&self, key: &K) -> Option<V> {
+		get(&self.key(key)).and_then(|raw| V::decode_all(&raw).ok())
+	}
+
+	/// Store `value` for `key`.
+	pub fn insert(&self, key: K, value: V) {
+		set(&self.key(&key), value.encode());
+	}
+
+	/// Remove the value stored for `key`, returning it.
+	pub fn remove(&self, key: &K) -> Option<V> {
+		remove(&self.key(key)).and_then(|raw| V::decode_all(&raw).ok())
+	}
+
+	/// Whether a value is stored for `key`.
+	pub fn contains_key(&self, key: &K) -> bool {
+		get(&self.key(key)).is_some()
+	}
+
+	/// Whether the map has no entries.
+	pub fn is_empty(&self) -> bool {
+		iter_prefix(&self.prefix()).is_empty()
+	}
+
+	/// All the entries of the map which can be decoded, ordered by their encoded key.
+	pub fn iter(&self) -> impl Iterator<Item = (K, V)> {
+		let prefix = self.prefix();
+		iter_prefix(&prefix).into_iter().filter_map(move |(raw_key, raw_value)| {
+			let key = K::decode_all(&raw_key[prefix.len()..]).ok()?;
+			let value = V::decode_all(&raw_value).ok()?;
+			Some((key, value))
+		})
+	}
+
+	/// Replace every value by decoding it as the old type `O` and converting it with `f`.
+	/// Returning `None` removes the entry. This is meant for migrations.
+	pub fn translate<O: Decode>(&self, mut f: impl FnMut(K, O) -> Option<V>) {
+		let prefix = self.prefix();
+		for (raw_key, raw_value) in iter_prefix(&prefix) {
+			let key = K::decode_all(&raw_key[prefix.len()..]);
+			let old = O::decode_all(&raw_value);
+			match (key, old) {
+				(Ok(key), Ok(old)) => match f(key, old) {
+					Some(value) => set(&raw_key, value.encode()),
+					None => {
+						remove(&raw_key);
+					},
+				},
+				// Entries which can't be decoded are useless, so we get rid of them.
+				_ => {
+					remove(&raw_key);
+				},
+			}
+		}
+	}
+}
+
+impl<K, V> Debug for StorageMap<K, V>
+where
+	K: Encode + Decode + Debug,
+	V: Encode + Decode + Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{execute_with, root, snapshot, with_transaction, State, StorageMap, StorageValue};
+
+	#[test]
+	fn value_and_map() {
+		let value = StorageValue::<u32>::new("Test", "Value");
+		let map = StorageMap::<String, u64>::new("Test", "Map");
+
+		assert_eq!(value.get(), None);
+		value.put(7);
+		assert_eq!(value.get(), Some(7));
+
+		assert!(map.is_empty());
+		map.insert("bob".to_string(), 2);
+		map.insert("alice".to_string(), 1);
+		assert_eq!(map.get(&"alice".to_string()), Some(1));
+		assert_eq!(
+			map.iter().collect::<Vec<_>>(),
+			vec![("bob".to_string(), 2), ("alice".to_string(), 1)]
+		);
+		assert_eq!(map.remove(&"bob".to_string()), Some(2));
+		assert!(!map.contains_key(&"bob".to_string()));
+
+		// Items of other pallets or with other names don't collide.
+		assert_eq!(StorageValue::<u32>::new("Other", "Value").get(), None);
+		assert!(StorageMap::<String, u64>::new("Test", "Map2").is_empty());
+	}
+
+	#[test]
+	fn translate() {
+		let map = StorageMap::<u8, u64>::new("Test", "Map");
+		StorageMap::<u8, u32>::new("Test", "Map").insert(1, 10);
+		StorageMap::<u8, u32>::new("Test", "Map").insert(2, 0);
+
+		map.translate(|_, old: u32| if old == 0 { None } else { Some(old as u64 * 2) });
+		assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 20)]);
+	}
+
+	#[test]
+	fn execute_with_state() {
+		let value = StorageValue::<u32>::new("Test", "Value");
+		value.put(1);
+
+		let mut other = State::new();
+		execute_with(&mut other, || {
+			assert_eq!(value.get(), None);
+			value.put(2);
+		});
+		assert_eq!(value.get(), Some(1));
+		assert_eq!(execute_with(&mut other, || value.get()), Some(2));
+		assert_ne!(snapshot(), other);
+		assert_ne!(root(&snapshot()), root(&other));
+		assert_eq!(root(&other), root(&other.clone()));
+	}
+
+	#[test]
+	fn transactions() {
+		let value = StorageValue::<u32>::new("Test", "Value");
+		let map = StorageMap::<u8, u32>::new("Test", "Map");
+		value.put(1);
+		let before = snapshot();
+
+		let result = with_transaction(|| {
+			value.put(2);
+			map.insert(1, 1);
+			// The inner transaction is kept, but reverted with the outer one.
+			let inner = with_transaction(|| {
+				map.insert(2, 2);
+				Ok::<_, ()>(map.get(&2))
+			});
+			assert_eq!(inner, Ok(Some(2)));
+			assert_eq!(
+				with_transaction(|| {
+					value.take();
+					Err::<(), _>("inner")
+				}),
+				Err("inner")
+			);
+			assert_eq!(value.get(), Some(2));
+			Err::<(), _>("outer")
+		});
+		assert_eq!(result, Err("outer"));
+		assert_eq!(snapshot(), before);
+
+		let result = with_transaction(|| {
+			value.put(3);
+			Ok::<_, ()>(())
+		});
+		assert_eq!(result, Ok(()));
+		assert_eq!(value.get(), Some(3));
+	}
+}
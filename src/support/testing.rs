@@ -0,0 +1,97 @@
+//! Fixtures shared by the tests of the pallets and of the runtime.
+
+use core::{fmt::Debug, marker::PhantomData};
+
+use super::{storage::StorageValue, Decode, DispatchResult, Dispatchable, Encode, Origin};
+
+/// The origins a `TestCall` accepts.
+pub trait TestOrigin {
+	/// Check the origin of a `TestCall`, before it is dispatched.
+	fn ensure(origin: &Origin<u64>) -> DispatchResult;
+}
+
+/// Any origin.
+pub struct AnyOrigin;
+
+impl TestOrigin for AnyOrigin {
+	fn ensure(_origin: &Origin<u64>) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// Only `Origin::Signed`.
+pub struct SignedOrigin;
+
+impl TestOrigin for SignedOrigin {
+	fn ensure(origin: &Origin<u64>) -> DispatchResult {
+		super::ensure_signed(origin.clone()).map(|_| ())
+	}
+}
+
+/// Only `Origin::Root`.
+pub struct RootOrigin;
+
+impl TestOrigin for RootOrigin {
+	fn ensure(origin: &Origin<u64>) -> DispatchResult {
+		super::ensure_root(origin.clone())
+	}
+}
+
+/// A call for pallets dispatching the calls of the runtime, which records its value and origin
+/// when dispatched, see `dispatched`, and then fails if its value is zero. It only accepts the
+/// origins of `O`.
+pub struct TestCall<O = AnyOrigin> {
+	pub value: u32,
+	origin: PhantomData<O>,
+}
+
+impl<O> TestCall<O> {
+	/// A call with `value`, which fails if it is zero.
+	pub fn new(value: u32) -> Self {
+		Self { value, origin: PhantomData }
+	}
+}
+
+impl<O> Encode for TestCall<O> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.value.encode_to(dest)
+	}
+}
+
+impl<O> Decode for TestCall<O> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self::new(Decode::decode(input)?))
+	}
+}
+
+impl<O: TestOrigin> Dispatchable<u64> for TestCall<O> {
+	fn dispatch(self, origin: Origin<u64>) -> DispatchResult {
+		O::ensure(&origin)?;
+		let mut calls = dispatched();
+		calls.push((self.value, origin));
+		dispatched_calls().put(calls);
+		if self.value == 0 {
+			return Err("zero");
+		}
+		Ok(())
+	}
+}
+
+fn dispatched_calls() -> StorageValue<Vec<(u32, Origin<u64>)>> {
+	StorageValue::new("Test", "Dispatched")
+}
+
+/// The value and origin of every `TestCall` dispatched so far, oldest first. What failing calls
+/// recorded is kept, unless the caller reverted it.
+pub fn dispatched() -> Vec<(u32, Origin<u64>)> {
+	dispatched_calls().get().unwrap_or_default()
+}
+
+/// Check that each of `values` decodes back from its encoding, e.g. the events of a test.
+pub fn assert_round_trip<T: Encode + Decode + Debug + PartialEq>(
+	values: impl IntoIterator<Item = T>,
+) {
+	for value in values {
+		assert_eq!(T::decode_all(&value.encode()), Ok(value));
+	}
+}
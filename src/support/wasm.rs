@@ -0,0 +1,185 @@
+//! Execute a runtime compiled to WebAssembly, with the `wasmi` interpreter.
+//!
+//! A wasm runtime exports its `memory`, an `alloc(len) -> ptr` function, and entry points taking
+//! the pointer and length of their encoded input. Entry points return their encoded output as
+//! `ptr << 32 | len`:
+//! - `version()` returns the `spec_name` and `spec_version` of the runtime, as a `(String, u32)`.
+//! - `execute_block(block)` returns the `DispatchResult` of executing the encoded block.
+//!
+//! The runtime imports the host functions defined here, which give it access to the state in
+//! `storage`, hashing and logging. Every call runs in a fresh instance of the runtime, with a
+//! budget of fuel: each executed instruction consumes some, and the call fails once it runs out.
+
+use wasmi::{core::TrapCode, Caller, Config, Engine, Error, Extern, Linker, Memory, Module, Store};
+
+use super::{hashing, storage, Decode, Encode};
+
+/// The fuel of a call to `version`, which only returns constants.
+const VERSION_FUEL: u64 = 1_000_000;
+
+/// A compiled wasm runtime.
+pub struct WasmRuntime {
+	module: Module,
+}
+
+impl WasmRuntime {
+	/// Compile the wasm runtime `code`, metering the fuel it consumes.
+	pub fn new(code: &[u8]) -> Result<Self, String> {
+		let mut config = Config::default();
+		config.consume_fuel(true);
+		let module = Module::new(&Engine::new(&config), code).map_err(|error| error.to_string())?;
+		Ok(Self { module })
+	}
+
+	/// The `spec_name` and `spec_version` of the runtime.
+	pub fn version(&self) -> Result<(String, u32), String> {
+		let output = self.call("version", &[], VERSION_FUEL)?;
+		Ok(Decode::decode_all(&output)?)
+	}
+
+	/// Execute an encoded block on the current state, consuming at most `fuel`. The block is
+	/// invalid if it needs more.
+	pub fn execute_block(&self, block: &[u8], fuel: u64) -> Result<(), String> {
+		let output = self.call("execute_block", block, fuel)?;
+		<Result<(), String>>::decode_all(&output)?
+	}
+
+	/// Call the entry point `name` of a new instance of the runtime with a budget of `fuel`,
+	/// returning its output.
+	fn call(&self, name: &str, input: &[u8], fuel: u64) -> Result<Vec<u8>, String> {
+		let engine = self.module.engine();
+		let mut store = Store::new(engine, ());
+		store.set_fuel(fuel).map_err(|error| error.to_string())?;
+		let instance = host_functions(engine)
+			.and_then(|linker| linker.instantiate(&mut store, &self.module)?.start(&mut store))
+			.map_err(|error| error.to_string())?;
+		let memory = instance
+			.get_memory(&store, "memory")
+			.ok_or("the runtime does not export its memory")?;
+		let alloc = instance
+			.get_typed_func::<u32, u32>(&store, "alloc")
+			.map_err(|error| error.to_string())?;
+		let entry_point = instance
+			.get_typed_func::<(u32, u32), u64>(&store, name)
+			.map_err(|error| error.to_string())?;
+
+		let run = |store: &mut Store<()>| -> Result<Vec<u8>, Error> {
+			let input_ptr = alloc.call(&mut *store, input.len() as u32)?;
+			memory.write(&mut *store, input_ptr as usize, input)?;
+			let output = entry_point.call(&mut *store, (input_ptr, input.len() as u32))?;
+			let mut buffer = vec![0; output as u32 as usize];
+			memory.read(&*store, (output >> 32) as usize, &mut buffer)?;
+			Ok(buffer)
+		};
+		run(&mut store).map_err(|error| match error.as_trap_code() {
+			Some(TrapCode::OutOfFuel) => "the wasm runtime ran out of fuel".to_string(),
+			_ => format!("wasm runtime failed: {error}"),
+		})
+	}
+}
+
+/// The memory of the runtime calling a host function.
+fn memory(caller: &Caller<'_, ()>) -> Result<Memory, Error> {
+	caller
+		.get_export("memory")
+		.and_then(Extern::into_memory)
+		.ok_or_else(|| Error::new("the runtime does not export its memory"))
+}
+
+/// Read `len` bytes at `ptr` from the memory of the runtime.
+fn read(caller: &Caller<'_, ()>, ptr: u32, len: u32) -> Result<Vec<u8>, Error> {
+	let mut buffer = vec![0; len as usize];
+	memory(caller)?.read(caller, ptr as usize, &mut buffer)?;
+	Ok(buffer)
+}
+
+/// Write as much of `data` as fits in the output buffer of the runtime, returning the full length
+/// of `data`.
+fn write_out(
+	caller: &mut Caller<'_, ()>,
+	out_ptr: u32,
+	out_len: u32,
+	data: &[u8],
+) -> Result<i64, Error> {
+	let len = data.len().min(out_len as usize);
+	memory(caller)?.write(caller, out_ptr as usize, &data[..len])?;
+	Ok(data.len() as i64)
+}
+
+/// The functions a wasm runtime can import, see `host` for the runtime side.
+fn host_functions(engine: &Engine) -> Result<Linker<()>, Error> {
+	let mut linker = Linker::new(engine);
+	linker.func_wrap(
+		"env",
+		"ext_storage_get",
+		|mut caller: Caller<'_, ()>, key_ptr: u32, key_len: u32, out_ptr: u32, out_len: u32| {
+			match storage::get(&read(&caller, key_ptr, key_len)?) {
+				Some(value) => write_out(&mut caller, out_ptr, out_len, &value),
+				None => Ok(-1),
+			}
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_storage_set",
+		|caller: Caller<'_, ()>, key_ptr: u32, key_len: u32, value_ptr: u32, value_len: u32| {
+			storage::set(&read(&caller, key_ptr, key_len)?, read(&caller, value_ptr, value_len)?);
+			Ok(())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_storage_remove",
+		|caller: Caller<'_, ()>, key_ptr: u32, key_len: u32| {
+			storage::remove(&read(&caller, key_ptr, key_len)?);
+			Ok(())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_storage_prefix",
+		|mut caller: Caller<'_, ()>,
+		 prefix_ptr: u32,
+		 prefix_len: u32,
+		 out_ptr: u32,
+		 out_len: u32| {
+			let entries = storage::iter_prefix(&read(&caller, prefix_ptr, prefix_len)?);
+			write_out(&mut caller, out_ptr, out_len, &entries.encode())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_hashing_blake2_256",
+		|mut caller: Caller<'_, ()>, data_ptr: u32, data_len: u32, out_ptr: u32| {
+			let hash = hashing::blake2_256(&read(&caller, data_ptr, data_len)?);
+			memory(&caller)?.write(&mut caller, out_ptr as usize, &hash)?;
+			Ok(())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_hashing_sha2_256",
+		|mut caller: Caller<'_, ()>, data_ptr: u32, data_len: u32, out_ptr: u32| {
+			let hash = hashing::sha2_256(&read(&caller, data_ptr, data_len)?);
+			memory(&caller)?.write(&mut caller, out_ptr as usize, &hash)?;
+			Ok(())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_logging_log",
+		|caller: Caller<'_, ()>, message_ptr: u32, message_len: u32| {
+			super::log(&String::from_utf8_lossy(&read(&caller, message_ptr, message_len)?));
+			Ok(())
+		},
+	)?;
+	linker.func_wrap(
+		"env",
+		"ext_misc_runtime_version",
+		|mut caller: Caller<'_, ()>, code_ptr: u32, code_len: u32, out_ptr: u32, out_len: u32| {
+			let version = super::runtime_version(&read(&caller, code_ptr, code_len)?);
+			write_out(&mut caller, out_ptr, out_len, &version.encode())
+		},
+	)?;
+	Ok(linker)
+}
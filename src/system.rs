@@ -1,11 +1,34 @@
-use std::{collections::BTreeMap, ops::AddAssign};
+use core::fmt::Debug;
+use std::ops::AddAssign;
 
-use num::{One, Zero};
+use num::{CheckedAdd, One, Zero};
+
+use crate::support::{
+    self, ensure_root,
+    storage::{StorageMap, StorageValue},
+    Decode, DispatchResult, Encode, Hooks, OnRuntimeUpgrade, Origin, RuntimeVersion,
+};
 
 pub trait Config {
-    type BlockNumber: Zero + One + AddAssign + Copy;
-    type AccountId: Ord + Clone;
-    type Nonce: Zero + One + AddAssign + Copy;
+    type BlockNumber: Zero
+        + One
+        + AddAssign
+        + CheckedAdd
+        + Copy
+        + PartialOrd
+        + Encode
+        + Decode
+        + Debug;
+    type AccountId: Ord + Clone + Encode + Decode;
+    type Nonce: Zero + One + AddAssign + Copy + Encode + Decode;
+    /// The events of every pallet of the runtime, see `deposit_event`.
+    type RuntimeEvent: Debug + Encode + Decode;
+    /// The versions of the stored data layout known to this binary, oldest first.
+    /// A new chain starts on the last one, and `set_code` can only switch to one of these. Each
+    /// version has its own native dispatch logic, see `support::NativeVersions`, and the
+    /// migrations its stored data went through. Other versions can only run as wasm code, see
+    /// `set_wasm_code`.
+    const VERSIONS: &'static [RuntimeVersion];
 }
 
 /// This is the System Pallet.
@@ -13,42 +36,180 @@ pub trait Config {
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
     /// The current block number.
-    block_number: T::BlockNumber,
+    block_number: StorageValue<T::BlockNumber>,
     /// A map from an account to their nonce.
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    nonce: StorageMap<T::AccountId, T::Nonce>,
+    /// The spec version of the runtime logic the chain is running.
+    spec_version: StorageValue<u32>,
+    /// The spec version the stored data was last migrated to.
+    last_runtime_upgrade: StorageValue<u32>,
+    /// The wasm code of the runtime logic, if the chain switched to one with `set_wasm_code`.
+    code: StorageValue<Vec<u8>>,
+    /// The events deposited by the pallets during the current block.
+    events: StorageValue<Vec<T::RuntimeEvent>>,
 }
 
 impl<T: Config> Pallet<T> {
     /// Create a new instance of the System Pallet.
     pub fn new() -> Self {
         Self {
-            block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::new(),
+            block_number: StorageValue::new("System", "BlockNumber"),
+            nonce: StorageMap::new("System", "Nonce"),
+            spec_version: StorageValue::new("System", "SpecVersion"),
+            last_runtime_upgrade: StorageValue::new("System", "LastRuntimeUpgrade"),
+            code: StorageValue::new("System", "Code"),
+            events: StorageValue::new("System", "Events"),
         }
     }
 
     /// Get the current block number.
     pub fn block_number(&self) -> T::BlockNumber {
-        self.block_number
+        self.block_number.get().unwrap_or_else(T::BlockNumber::zero)
     }
 
     // This function can be used to increment the block number.
     // Increases the block number by one.
     pub fn inc_block_number(&mut self) {
-        self.block_number.add_assign(T::BlockNumber::one());
+        let mut val = self.block_number();
+        val.add_assign(T::BlockNumber::one());
+        self.block_number.put(val);
+    }
+
+    /// Get the nonce of an account.
+    pub fn nonce(&self, who: &T::AccountId) -> T::Nonce {
+        self.nonce.get(who).unwrap_or_else(T::Nonce::zero)
     }
 
     // Increment the nonce of an account. This helps us keep track of how many transactions each
     // account has made.
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
-        let mut val = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
+        let mut val = self.nonce(who);
         val.add_assign(T::Nonce::one());
         self.nonce.insert(who.clone(), val);
     }
+
+    /// Remove what this pallet stores about `who`, once the account was reaped.
+    pub fn reap_account(&mut self, who: &T::AccountId) {
+        self.nonce.remove(who);
+    }
+
+    /// Get the spec version of the runtime logic the chain is running.
+    pub fn spec_version(&self) -> u32 {
+        self.spec_version.get().unwrap_or_else(|| {
+            T::VERSIONS.last().expect("no runtime version registered").spec_version
+        })
+    }
+
+    /// Get the spec version the current block runs: the one the stored data was migrated to at the
+    /// start of the block. A version set with `set_code` only takes effect at the next block.
+    pub fn active_spec_version(&self) -> u32 {
+        self.last_runtime_upgrade.get().unwrap_or_else(|| self.spec_version())
+    }
+
+    /// Get the version of the runtime logic the chain is running, or `None` if this binary does
+    /// not know it, in which case the chain runs the wasm code from `set_wasm_code`.
+    pub fn runtime_version(&self) -> Option<RuntimeVersion> {
+        let spec_version = self.spec_version();
+        T::VERSIONS.iter().find(|version| version.spec_version == spec_version).copied()
+    }
+
+    /// Get the wasm code of the runtime logic, if the chain switched to one with `set_wasm_code`.
+    pub fn code(&self) -> Option<Vec<u8>> {
+        self.code.get()
+    }
+
+    /// Record an event, so that users can see what happened during the current block.
+    pub fn deposit_event(&mut self, event: T::RuntimeEvent) {
+        let mut events = self.events();
+        events.push(event);
+        self.events.put(events);
+    }
+
+    /// Get the events deposited during the current block, in order.
+    pub fn events(&self) -> Vec<T::RuntimeEvent> {
+        self.events.get().unwrap_or_default()
+    }
+
+    /// Remove the events of the previous block. This is called at the start of each block.
+    pub fn reset_events(&mut self) {
+        self.events.take();
+    }
+
+    /// Record that the stored data is migrated to the current spec version.
+    /// Returns whether the spec version changed since the last call, in which case the migrations
+    /// of every pallet must run.
+    pub fn note_runtime_upgrade(&mut self) -> bool {
+        let current = self.spec_version();
+        let last = self.last_runtime_upgrade.get();
+        self.last_runtime_upgrade.put(current);
+        last.is_some_and(|last| last != current)
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Record that the chain runs the registered runtime version `spec_version`.
+    /// This function can only be called by `Root`, and the new version must be newer than the
+    /// current one. From the next block on, the migrations of every pallet bring the stored data
+    /// to the layout of the new version, and calls are dispatched with its native logic. The chain
+    /// stops running any wasm code set with `set_wasm_code`.
+    #[call_index(0)]
+    pub fn set_code(&mut self, origin: Origin<T::AccountId>, spec_version: u32) -> DispatchResult {
+        ensure_root(origin)?;
+        let version = T::VERSIONS
+            .iter()
+            .find(|version| version.spec_version == spec_version)
+            .ok_or("runtime version is not registered")?;
+        if version.spec_version <= self.spec_version() {
+            return Err("runtime version must be newer than the current one");
+        }
+        self.spec_version.put(version.spec_version);
+        self.code.take();
+        Ok(())
+    }
+
+    /// Switch the chain to the runtime logic compiled to wasm in `code`.
+    /// This function can only be called by `Root`, and the runtime in `code` must have the same
+    /// spec name and a newer spec version than the current one. Blocks are executed with `code`
+    /// from the next block on, unless this binary knows its version natively.
+    #[call_index(1)]
+    pub fn set_wasm_code(&mut self, origin: Origin<T::AccountId>, code: Vec<u8>) -> DispatchResult {
+        ensure_root(origin)?;
+        let (spec_name, spec_version) =
+            support::runtime_version(&code).ok_or("invalid runtime code")?;
+        let native = T::VERSIONS.last().expect("no runtime version registered");
+        if spec_name != native.spec_name {
+            return Err("runtime code has a different spec name");
+        }
+        if spec_version <= self.spec_version() {
+            return Err("runtime version must be newer than the current one");
+        }
+        self.spec_version.put(spec_version);
+        self.code.put(code);
+        Ok(())
+    }
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {
+    // Pin the chain to the version it starts on, which later binaries may no longer default to.
+    fn on_genesis(&mut self) {
+        let spec_version = self.spec_version();
+        self.spec_version.put(spec_version);
+        self.last_runtime_upgrade.put(spec_version);
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::support::{Origin, RuntimeVersion};
     use crate::system::{Config, Pallet};
 
     struct TestConfig;
@@ -56,6 +217,12 @@ mod test {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = u32;
+        const VERSIONS: &'static [RuntimeVersion] = &[
+            RuntimeVersion { spec_name: "test", spec_version: 1 },
+            RuntimeVersion { spec_name: "test", spec_version: 2 },
+            RuntimeVersion { spec_name: "test", spec_version: 3 },
+        ];
     }
 
     #[test]
@@ -68,12 +235,53 @@ mod test {
         s.inc_nonce(alice);
 
         assert_eq!(s.block_number(), 1);
-        assert_eq!(s.nonce.get(alice), Some(&1));
+        assert_eq!(s.nonce.get(alice), Some(1));
 
         s.inc_block_number();
         s.inc_nonce(alice);
 
         assert_eq!(s.block_number(), 2);
-        assert_eq!(s.nonce.get(alice), Some(&2));
+        assert_eq!(s.nonce.get(alice), Some(2));
+
+        s.deposit_event(1);
+        s.deposit_event(2);
+        assert_eq!(s.events(), vec![1, 2]);
+        s.reset_events();
+        assert!(s.events().is_empty());
+    }
+
+    #[test]
+    fn set_code() {
+        let mut s: Pallet<TestConfig> = Pallet::new();
+        assert_eq!(s.spec_version(), 3);
+
+        // A chain which started on the first version.
+        s.spec_version.put(1);
+        assert!(!s.note_runtime_upgrade());
+
+        assert_eq!(
+            s.set_code(Origin::Signed("alice".to_string()), 2),
+            Err("bad origin: expected root")
+        );
+        assert_eq!(s.set_code(Origin::Root, 4), Err("runtime version is not registered"));
+        assert_eq!(s.set_code(Origin::Root, 3), Ok(()));
+        assert_eq!(
+            s.set_code(Origin::Root, 3),
+            Err("runtime version must be newer than the current one")
+        );
+        assert_eq!(
+            s.runtime_version(),
+            Some(RuntimeVersion { spec_name: "test", spec_version: 3 })
+        );
+
+        // The upgrade is only noted once.
+        assert!(s.note_runtime_upgrade());
+        assert!(!s.note_runtime_upgrade());
+
+        assert_eq!(
+            s.set_wasm_code(Origin::Root, b"not wasm".to_vec()),
+            Err("invalid runtime code")
+        );
+        assert_eq!(s.code(), None);
     }
 }
@@ -0,0 +1,122 @@
+use crate::support::{
+	ensure_none, storage::StorageValue, DispatchResult, Hooks, OnRuntimeUpgrade, Origin, UnixTime,
+};
+
+pub trait Config: crate::system::Config {
+	/// The minimum time between two blocks, in milliseconds.
+	const MINIMUM_PERIOD: u64;
+}
+
+/// This is the Timestamp Module.
+/// It keeps the time of the current block, which the block author sets with an inherent.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The time of the current block, in milliseconds since the Unix epoch.
+	now: StorageValue<u64>,
+	// Whether the time was set in the current block.
+	did_update: StorageValue<bool>,
+	_config: core::marker::PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the timestamp module.
+	pub fn new() -> Self {
+		Self {
+			now: StorageValue::new("Timestamp", "Now"),
+			did_update: StorageValue::new("Timestamp", "DidUpdate"),
+			_config: core::marker::PhantomData,
+		}
+	}
+
+	/// The time of the current block, in milliseconds since the Unix epoch, or `None` if it was
+	/// never set. Every block sets it with the inherent, blocks without it are invalid.
+	pub fn now(&self) -> Option<u64> {
+		self.now.get()
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Set the time of the current block to `now`, in milliseconds since the Unix epoch.
+	/// This is an inherent, added by the block author at the start of the block. It can be set
+	/// once per block, at least `MINIMUM_PERIOD` after the time of the previous block.
+	#[call_index(0)]
+	pub fn set(&mut self, origin: Origin<T::AccountId>, now: u64) -> DispatchResult {
+		ensure_none(origin)?;
+		if self.did_update.exists() {
+			return Err("timestamp must be set only once in the block");
+		}
+		if self.now().is_some_and(|previous| now < previous.saturating_add(T::MINIMUM_PERIOD)) {
+			return Err("timestamp must increment by at least the minimum period");
+		}
+		self.now.put(now);
+		self.did_update.put(true);
+		Ok(())
+	}
+}
+
+impl<T: Config> UnixTime for Pallet<T> {
+	fn now() -> Option<u64> {
+		Self::new().now.get()
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// Every block must set its time, so that `now` never goes stale.
+	fn on_finalize(&mut self, _block_number: T::BlockNumber) -> DispatchResult {
+		self.did_update.take().map(|_| ()).ok_or("timestamp must be set in every block")
+	}
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, Pallet};
+	use crate::support::{Hooks, Origin, RuntimeVersion, UnixTime};
+	use crate::system;
+
+	struct TestConfig;
+	impl Config for TestConfig {
+		const MINIMUM_PERIOD: u64 = 3_000;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	#[test]
+	fn set_timestamp() {
+		let mut timestamp = Pallet::<TestConfig>::new();
+		assert_eq!(timestamp.now(), None);
+		assert_eq!(
+			timestamp.set(Origin::Signed("alice".to_string()), 10_000),
+			Err("bad origin: expected none")
+		);
+		assert_eq!(timestamp.set(Origin::None, 10_000), Ok(()));
+		assert_eq!(
+			timestamp.set(Origin::None, 20_000),
+			Err("timestamp must be set only once in the block")
+		);
+		assert_eq!(<Pallet<TestConfig> as UnixTime>::now(), Some(10_000));
+
+		assert_eq!(timestamp.on_finalize(1), Ok(()));
+		assert_eq!(timestamp.on_finalize(2), Err("timestamp must be set in every block"));
+		assert_eq!(
+			timestamp.set(Origin::None, 12_999),
+			Err("timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(timestamp.set(Origin::None, 13_000), Ok(()));
+		assert_eq!(timestamp.now(), Some(13_000));
+	}
+}
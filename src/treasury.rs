@@ -0,0 +1,470 @@
+use num::traits::{CheckedDiv, CheckedMul, Saturating, Zero};
+
+use crate::support::{
+	ensure_root,
+	storage::{StorageMap, StorageValue},
+	BalanceStatus, Currency, Decode, DeriveAccount, DispatchResult, Encode, Hooks,
+	OnRuntimeUpgrade, Origin, Reasons, ReservableCurrency,
+};
+
+pub trait Config: crate::system::Config<AccountId: DeriveAccount> {
+	/// The pallet holding the funds of accounts, usually `balances`. Balances are multiplied by
+	/// percentages for bonds and burns.
+	type Currency: ReservableCurrency<
+		Self::AccountId,
+		Balance: From<u32> + CheckedMul + CheckedDiv,
+	>;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<EventOf<Self>> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+	/// The percentage of the value of a proposal its proposer reserves as a bond.
+	const PROPOSAL_BOND: u32;
+	/// The minimum bond of a proposal.
+	const PROPOSAL_BOND_MINIMUM: BalanceOf<Self>;
+	/// The number of blocks between two payouts of the approved proposals.
+	const SPEND_PERIOD: Self::BlockNumber;
+	/// The percentage of the funds left in the pot which is burnt after each payout.
+	const BURN: u32;
+	/// The maximum number of approved proposals waiting for a payout.
+	const MAX_APPROVALS: u32;
+}
+
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// The index of a proposal.
+pub type ProposalIndex = u32;
+
+/// A proposal to spend funds of the treasury.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal<AccountId, Balance> {
+	/// The account which made the proposal.
+	pub proposer: AccountId,
+	/// The funds to pay.
+	pub value: Balance,
+	/// The account to pay.
+	pub beneficiary: AccountId,
+	/// The funds reserved from the proposer, which are lost if the proposal is rejected.
+	pub bond: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for Proposal<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.proposer.encode_to(dest);
+		self.value.encode_to(dest);
+		self.beneficiary.encode_to(dest);
+		self.bond.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Proposal<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			proposer: Decode::decode(input)?,
+			value: Decode::decode(input)?,
+			beneficiary: Decode::decode(input)?,
+			bond: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A proposal, for a runtime `T`.
+pub type ProposalOf<T> = Proposal<<T as crate::system::Config>::AccountId, BalanceOf<T>>;
+
+/// The events of this pallet, for a runtime `T`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId, BalanceOf<T>>;
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId, Balance> {
+	/// A proposal was made.
+	Proposed { proposal_index: ProposalIndex },
+	/// A proposal was rejected, and its bond given to the treasury.
+	Rejected { proposal_index: ProposalIndex, slashed: Balance },
+	/// The approved proposals are being paid, with the given funds.
+	Spending { budget_remaining: Balance },
+	/// A proposal was paid.
+	Awarded { proposal_index: ProposalIndex, award: Balance, account: AccountId },
+	/// Funds of the pot were burnt.
+	Burnt { burnt_funds: Balance },
+	/// Funds are left in the pot for the next spend period.
+	Rollover { rollover_balance: Balance },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl<AccountId: Encode, Balance: Encode> Encode for Event<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::Proposed { proposal_index } => (0u8, proposal_index).encode_to(dest),
+			Event::Rejected { proposal_index, slashed } => {
+				(1u8, (proposal_index, slashed)).encode_to(dest)
+			},
+			Event::Spending { budget_remaining } => (2u8, budget_remaining).encode_to(dest),
+			Event::Awarded { proposal_index, award, account } => {
+				(3u8, (proposal_index, award, account)).encode_to(dest)
+			},
+			Event::Burnt { burnt_funds } => (4u8, burnt_funds).encode_to(dest),
+			Event::Rollover { rollover_balance } => (5u8, rollover_balance).encode_to(dest),
+		}
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Event<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => Event::Proposed { proposal_index: Decode::decode(input)? },
+			1 => {
+				let (proposal_index, slashed) = Decode::decode(input)?;
+				Event::Rejected { proposal_index, slashed }
+			},
+			2 => Event::Spending { budget_remaining: Decode::decode(input)? },
+			3 => {
+				let (proposal_index, award, account) = Decode::decode(input)?;
+				Event::Awarded { proposal_index, award, account }
+			},
+			4 => Event::Burnt { burnt_funds: Decode::decode(input)? },
+			5 => Event::Rollover { rollover_balance: Decode::decode(input)? },
+			_ => return Err("invalid treasury event"),
+		})
+	}
+}
+
+/// This is the Treasury Module.
+/// It holds funds in a pot account, which no key controls, and spends them on proposals.
+///
+/// The runtime gives the pot a part of the transaction fees, and anyone can transfer funds to it.
+/// Accounts propose to pay some of these funds to a beneficiary, with a bond. Proposals are
+/// approved or rejected by `Root`, e.g. through a referendum, and rejected proposals lose their
+/// bond to the pot. Every spend period, the approved proposals are paid while the pot can afford
+/// them, and a part of the funds left is burnt.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The index of the next proposal.
+	proposal_count: StorageValue<ProposalIndex>,
+	// The proposals which were not paid nor rejected.
+	proposals: StorageMap<ProposalIndex, Proposal<T::AccountId, BalanceOf<T>>>,
+	// The approved proposals, in the order they are paid.
+	approvals: StorageValue<Vec<ProposalIndex>>,
+	// The block the approved proposals are paid at next.
+	next_spend: StorageValue<T::BlockNumber>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the treasury module.
+	pub fn new() -> Self {
+		Self {
+			proposal_count: StorageValue::new("Treasury", "ProposalCount"),
+			proposals: StorageMap::new("Treasury", "Proposals"),
+			approvals: StorageValue::new("Treasury", "Approvals"),
+			next_spend: StorageValue::new("Treasury", "NextSpend"),
+		}
+	}
+
+	/// The account holding the funds of the treasury.
+	pub fn account_id() -> T::AccountId {
+		T::AccountId::derive_account(b"treasury")
+	}
+
+	/// The funds the treasury can spend, keeping the pot alive.
+	pub fn pot(&self) -> BalanceOf<T> {
+		let currency = T::Currency::default();
+		let balance = currency.free_balance(&Self::account_id());
+		balance.saturating_sub(currency.minimum_balance())
+	}
+
+	/// The proposal `proposal_index`, if it was not paid nor rejected.
+	pub fn proposal(&self, proposal_index: ProposalIndex) -> Option<ProposalOf<T>> {
+		self.proposals.get(&proposal_index)
+	}
+
+	/// The approved proposals, in the order they are paid.
+	pub fn approvals(&self) -> Vec<ProposalIndex> {
+		self.approvals.get().unwrap_or_default()
+	}
+
+	fn deposit_event(&mut self, event: EventOf<T>) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+
+	/// `percent` percents of `value`.
+	fn percent_of(value: BalanceOf<T>, percent: u32) -> BalanceOf<T> {
+		// Multiplying first keeps the precision of small values, dividing first can't overflow.
+		match value.checked_mul(&BalanceOf::<T>::from(percent)) {
+			Some(value) => value.checked_div(&BalanceOf::<T>::from(100)),
+			None => value
+				.checked_div(&BalanceOf::<T>::from(100))
+				.and_then(|value| value.checked_mul(&BalanceOf::<T>::from(percent))),
+		}
+		.unwrap_or_else(BalanceOf::<T>::zero)
+	}
+
+	/// Pay the approved proposals the pot can afford, then burn a part of the funds left.
+	fn spend_funds(&mut self) {
+		let mut currency = T::Currency::default();
+		let pot = Self::account_id();
+		let mut budget_remaining = self.pot();
+		self.deposit_event(Event::Spending { budget_remaining });
+
+		let mut missed = Vec::new();
+		for proposal_index in self.approvals() {
+			let Some(proposal) = self.proposals.get(&proposal_index) else { continue };
+			if proposal.value > budget_remaining
+				|| currency.transfer(&pot, &proposal.beneficiary, proposal.value, true).is_err()
+			{
+				missed.push(proposal_index);
+				continue;
+			}
+			budget_remaining = budget_remaining.saturating_sub(proposal.value);
+			currency.unreserve(&proposal.proposer, proposal.bond);
+			self.proposals.remove(&proposal_index);
+			self.deposit_event(Event::Awarded {
+				proposal_index,
+				award: proposal.value,
+				account: proposal.beneficiary,
+			});
+		}
+		self.approvals.put(missed);
+
+		let burnt_funds = Self::percent_of(budget_remaining, T::BURN);
+		if !burnt_funds.is_zero()
+			&& currency.withdraw(&pot, burnt_funds, Reasons::Misc, true).is_ok()
+		{
+			budget_remaining = budget_remaining.saturating_sub(burnt_funds);
+			self.deposit_event(Event::Burnt { burnt_funds });
+		}
+		self.deposit_event(Event::Rollover { rollover_balance: budget_remaining });
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose to pay `value` from the treasury to `beneficiary`, reserving a bond from the
+	/// `caller` until the proposal is paid or rejected.
+	#[call_index(0)]
+	pub fn propose_spend(
+		&mut self,
+		caller: T::AccountId,
+		value: BalanceOf<T>,
+		beneficiary: T::AccountId,
+	) -> DispatchResult {
+		let mut currency = T::Currency::default();
+		if value < currency.minimum_balance() {
+			return Err("value is below the minimum balance");
+		}
+		let bond = Self::percent_of(value, T::PROPOSAL_BOND);
+		let bond = if bond < T::PROPOSAL_BOND_MINIMUM { T::PROPOSAL_BOND_MINIMUM } else { bond };
+		currency.reserve(&caller, bond)?;
+
+		let proposal_index = self.proposal_count.get().unwrap_or(0);
+		self.proposal_count.put(proposal_index + 1);
+		let proposal = Proposal { proposer: caller, value, beneficiary, bond };
+		self.proposals.insert(proposal_index, proposal);
+		self.deposit_event(Event::Proposed { proposal_index });
+		Ok(())
+	}
+
+	/// Reject the proposal `proposal_id`, giving its bond to the treasury.
+	#[call_index(1)]
+	pub fn reject_proposal(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		proposal_id: ProposalIndex,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		let proposal = self.proposals.get(&proposal_id).ok_or("proposal does not exist")?;
+		// The bond is moved first, so that the proposal is kept if this fails.
+		let (proposer, pot) = (&proposal.proposer, &Self::account_id());
+		let status = BalanceStatus::Free;
+		let missing =
+			T::Currency::default().repatriate_reserved(proposer, pot, proposal.bond, status)?;
+		let slashed = proposal.bond.saturating_sub(missing);
+
+		self.proposals.remove(&proposal_id);
+		let mut approvals = self.approvals();
+		approvals.retain(|index| *index != proposal_id);
+		self.approvals.put(approvals);
+		self.deposit_event(Event::Rejected { proposal_index: proposal_id, slashed });
+		Ok(())
+	}
+
+	/// Approve the proposal `proposal_id`, which is paid at the next spend period the treasury
+	/// can afford it.
+	#[call_index(2)]
+	pub fn approve_proposal(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		proposal_id: ProposalIndex,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		if self.proposals.get(&proposal_id).is_none() {
+			return Err("proposal does not exist");
+		}
+		let mut approvals = self.approvals();
+		if approvals.contains(&proposal_id) {
+			return Err("proposal is already approved");
+		}
+		if approvals.len() >= T::MAX_APPROVALS as usize {
+			return Err("too many approvals");
+		}
+		approvals.push(proposal_id);
+		self.approvals.put(approvals);
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// Pay the approved proposals every spend period, the first one ending a spend period after
+	/// the first block.
+	fn on_initialize(&mut self, now: T::BlockNumber) {
+		let next_spend = self.next_spend.get();
+		if next_spend.is_none_or(|next_spend| now >= next_spend) {
+			let mut next = now;
+			next += T::SPEND_PERIOD;
+			self.next_spend.put(next);
+			if next_spend.is_some() {
+				self.spend_funds();
+			}
+		}
+	}
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, EventOf, Pallet};
+	use crate::support::{testing, Hooks, Origin, RuntimeVersion};
+	use crate::{balances, system};
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type Currency = balances::Pallet<Self>;
+		type RuntimeEvent = EventOf<Self>;
+		const PROPOSAL_BOND: u32 = 5;
+		const PROPOSAL_BOND_MINIMUM: u128 = 5;
+		const SPEND_PERIOD: u32 = 2;
+		const BURN: u32 = 50;
+		const MAX_APPROVALS: u32 = 2;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 10;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = EventOf<Self>;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+	const BOB: u64 = 2;
+
+	fn setup() -> (Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance(&ALICE, 100);
+		balances.set_balance(&BOB, 14);
+		(Pallet::new(), balances)
+	}
+
+	fn events() -> Vec<EventOf<TestConfig>> {
+		system::Pallet::<TestConfig>::new().events()
+	}
+
+	#[test]
+	fn proposals() {
+		let (mut treasury, balances) = setup();
+
+		assert_eq!(
+			treasury.propose_spend(ALICE, 9, BOB),
+			Err("value is below the minimum balance")
+		);
+		assert_eq!(treasury.propose_spend(BOB, 300, ALICE), Err("Not enough funds."));
+		assert_eq!(treasury.propose_spend(ALICE, 10, BOB), Ok(()));
+		assert_eq!(treasury.propose_spend(ALICE, 200, BOB), Ok(()));
+		assert_eq!(treasury.propose_spend(ALICE, 30, BOB), Ok(()));
+		assert_eq!(balances.reserved_balance(&ALICE), 20);
+
+		assert_eq!(
+			treasury.approve_proposal(Origin::Signed(ALICE), 0),
+			Err("bad origin: expected root")
+		);
+		assert_eq!(treasury.approve_proposal(Origin::Root, 3), Err("proposal does not exist"));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 0), Ok(()));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 0), Err("proposal is already approved"));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 1), Ok(()));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 2), Err("too many approvals"));
+		assert_eq!(treasury.approvals(), vec![0, 1]);
+	}
+
+	#[test]
+	fn reject_proposal() {
+		let (mut treasury, mut balances) = setup();
+		let pot = Pallet::<TestConfig>::account_id();
+		assert_eq!(treasury.reject_proposal(Origin::Root, 0), Err("proposal does not exist"));
+		assert_eq!(treasury.propose_spend(ALICE, 10, BOB), Ok(()));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 0), Ok(()));
+
+		// The bond alone can't create the account of the pot, so the proposal is kept.
+		assert_eq!(
+			treasury.reject_proposal(Origin::Signed(ALICE), 0),
+			Err("bad origin: expected root")
+		);
+		assert_eq!(
+			treasury.reject_proposal(Origin::Root, 0),
+			Err("Balance too low to create the account.")
+		);
+		assert!(treasury.proposal(0).is_some());
+		assert_eq!(treasury.approvals(), vec![0]);
+		assert_eq!(balances.reserved_balance(&ALICE), 5);
+
+		balances.set_balance(&pot, 10);
+		assert_eq!(treasury.reject_proposal(Origin::Root, 0), Ok(()));
+		assert_eq!(treasury.proposal(0), None);
+		assert!(treasury.approvals().is_empty());
+		assert_eq!(balances.reserved_balance(&ALICE), 0);
+		assert_eq!(treasury.pot(), 5);
+		assert_eq!(events().last(), Some(&Event::Rejected { proposal_index: 0, slashed: 5 }));
+	}
+
+	#[test]
+	fn spend_funds() {
+		let (mut treasury, mut balances) = setup();
+		let pot = Pallet::<TestConfig>::account_id();
+		balances.set_balance(&pot, 110);
+		assert_eq!(treasury.propose_spend(ALICE, 150, BOB), Ok(()));
+		assert_eq!(treasury.propose_spend(ALICE, 40, BOB), Ok(()));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 0), Ok(()));
+		assert_eq!(treasury.approve_proposal(Origin::Root, 1), Ok(()));
+
+		// The first spend period ends at block 3.
+		treasury.on_initialize(1);
+		treasury.on_initialize(2);
+		assert_eq!(balances.balance(&BOB), 14);
+		treasury.on_initialize(3);
+		assert_eq!(balances.balance(&BOB), 54);
+		assert_eq!(treasury.approvals(), vec![0]);
+		assert_eq!(treasury.pot(), 30);
+
+		let events = events();
+		assert_eq!(
+			events[2..],
+			[
+				Event::Spending { budget_remaining: 100 },
+				Event::Awarded { proposal_index: 1, award: 40, account: BOB },
+				Event::Burnt { burnt_funds: 30 },
+				Event::Rollover { rollover_balance: 30 },
+			]
+		);
+		testing::assert_round_trip(events);
+	}
+}
@@ -0,0 +1,248 @@
+use crate::support::{
+	storage, Decode, DispatchResult, Dispatchable, Encode, Hooks, OnRuntimeUpgrade, Origin,
+};
+
+pub trait Config: crate::system::Config {
+	/// The call type of the runtime, whose calls can be batched.
+	type RuntimeCall: Dispatchable<Self::AccountId> + Encode + Decode;
+	/// The event type of the runtime, which must include the events of this pallet.
+	type RuntimeEvent: From<Event> + Into<<Self as crate::system::Config>::RuntimeEvent>;
+}
+
+/// The events of this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	/// A `batch` stopped at the call at `index`, which failed with `error`.
+	BatchInterrupted { index: u32, error: String },
+	/// Every call of a batch succeeded.
+	BatchCompleted,
+	/// A `force_batch` dispatched all of its calls, but some of them failed.
+	BatchCompletedWithErrors,
+	/// A call of a `force_batch` succeeded.
+	ItemCompleted,
+	/// A call of a `force_batch` failed with `error`.
+	ItemFailed { error: String },
+}
+
+/// Each event is encoded as the index of its variant, followed by its fields.
+impl Encode for Event {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Event::BatchInterrupted { index, error } => (0u8, (index, error)).encode_to(dest),
+			Event::BatchCompleted => 1u8.encode_to(dest),
+			Event::BatchCompletedWithErrors => 2u8.encode_to(dest),
+			Event::ItemCompleted => 3u8.encode_to(dest),
+			Event::ItemFailed { error } => (4u8, error).encode_to(dest),
+		}
+	}
+}
+
+impl Decode for Event {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(match u8::decode(input)? {
+			0 => {
+				let (index, error) = Decode::decode(input)?;
+				Event::BatchInterrupted { index, error }
+			},
+			1 => Event::BatchCompleted,
+			2 => Event::BatchCompletedWithErrors,
+			3 => Event::ItemCompleted,
+			4 => Event::ItemFailed { error: Decode::decode(input)? },
+			_ => return Err("invalid utility event"),
+		})
+	}
+}
+
+/// This is the Utility Module.
+/// It dispatches several calls at once, with the origin of the batch.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	_config: core::marker::PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the utility module.
+	pub fn new() -> Self {
+		Self { _config: core::marker::PhantomData }
+	}
+
+	fn deposit_event(&mut self, event: Event) {
+		let event = <T as Config>::RuntimeEvent::from(event);
+		crate::system::Pallet::<T>::new().deposit_event(event.into());
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `calls` in order, stopping at the first one which fails. The calls before it are
+	/// kept, the changes of the failing one are reverted, and its failure is recorded in a
+	/// `BatchInterrupted` event rather than returned.
+	#[call_index(0)]
+	pub fn batch(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		for (index, call) in calls.into_iter().enumerate() {
+			if let Err(error) = storage::with_transaction(|| call.dispatch(origin.clone())) {
+				let error = error.to_string();
+				self.deposit_event(Event::BatchInterrupted { index: index as u32, error });
+				return Ok(());
+			}
+		}
+		self.deposit_event(Event::BatchCompleted);
+		Ok(())
+	}
+
+	/// Dispatch `calls` in order, all or nothing: if one of them fails, the changes of the calls
+	/// before it are reverted, and its error is returned.
+	#[call_index(1)]
+	pub fn batch_all(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		storage::with_transaction(|| {
+			calls.into_iter().try_for_each(|call| call.dispatch(origin.clone()))
+		})?;
+		self.deposit_event(Event::BatchCompleted);
+		Ok(())
+	}
+
+	/// Dispatch all of the `calls` in order, even if some of them fail. The changes of each failing
+	/// call are reverted, and the result of each call is recorded in an `ItemCompleted` or
+	/// `ItemFailed` event.
+	#[call_index(2)]
+	pub fn force_batch(
+		&mut self,
+		origin: Origin<T::AccountId>,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult {
+		let mut failed = false;
+		for call in calls {
+			match storage::with_transaction(|| call.dispatch(origin.clone())) {
+				Ok(()) => self.deposit_event(Event::ItemCompleted),
+				Err(error) => {
+					failed = true;
+					self.deposit_event(Event::ItemFailed { error: error.to_string() });
+				},
+			}
+		}
+		if failed {
+			self.deposit_event(Event::BatchCompletedWithErrors);
+		} else {
+			self.deposit_event(Event::BatchCompleted);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Event, Pallet};
+	use crate::support::{
+		testing::{self, SignedOrigin},
+		Origin, RuntimeVersion,
+	};
+	use crate::system;
+
+	type TestCall = testing::TestCall<SignedOrigin>;
+
+	struct TestConfig;
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		type RuntimeEvent = Event;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = u64;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = Event;
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	const ALICE: u64 = 1;
+
+	fn calls(values: &[u32]) -> Vec<TestCall> {
+		values.iter().copied().map(TestCall::new).collect()
+	}
+
+	fn dispatched() -> Vec<u32> {
+		testing::dispatched().into_iter().map(|(value, _)| value).collect()
+	}
+
+	fn events() -> Vec<Event> {
+		system::Pallet::<TestConfig>::new().events()
+	}
+
+	#[test]
+	fn batch() {
+		let mut utility = Pallet::<TestConfig>::new();
+		let alice = || Origin::Signed(ALICE);
+
+		assert_eq!(utility.batch(alice(), calls(&[1, 0, 2])), Ok(()));
+		assert_eq!(dispatched(), vec![1]);
+		assert_eq!(utility.batch(Origin::Root, calls(&[3])), Ok(()));
+		assert_eq!(utility.batch(alice(), calls(&[4])), Ok(()));
+		assert_eq!(dispatched(), vec![1, 4]);
+		assert_eq!(
+			events(),
+			vec![
+				Event::BatchInterrupted { index: 1, error: "zero".to_string() },
+				Event::BatchInterrupted {
+					index: 0,
+					error: "bad origin: expected signed".to_string(),
+				},
+				Event::BatchCompleted,
+			]
+		);
+	}
+
+	#[test]
+	fn batch_all() {
+		let mut utility = Pallet::<TestConfig>::new();
+		let alice = || Origin::Signed(ALICE);
+
+		assert_eq!(utility.batch_all(alice(), calls(&[1, 2, 0])), Err("zero"));
+		assert_eq!(
+			utility.batch_all(Origin::Root, calls(&[1])),
+			Err("bad origin: expected signed")
+		);
+		assert_eq!(dispatched(), Vec::<u32>::new());
+		assert!(events().is_empty());
+		assert_eq!(utility.batch_all(alice(), calls(&[1, 2])), Ok(()));
+		assert_eq!(dispatched(), vec![1, 2]);
+		assert_eq!(events(), vec![Event::BatchCompleted]);
+	}
+
+	#[test]
+	fn force_batch() {
+		let mut utility = Pallet::<TestConfig>::new();
+
+		assert_eq!(utility.force_batch(Origin::Signed(ALICE), calls(&[0, 1, 0, 2])), Ok(()));
+		assert_eq!(dispatched(), vec![1, 2]);
+		let failed = || Event::ItemFailed { error: "zero".to_string() };
+		assert_eq!(
+			events(),
+			vec![
+				failed(),
+				Event::ItemCompleted,
+				failed(),
+				Event::ItemCompleted,
+				Event::BatchCompletedWithErrors,
+			]
+		);
+		testing::assert_round_trip(events());
+	}
+}
@@ -0,0 +1,346 @@
+use num::traits::{CheckedDiv, CheckedMul, One, Saturating, Zero};
+
+use crate::support::{
+	storage::StorageMap, Currency, Decode, DispatchResult, Encode, Hooks, LockIdentifier,
+	LockableCurrency, OnRuntimeUpgrade, Reasons,
+};
+
+/// The name of the lock this pallet sets on vesting accounts.
+pub const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: crate::system::Config {
+	/// The pallet holding the funds of accounts, usually `balances`. Block numbers are converted
+	/// to its balance type to compute how much is unlocked.
+	type Currency: LockableCurrency<
+		Self::AccountId,
+		Balance: From<Self::BlockNumber> + CheckedMul + CheckedDiv + One,
+	>;
+	/// The minimum amount which can be transferred with `vested_transfer`.
+	const MIN_VESTED_TRANSFER: BalanceOf<Self>;
+	/// The maximum number of vesting schedules of an account.
+	const MAX_VESTING_SCHEDULES: u32;
+}
+
+/// The type of the funds of an account, in the currency of the pallet.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as crate::system::Config>::AccountId>>::Balance;
+
+/// A vesting schedule: `locked` funds, of which `per_block` are unlocked at each block from
+/// `starting_block` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	pub locked: Balance,
+	pub per_block: Balance,
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+	Balance:
+		From<BlockNumber> + CheckedMul + CheckedDiv + One + Saturating + Zero + Copy + PartialOrd,
+	BlockNumber: Copy,
+{
+	/// Whether the schedule unlocks its funds at some point.
+	pub fn is_valid(&self) -> bool {
+		!self.locked.is_zero() && !self.per_block.is_zero()
+	}
+
+	/// The funds still locked at the block `now`.
+	pub fn locked_at(&self, now: BlockNumber) -> Balance {
+		let elapsed = Balance::from(now).saturating_sub(Balance::from(self.starting_block));
+		let unlocked = self.per_block.checked_mul(&elapsed).unwrap_or(self.locked);
+		self.locked.saturating_sub(unlocked)
+	}
+
+	/// The block at which all the funds are unlocked, as a balance.
+	fn ending_block(&self) -> Balance {
+		let duration = self.locked.checked_div(&self.per_block).unwrap_or_else(Balance::zero);
+		// The last block unlocks what is left, if `per_block` does not divide `locked`.
+		let duration = match self.per_block.checked_mul(&duration) {
+			Some(unlocked) if unlocked >= self.locked => duration,
+			_ => duration.saturating_add(Balance::one()),
+		};
+		Balance::from(self.starting_block).saturating_add(duration)
+	}
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for VestingInfo<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.locked.encode_to(dest);
+		self.per_block.encode_to(dest);
+		self.starting_block.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for VestingInfo<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Self {
+			locked: Decode::decode(input)?,
+			per_block: Decode::decode(input)?,
+			starting_block: Decode::decode(input)?,
+		})
+	}
+}
+
+/// A vesting schedule, for a runtime `T`.
+pub type VestingInfoOf<T> = VestingInfo<BalanceOf<T>, <T as crate::system::Config>::BlockNumber>;
+
+/// This is the Vesting Module.
+/// It locks funds of accounts, and unlocks them linearly over blocks.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+	// The vesting schedules of each account. Accounts without any are not stored.
+	vesting: StorageMap<T::AccountId, Vec<VestingInfoOf<T>>>,
+	// The pallet holding the vesting funds.
+	currency: T::Currency,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the vesting module.
+	pub fn new() -> Self {
+		Self { vesting: StorageMap::new("Vesting", "Vesting"), currency: T::Currency::default() }
+	}
+
+	/// Get the vesting schedules of `who`.
+	pub fn vesting(&self, who: &T::AccountId) -> Vec<VestingInfoOf<T>> {
+		self.vesting.get(who).unwrap_or_default()
+	}
+
+	/// Get the funds of `who` which are still locked by its vesting schedules at the current
+	/// block, or `None` if it has no vesting schedule.
+	pub fn vesting_balance(&self, who: &T::AccountId) -> Option<BalanceOf<T>> {
+		let now = crate::system::Pallet::<T>::new().block_number();
+		let schedules = self.vesting.get(who)?;
+		Some(
+			schedules
+				.iter()
+				.fold(BalanceOf::<T>::zero(), |total, s| total.saturating_add(s.locked_at(now))),
+		)
+	}
+
+	/// Store the schedules of `who` which still lock funds at the current block, and lock these
+	/// funds.
+	fn write_vesting(&mut self, who: &T::AccountId, mut schedules: Vec<VestingInfoOf<T>>) {
+		let now = crate::system::Pallet::<T>::new().block_number();
+		schedules.retain(|schedule| !schedule.locked_at(now).is_zero());
+		let locked = schedules
+			.iter()
+			.fold(BalanceOf::<T>::zero(), |total, s| total.saturating_add(s.locked_at(now)));
+		if schedules.is_empty() {
+			self.vesting.remove(who);
+			self.currency.remove_lock(VESTING_ID, who);
+		} else {
+			self.vesting.insert(who.clone(), schedules);
+			self.currency.set_lock(VESTING_ID, who, locked, Reasons::Misc);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Unlock the funds of `caller` which vested until the current block.
+	#[call_index(0)]
+	pub fn vest(&mut self, caller: T::AccountId) -> DispatchResult {
+		let schedules = self.vesting.get(&caller).ok_or("account has no vesting schedule")?;
+		self.write_vesting(&caller, schedules);
+		Ok(())
+	}
+
+	/// Transfer `schedule.locked` from `caller` to `target`, locked by the vesting `schedule`.
+	#[call_index(1)]
+	pub fn vested_transfer(
+		&mut self,
+		caller: T::AccountId,
+		target: T::AccountId,
+		schedule: VestingInfoOf<T>,
+	) -> DispatchResult {
+		if !schedule.is_valid() {
+			return Err("invalid vesting schedule");
+		}
+		if schedule.locked < T::MIN_VESTED_TRANSFER {
+			return Err("amount vested is too low");
+		}
+		let mut schedules = self.vesting(&target);
+		if schedules.len() >= T::MAX_VESTING_SCHEDULES as usize {
+			return Err("too many vesting schedules");
+		}
+		self.currency.transfer(&caller, &target, schedule.locked, false)?;
+		schedules.push(schedule);
+		self.write_vesting(&target, schedules);
+		Ok(())
+	}
+
+	/// Merge the vesting schedules of `caller` at indices `index1` and `index2` into one, which
+	/// starts now and ends when the last of them ends.
+	#[call_index(2)]
+	pub fn merge_schedules(
+		&mut self,
+		caller: T::AccountId,
+		index1: u32,
+		index2: u32,
+	) -> DispatchResult {
+		let (index1, index2) = (index1 as usize, index2 as usize);
+		let mut schedules = self.vesting.get(&caller).ok_or("account has no vesting schedule")?;
+		if index1 == index2 || index1.max(index2) >= schedules.len() {
+			return Err("invalid schedule index");
+		}
+		let second = schedules.remove(index1.max(index2));
+		let first = schedules.remove(index1.min(index2));
+
+		let now = crate::system::Pallet::<T>::new().block_number();
+		let locked = first.locked_at(now).saturating_add(second.locked_at(now));
+		if !locked.is_zero() {
+			// The merged schedule starts at the latest of now and both starts.
+			let starting_block = [first.starting_block, second.starting_block].into_iter().fold(
+				now,
+				|latest, start| {
+					if BalanceOf::<T>::from(start) > BalanceOf::<T>::from(latest) {
+						start
+					} else {
+						latest
+					}
+				},
+			);
+			let ending_block = if first.ending_block() > second.ending_block() {
+				first.ending_block()
+			} else {
+				second.ending_block()
+			};
+			let duration = ending_block.saturating_sub(BalanceOf::<T>::from(starting_block));
+			let per_block = match locked.checked_div(&duration) {
+				Some(per_block) if !per_block.is_zero() => per_block,
+				Some(_) => BalanceOf::<T>::one(),
+				None => locked,
+			};
+			schedules.push(VestingInfo { locked, per_block, starting_block });
+		}
+		self.write_vesting(&caller, schedules);
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{Config, Pallet, VestingInfo, VESTING_ID};
+	use crate::support::RuntimeVersion;
+	use crate::{balances, system};
+
+	struct TestConfig;
+	impl Config for TestConfig {
+		type Currency = balances::Pallet<Self>;
+		const MIN_VESTED_TRANSFER: u128 = 10;
+		const MAX_VESTING_SCHEDULES: u32 = 2;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl system::Config for TestConfig {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		const VERSIONS: &'static [RuntimeVersion] =
+			&[RuntimeVersion { spec_name: "test", spec_version: 1 }];
+	}
+
+	fn run_to_block(n: u32) {
+		let mut system = system::Pallet::<TestConfig>::new();
+		while system.block_number() < n {
+			system.inc_block_number();
+		}
+	}
+
+	fn schedule(locked: u128, per_block: u128, starting_block: u32) -> VestingInfo<u128, u32> {
+		VestingInfo { locked, per_block, starting_block }
+	}
+
+	#[test]
+	fn vested_transfer() {
+		let mut vesting = Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		balances.set_balance(&alice, 1000);
+
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(100, 0, 0)),
+			Err("invalid vesting schedule")
+		);
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(9, 1, 0)),
+			Err("amount vested is too low")
+		);
+		// 100 unlocked over blocks 10 to 20.
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(100, 10, 10)),
+			Ok(())
+		);
+		assert_eq!(balances.balance(&bob), 100);
+		assert_eq!(balances.usable_balance(&bob, balances::Reasons::Misc), 0);
+		assert_eq!(balances.transfer(bob.clone(), alice.clone(), 1), Err("Balance is locked."));
+
+		run_to_block(13);
+		assert_eq!(vesting.vesting_balance(&bob), Some(70));
+		// The lock only changes when vesting.
+		assert_eq!(balances.usable_balance(&bob, balances::Reasons::Misc), 0);
+		assert_eq!(vesting.vest(bob.clone()), Ok(()));
+		assert_eq!(balances.usable_balance(&bob, balances::Reasons::Misc), 30);
+		assert_eq!(balances.transfer(bob.clone(), alice.clone(), 30), Ok(()));
+
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(10, 1, 20)),
+			Ok(())
+		);
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(10, 1, 20)),
+			Err("too many vesting schedules")
+		);
+
+		// Once everything vested, the schedules and the lock are removed.
+		run_to_block(30);
+		assert_eq!(vesting.vest(bob.clone()), Ok(()));
+		assert!(vesting.vesting(&bob).is_empty());
+		assert!(balances.locks(&bob).iter().all(|lock| lock.id != VESTING_ID));
+		assert_eq!(vesting.vest(bob), Err("account has no vesting schedule"));
+	}
+
+	#[test]
+	fn merge_schedules() {
+		let mut vesting = Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		balances.set_balance(&alice, 1000);
+
+		// 100 unlocked over blocks 0 to 10, and 200 over blocks 5 to 25.
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(100, 10, 0)),
+			Ok(())
+		);
+		assert_eq!(
+			vesting.vested_transfer(alice.clone(), bob.clone(), schedule(200, 10, 5)),
+			Ok(())
+		);
+		assert_eq!(vesting.merge_schedules(bob.clone(), 0, 0), Err("invalid schedule index"));
+		assert_eq!(vesting.merge_schedules(bob.clone(), 0, 2), Err("invalid schedule index"));
+
+		// At block 5, 50 and 200 are locked: they are now unlocked over blocks 5 to 25.
+		run_to_block(5);
+		assert_eq!(vesting.merge_schedules(bob.clone(), 1, 0), Ok(()));
+		assert_eq!(vesting.vesting(&bob), vec![schedule(250, 12, 5)]);
+		assert_eq!(vesting.vesting_balance(&bob), Some(250));
+		assert_eq!(balances.usable_balance(&bob, balances::Reasons::Misc), 50);
+
+		run_to_block(26);
+		assert_eq!(vesting.vesting_balance(&bob), Some(0));
+	}
+}